@@ -1,6 +1,6 @@
 use std::hash::{Hash, Hasher};
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Children {
     pub child_bits: u8,
 }
@@ -1,24 +1,36 @@
 use super::{Children, HashedVolumeNode};
-use crate::volume::{CubicVolume, DensityVolume, IsVolume, VolumePosition};
+use crate::volume::{CubicVolume, DensityVolume, IsVolume, VolumeDimensions, VolumePosition};
 use std::{
     collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
+    hash::{BuildHasher, BuildHasherDefault, Hash, Hasher},
 };
 
 pub type HashedVolume = CubicVolume<HashedVolumeNode>;
 
 impl From<&DensityVolume> for HashedVolume {
     fn from(src_density_volume: &DensityVolume) -> Self {
-        let old_dimensions = src_density_volume.get_dimensions();
-        let new_side_length = old_dimensions.0 / 2;
-
-        println!("new side length: {}", new_side_length);
-
-        let mut hashed_volume = HashedVolume::new(src_density_volume.depth - 1);
+        HashedVolume::from_density_volume_with_hasher(
+            src_density_volume,
+            &BuildHasherDefault::<DefaultHasher>::default(),
+        )
+    }
+}
 
-        for y in 0..new_side_length {
-            for z in 0..new_side_length {
-                for x in 0..new_side_length {
+impl HashedVolume {
+    pub fn from_density_volume_with_hasher(
+        src_density_volume: &DensityVolume,
+        hash_builder: &impl BuildHasher,
+    ) -> HashedVolume {
+        let mut hashed_volume = HashedVolume::padded(
+            src_density_volume.depth - 1,
+            half_dimensions(src_density_volume.get_dimensions()),
+        );
+        let new_dimensions = hashed_volume.get_dimensions();
+
+        //Source positions past the dimensions are padding and read as empty
+        for y in 0..new_dimensions.1 {
+            for z in 0..new_dimensions.2 {
+                for x in 0..new_dimensions.0 {
                     let x_src = x * 2;
                     let y_src = y * 2;
                     let z_src = z * 2;
@@ -36,32 +48,36 @@ impl From<&DensityVolume> for HashedVolume {
                         *src_density_volume.get((x_src + 1, y_src + 1, z_src + 1)),
                     );
 
-                    let mut hasher = DefaultHasher::new();
-                    children.hash(&mut hasher);
-
-                    let mut new_hashnode = hashed_volume.get_mut((x, y, z));
+                    let new_hashnode = hashed_volume.get_mut((x, y, z));
                     new_hashnode.children = children;
-                    new_hashnode.hash = hasher.finish();
+                    new_hashnode.hash = hash_builder.hash_one(children);
                 }
             }
         }
 
         hashed_volume
     }
-}
 
-impl HashedVolume {
     pub fn from_hashed_volume(src_hashed_volume: &HashedVolume) -> HashedVolume {
-        let old_dimensions = src_hashed_volume.get_dimensions();
-        let new_side_length = old_dimensions.0 / 2;
-
-        println!("new side length: {}", new_side_length);
-
-        let mut new_hashed_volume = HashedVolume::new(src_hashed_volume.depth - 1);
+        HashedVolume::from_hashed_volume_with_hasher(
+            src_hashed_volume,
+            &BuildHasherDefault::<DefaultHasher>::default(),
+        )
+    }
 
-        for y in 0..new_side_length {
-            for z in 0..new_side_length {
-                for x in 0..new_side_length {
+    pub fn from_hashed_volume_with_hasher(
+        src_hashed_volume: &HashedVolume,
+        hash_builder: &impl BuildHasher,
+    ) -> HashedVolume {
+        let mut new_hashed_volume = HashedVolume::padded(
+            src_hashed_volume.depth - 1,
+            half_dimensions(src_hashed_volume.get_dimensions()),
+        );
+        let new_dimensions = new_hashed_volume.get_dimensions();
+
+        for y in 0..new_dimensions.1 {
+            for z in 0..new_dimensions.2 {
+                for x in 0..new_dimensions.0 {
                     let x_src = x * 2;
                     let y_src = y * 2;
                     let z_src = z * 2;
@@ -85,17 +101,18 @@ impl HashedVolume {
                     children.set(6, node6.children.have_occupied_children());
                     children.set(7, node7.children.have_occupied_children());
 
-                    let mut hasher = DefaultHasher::new();
-                    node0.hash.hash(&mut hasher);
-                    node1.hash.hash(&mut hasher);
-                    node2.hash.hash(&mut hasher);
-                    node3.hash.hash(&mut hasher);
-                    node4.hash.hash(&mut hasher);
-                    node5.hash.hash(&mut hasher);
-                    node6.hash.hash(&mut hasher);
-                    node7.hash.hash(&mut hasher);
-
-                    let mut new_hashnode = new_hashed_volume.get_mut((x, y, z));
+                    //Empty children all hash the same, whether they are padding or not
+                    let mut hasher = hash_builder.build_hasher();
+                    for node in [node0, node1, node2, node3, node4, node5, node6, node7] {
+                        let hash = if node.children.have_occupied_children() {
+                            node.hash
+                        } else {
+                            0
+                        };
+                        hash.hash(&mut hasher);
+                    }
+
+                    let new_hashnode = new_hashed_volume.get_mut((x, y, z));
                     new_hashnode.children = children;
                     new_hashnode.hash = hasher.finish();
                 }
@@ -118,3 +135,12 @@ impl HashedVolume {
         ]
     }
 }
+
+/// Dimensions of the layer above, every node covers two voxels along each axis.
+fn half_dimensions(dimensions: VolumeDimensions) -> VolumeDimensions {
+    (
+        dimensions.0.div_ceil(2),
+        dimensions.1.div_ceil(2),
+        dimensions.2.div_ceil(2),
+    )
+}
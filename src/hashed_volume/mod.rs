@@ -1,8 +1,9 @@
 mod children;
 pub use children::Children;
 
+#[allow(clippy::module_inception)]
 mod hashed_volume;
 pub use hashed_volume::HashedVolume;
 
 mod hashed_volume_node;
-pub use hashed_volume_node::HashedVolumeNode;
\ No newline at end of file
+pub use hashed_volume_node::HashedVolumeNode;
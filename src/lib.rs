@@ -0,0 +1,13 @@
+pub mod hashed_volume;
+pub mod svdag;
+pub mod volume;
+
+pub use crate::svdag::{
+    AttributedSvdag, BoxBrush, Brush, ChunkPosition, CompactReport, Face, FaceMask, MaterialSvdag,
+    MaterialSvdagBuilder, MeshVoxelizer, NodeOrder, OccupiedNodes, OccupiedVoxels, PointCloud,
+    PointCloudImporter, PointerFormat, SphereBrush, SurfaceVoxels, Svdag, SvdagBrush, SvdagBuilder,
+    SvdagEditor, SvdagError, SvdagHandle, SvdagHit, SvdagMesher, SvdagPool, SvdagRead, SvdagRef,
+    SvdagStreamBuilder, SvdagWorld, TriangleMesh, VoxFile, VoxModel, WorldHit, WorldPosition,
+};
+pub use hashed_volume::HashedVolume;
+pub use volume::{CubicVolume, DensityVolume, IsVolume};
@@ -1,11 +1,5 @@
-mod svdag;
-use svdag::Svdag;
-
-mod hashed_volume;
-
-mod volume;
-use std::{fs::File, io::Write, mem};
-use volume::{CubicVolume, IsVolume};
+use std::{fs::File, io::BufWriter};
+use svdag::{CubicVolume, IsVolume, Svdag, SvdagRead};
 
 fn main() {
     let mut volume = CubicVolume::new(3);
@@ -36,25 +30,31 @@ fn main() {
         println!("index: {}, node: {:?}", index, *node);
     }
 
+    let mut occupied_count = 0;
+    for position in svdag.iter_occupied() {
+        occupied_count += 1;
+
+        if !*volume.get(position) {
+            println!("\tposition: {:?} is empty in the volume", position);
+        }
+    }
+
+    let mut volume_count = 0;
     for x in 0..volume_dimensions.0 {
         for y in 0..volume_dimensions.1 {
             for z in 0..volume_dimensions.2 {
-                let position = (x, y, z);
-
-                let svdag_value = svdag.get(position);
-
-                if *volume.get(position) != svdag_value {
-                    println!(
-                        "\tposition: {:?}, volume value: {}, svdag value: {}",
-                        position,
-                        volume.get(position),
-                        svdag_value,
-                    );
-                }
+                volume_count += *volume.get((x, y, z)) as usize;
             }
         }
     }
 
+    if occupied_count != volume_count {
+        println!(
+            "\tvolume has {} occupied voxels, svdag has {}",
+            volume_count, occupied_count
+        );
+    }
+
     let array_size = volume_dimensions.0 * volume_dimensions.1 * volume_dimensions.2 / 8;
     let svdag_size = svdag.nodes.len() * 2;
 
@@ -65,15 +65,6 @@ fn main() {
         array_size as f32 / svdag_size as f32 * 100.0
     );
 
-    unsafe {
-        let nodes_binary = std::slice::from_raw_parts(
-            svdag.nodes.as_ptr() as *const u8,
-            svdag.nodes.len() * mem::size_of::<i16>(),
-        );
-
-        let mut file = File::create("svdag.bin").unwrap();
-        // Write a slice of bytes to the file
-        file.write_all(&[svdag.depth]).unwrap();
-        file.write_all(nodes_binary).unwrap();
-    }
+    let file = File::create("svdag.bin").unwrap();
+    svdag.write_to(BufWriter::new(file)).unwrap();
 }
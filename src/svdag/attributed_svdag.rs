@@ -0,0 +1,182 @@
+use super::{svdag_node_table::child_origin, Svdag, SvdagBuilder, SvdagError, SvdagRead};
+use crate::volume::{CubicVolume, DensityVolume, IsVolume, VolumePosition};
+use std::collections::HashMap;
+
+/// Geometry graph plus per-voxel attributes stored apart from it.
+///
+/// Attributes are kept in a flat array in the order the occupied voxels are visited (child index
+/// order at every level). A voxel's attribute index is the number of occupied voxels before it,
+/// which is summed up from `voxel_counts` on the way down. Because attributes never enter the
+/// geometry, subtrees with the same shape are still shared when their attributes differ.
+#[derive(Clone, Debug)]
+pub struct AttributedSvdag<T> {
+    pub geometry: Svdag,
+    /// Number of occupied voxels under each reachable node, keyed by its index in `geometry.nodes`.
+    pub voxel_counts: HashMap<usize, u64>,
+    pub attributes: Vec<T>,
+}
+
+impl<T> AttributedSvdag<T>
+where
+    T: Clone,
+{
+    /// Builds the graph from a volume where `None` marks empty voxels.
+    pub fn from_volume(volume: &CubicVolume<Option<T>>) -> Result<AttributedSvdag<T>, SvdagError> {
+        let mut density_volume = DensityVolume::with_dimensions(volume.get_dimensions());
+        for index in 0..volume.get_element_count() {
+            *density_volume.get_mut(index) = volume.get(index).is_some();
+        }
+
+        let geometry = SvdagBuilder::new()
+            .create_layers(&density_volume)
+            .create_graph()?
+            .finish();
+
+        let mut voxel_counts = HashMap::new();
+        count_voxels(&geometry, &mut voxel_counts, 0, geometry.depth);
+
+        let mut attributed_svdag = AttributedSvdag {
+            geometry,
+            voxel_counts,
+            attributes: Vec::new(),
+        };
+        attributed_svdag.collect_attributes(volume, 0, attributed_svdag.geometry.depth, (0, 0, 0));
+
+        Ok(attributed_svdag)
+    }
+
+    /// Pairs a graph with attributes already in its voxel order.
+    pub(crate) fn from_geometry(geometry: Svdag, attributes: Vec<T>) -> AttributedSvdag<T> {
+        let mut voxel_counts = HashMap::new();
+        count_voxels(&geometry, &mut voxel_counts, 0, geometry.depth);
+
+        AttributedSvdag {
+            geometry,
+            voxel_counts,
+            attributes,
+        }
+    }
+
+    pub fn get(&self, target_position: VolumePosition) -> Option<bool> {
+        self.geometry.get(target_position)
+    }
+
+    /// Returns the attribute of the voxel at `target_position`, `None` if it's empty or outside the volume.
+    pub fn get_attribute(&self, target_position: VolumePosition) -> Option<&T> {
+        self.attribute_index(target_position)
+            .and_then(|attribute_index| self.attributes.get(attribute_index))
+    }
+
+    /// Index into `attributes` of the voxel at `target_position`, if it's occupied.
+    pub fn attribute_index(&self, target_position: VolumePosition) -> Option<usize> {
+        let depth = self.geometry.depth;
+        if depth == 0 || depth as u32 >= usize::BITS {
+            return None;
+        }
+
+        let dimensions = self.geometry.dimensions();
+        if target_position.0 >= dimensions.0
+            || target_position.1 >= dimensions.1
+            || target_position.2 >= dimensions.2
+        {
+            return None;
+        }
+
+        let pointer_word_count = self.geometry.pointer_format.word_count();
+        let mut node_index = 0;
+        let mut attribute_index = 0;
+
+        for level in (0..depth).rev() {
+            let child_index = (target_position.0 >> level & 1) << 2
+                | (target_position.1 >> level & 1) << 1
+                | (target_position.2 >> level & 1);
+
+            let node = self.geometry.nodes.get(node_index)?.node();
+            if !node.children.get(child_index) {
+                return None;
+            }
+
+            //Occupied voxels of the leaf come before this one in child index order
+            if level == 0 {
+                return Some(attribute_index + node.children.get_n(child_index));
+            }
+
+            //Skip over the voxels of all occupied siblings before this child
+            for child_number in 0..node.children.get_n(child_index) {
+                let sibling_index = self
+                    .geometry
+                    .read_pointer(node_index + 1 + child_number * pointer_word_count)?;
+                attribute_index += *self.voxel_counts.get(&sibling_index)? as usize;
+            }
+
+            let child_pointer_index =
+                node_index + 1 + node.children.get_n(child_index) * pointer_word_count;
+            node_index = self.geometry.read_pointer(child_pointer_index)?;
+        }
+
+        None
+    }
+
+    fn collect_attributes(
+        &mut self,
+        volume: &CubicVolume<Option<T>>,
+        node_index: usize,
+        height: u8,
+        origin: VolumePosition,
+    ) {
+        let node = self.geometry.nodes[node_index].node();
+        let half_size = 1usize << (height - 1);
+        let pointer_word_count = self.geometry.pointer_format.word_count();
+        let mut pointer_index = node_index + 1;
+
+        for child_index in 0..8 {
+            if !node.children.get(child_index) {
+                continue;
+            }
+
+            let child_origin = child_origin(origin, child_index, half_size);
+
+            if height == 1 {
+                if let Some(attribute) = volume.get(child_origin) {
+                    self.attributes.push(attribute.clone());
+                }
+            } else if let Some(child_node_index) = self.geometry.read_pointer(pointer_index) {
+                self.collect_attributes(volume, child_node_index, height - 1, child_origin);
+            }
+
+            pointer_index += pointer_word_count;
+        }
+    }
+}
+
+fn count_voxels(
+    geometry: &Svdag,
+    voxel_counts: &mut HashMap<usize, u64>,
+    node_index: usize,
+    height: u8,
+) -> u64 {
+    if let Some(voxel_count) = voxel_counts.get(&node_index) {
+        return *voxel_count;
+    }
+
+    let node = geometry.nodes[node_index].node();
+    let mut voxel_count = 0;
+
+    if height == 1 {
+        voxel_count = node.children.count_occupied() as u64;
+    } else {
+        let pointer_word_count = geometry.pointer_format.word_count();
+
+        for child_number in 0..node.children.count_occupied() {
+            if let Some(child_node_index) =
+                geometry.read_pointer(node_index + 1 + child_number * pointer_word_count)
+            {
+                voxel_count += count_voxels(geometry, voxel_counts, child_node_index, height - 1);
+            }
+        }
+    }
+
+    voxel_counts.insert(node_index, voxel_count);
+
+    voxel_count
+}
@@ -0,0 +1,167 @@
+use super::{svdag_world::WorldPosition, NodeId, PointerFormat};
+use crate::volume::{VolumeDimensions, VolumePosition};
+use std::{error::Error, fmt, io};
+
+#[derive(Debug)]
+pub enum SvdagError {
+    /// A child pointer can't reach its target node with the chosen pointer format.
+    PointerOverflow {
+        pointer_format: PointerFormat,
+        pointer_index: usize,
+        target_index: usize,
+    },
+    /// A pointer to a reflected node can only be stored in the `Mirrored32` format.
+    MirrorUnsupported {
+        pointer_format: PointerFormat,
+    },
+    Io(io::Error),
+    /// The input doesn't start with the `SVDG` magic bytes.
+    InvalidMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    UnknownPointerFormat(u8),
+    /// The header announces more node words than this machine can address.
+    InvalidNodeCount(u64),
+    /// The input ended before all announced bytes were read.
+    Truncated {
+        expected: u64,
+        actual: u64,
+    },
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// The depth is too large for positions to be addressable.
+    InvalidDepth(u8),
+    /// Two graphs that have to line up voxel by voxel differ in depth.
+    DepthMismatch {
+        expected: u8,
+        actual: u8,
+    },
+    /// The dimensions don't fit inside the cube of side `2^depth`.
+    InvalidDimensions(VolumeDimensions),
+    /// A reachable node lies outside the node array.
+    InvalidNode {
+        index: usize,
+    },
+    /// A reachable pointer lies outside the node array or points outside of it.
+    InvalidPointer {
+        pointer_index: usize,
+    },
+    /// The position lies outside of the volume.
+    OutOfBounds {
+        position: VolumePosition,
+    },
+    /// The world position lies in a chunk whose coordinates don't fit a `ChunkPosition`.
+    OutOfWorld {
+        position: WorldPosition,
+    },
+    /// A node table holds more unique nodes than a `NodeId` can address.
+    TooManyNodes,
+    /// The MagicaVoxel file was written in a version of the format that can't be read.
+    UnsupportedVoxVersion(u32),
+    /// The input isn't a well-formed MagicaVoxel file.
+    InvalidVox(String),
+    /// The input isn't a well-formed mesh file.
+    InvalidMesh(String),
+    /// The input isn't a well-formed point cloud or can't be imported as given.
+    InvalidPointCloud(String),
+}
+
+impl fmt::Display for SvdagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvdagError::PointerOverflow {
+                pointer_format,
+                pointer_index,
+                target_index,
+            } => write!(
+                f,
+                "pointer at index {} can't address node {} with the {:?} pointer format",
+                pointer_index, target_index, pointer_format
+            ),
+            SvdagError::MirrorUnsupported { pointer_format } => write!(
+                f,
+                "the {:?} pointer format can't store reflected nodes",
+                pointer_format
+            ),
+            SvdagError::Io(error) => write!(f, "i/o error: {}", error),
+            SvdagError::InvalidMagic(magic) => {
+                write!(f, "invalid magic bytes {:?}, not an svdag file", magic)
+            }
+            SvdagError::UnsupportedVersion(version) => {
+                write!(f, "unsupported svdag format version {}", version)
+            }
+            SvdagError::UnknownPointerFormat(code) => {
+                write!(f, "unknown pointer format code {}", code)
+            }
+            SvdagError::InvalidNodeCount(node_count) => {
+                write!(f, "node count {} is too large", node_count)
+            }
+            SvdagError::Truncated { expected, actual } => write!(
+                f,
+                "input is truncated, expected {} bytes but got {}",
+                expected, actual
+            ),
+            SvdagError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch, expected {:#010x} but node data hashes to {:#010x}",
+                expected, actual
+            ),
+            SvdagError::InvalidDepth(depth) => write!(f, "depth {} is out of range", depth),
+            SvdagError::DepthMismatch { expected, actual } => write!(
+                f,
+                "expected a graph of depth {} but got depth {}",
+                expected, actual
+            ),
+            SvdagError::InvalidDimensions(dimensions) => write!(
+                f,
+                "dimensions {:?} don't fit inside the volume's cube",
+                dimensions
+            ),
+            SvdagError::InvalidNode { index } => {
+                write!(f, "node at index {} lies outside the node array", index)
+            }
+            SvdagError::InvalidPointer { pointer_index } => write!(
+                f,
+                "pointer at index {} doesn't point into the node array",
+                pointer_index
+            ),
+            SvdagError::OutOfBounds { position } => {
+                write!(f, "position {:?} lies outside the volume", position)
+            }
+            SvdagError::OutOfWorld { position } => {
+                write!(
+                    f,
+                    "world position {:?} lies outside the chunk grid",
+                    position
+                )
+            }
+            SvdagError::TooManyNodes => write!(
+                f,
+                "node table can't hold more than {} unique nodes",
+                NodeId::MAX as u64 + 1
+            ),
+            SvdagError::UnsupportedVoxVersion(version) => {
+                write!(f, "unsupported MagicaVoxel file version {}", version)
+            }
+            SvdagError::InvalidVox(reason) => write!(f, "invalid MagicaVoxel file: {}", reason),
+            SvdagError::InvalidMesh(reason) => write!(f, "invalid mesh: {}", reason),
+            SvdagError::InvalidPointCloud(reason) => write!(f, "invalid point cloud: {}", reason),
+        }
+    }
+}
+
+impl Error for SvdagError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SvdagError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SvdagError {
+    fn from(error: io::Error) -> Self {
+        SvdagError::Io(error)
+    }
+}
@@ -0,0 +1,186 @@
+use super::{
+    svdag_node_table::{child_origin, NodeId, NodeTable, TableNode},
+    PointerFormat, SvdagError, SvdagRead, SvdagValue,
+};
+use crate::volume::{CubicVolume, IsVolume, VolumeDimensions, VolumePosition};
+
+/// Graph whose leaves store a material id per voxel, material 0 being empty space.
+///
+/// The layout matches `Svdag` except that every leaf node is followed by one word per occupied
+/// voxel holding its material, so occupancy queries through `SvdagRead` work unchanged.
+#[derive(Clone, Debug)]
+pub struct MaterialSvdag {
+    pub depth: u8,
+    /// Logical extent of the volume inside the cube of side `2^depth`, voxels past it don't exist.
+    pub dimensions: VolumeDimensions,
+    pub pointer_format: PointerFormat,
+    pub nodes: Vec<SvdagValue>,
+}
+
+impl MaterialSvdag {
+    pub fn get(&self, target_position: VolumePosition) -> Option<bool> {
+        SvdagRead::get(self, target_position)
+    }
+
+    /// Returns the material at `target_position`, 0 for empty voxels and `None` outside the volume.
+    pub fn get_material(&self, target_position: VolumePosition) -> Option<u16> {
+        let depth = self.depth;
+        if depth == 0 || depth as u32 >= usize::BITS {
+            return None;
+        }
+
+        let dimensions = self.dimensions;
+        if target_position.0 >= dimensions.0
+            || target_position.1 >= dimensions.1
+            || target_position.2 >= dimensions.2
+        {
+            return None;
+        }
+
+        let pointer_word_count = self.pointer_format.word_count();
+        let mut node_index = 0;
+
+        for level in (0..depth).rev() {
+            let child_index = (target_position.0 >> level & 1) << 2
+                | (target_position.1 >> level & 1) << 1
+                | (target_position.2 >> level & 1);
+
+            let node = self.nodes.get(node_index)?.node();
+            if !node.children.get(child_index) {
+                return Some(0);
+            }
+
+            //Leaf materials follow the node in child index order, one per occupied voxel
+            if level == 0 {
+                let material_index = node_index + 1 + node.children.get_n(child_index);
+                return Some(self.nodes.get(material_index)?.bits);
+            }
+
+            let child_pointer_index =
+                node_index + 1 + node.children.get_n(child_index) * pointer_word_count;
+            node_index = self.read_pointer(child_pointer_index)?;
+        }
+
+        None
+    }
+}
+
+impl SvdagRead for MaterialSvdag {
+    fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    fn pointer_format(&self) -> PointerFormat {
+        self.pointer_format
+    }
+
+    fn word_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn word(&self, index: usize) -> Option<SvdagValue> {
+        self.nodes.get(index).copied()
+    }
+
+    fn dimensions(&self) -> VolumeDimensions {
+        self.dimensions
+    }
+}
+
+impl IsVolume for MaterialSvdag {
+    fn get_dimensions(&self) -> VolumeDimensions {
+        self.dimensions
+    }
+}
+
+/// Builds a `MaterialSvdag` from a volume of material ids.
+///
+/// Leaves are deduplicated together with their materials, so regions of a single material merge
+/// as aggressively as plain occupancy while regions that only share their shape stay apart.
+pub struct MaterialSvdagBuilder {
+    pointer_format: Option<PointerFormat>,
+    table: NodeTable,
+}
+
+impl MaterialSvdagBuilder {
+    pub fn new() -> MaterialSvdagBuilder {
+        MaterialSvdagBuilder {
+            pointer_format: None,
+            table: NodeTable::new(),
+        }
+    }
+
+    /// Forces a pointer format, by default the narrowest format that fits the graph is used.
+    pub fn pointer_format(&mut self, pointer_format: PointerFormat) -> &mut Self {
+        self.pointer_format = Some(pointer_format);
+        self
+    }
+
+    pub fn build<M>(&mut self, volume: &CubicVolume<M>) -> Result<MaterialSvdag, SvdagError>
+    where
+        M: Default + Clone + Copy + Into<u16>,
+    {
+        if volume.depth == 0 {
+            return Err(SvdagError::InvalidDepth(volume.depth));
+        }
+
+        let root = match self.build_octant(volume, volume.depth, (0, 0, 0))? {
+            Some(root) => root,
+            None => self.table.insert(TableNode::empty(volume.depth))?,
+        };
+
+        let svdag = self
+            .table
+            .to_svdag_with_leaf_payload(root, self.pointer_format);
+        self.table = NodeTable::new();
+
+        svdag.map(|svdag| MaterialSvdag {
+            depth: svdag.depth,
+            dimensions: volume.get_dimensions(),
+            pointer_format: svdag.pointer_format,
+            nodes: svdag.nodes,
+        })
+    }
+
+    fn build_octant<M>(
+        &mut self,
+        volume: &CubicVolume<M>,
+        height: u8,
+        origin: VolumePosition,
+    ) -> Result<Option<NodeId>, SvdagError>
+    where
+        M: Default + Clone + Copy + Into<u16>,
+    {
+        let half_size = 1usize << (height - 1);
+        let mut node = TableNode::empty(height);
+
+        for child_index in 0..8 {
+            let child_origin = child_origin(origin, child_index, half_size);
+
+            //Leaves keep the material in the child id slot, inner nodes the child's id
+            let child_id = if height == 1 {
+                let material: u16 = (*volume.get(child_origin)).into();
+                Some(material as NodeId).filter(|material| *material != 0)
+            } else {
+                self.build_octant(volume, height - 1, child_origin)?
+            };
+
+            if let Some(child_id) = child_id {
+                node.children.set(child_index, true);
+                node.child_ids[child_index] = child_id;
+            }
+        }
+
+        if node.children.have_occupied_children() {
+            Ok(Some(self.table.insert(node)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Default for MaterialSvdagBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
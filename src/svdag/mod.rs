@@ -1,9 +1,78 @@
+mod attributed_svdag;
+mod error;
+mod material_svdag;
+#[allow(clippy::module_inception)]
 mod svdag;
+mod svdag_boolean;
+mod svdag_brush;
 mod svdag_builder;
+mod svdag_editor;
+mod svdag_extract;
+mod svdag_format;
+mod svdag_iter;
+mod svdag_mesh;
+mod svdag_mesher;
+mod svdag_neighbors;
+mod svdag_node_table;
+mod svdag_patch;
+mod svdag_point_cloud;
+mod svdag_point_cloud_importer;
+mod svdag_pool;
+mod svdag_raycast;
+mod svdag_read;
+mod svdag_ref;
+mod svdag_stream_builder;
+mod svdag_vox;
+mod svdag_voxelizer;
+mod svdag_world;
 
+pub use attributed_svdag::AttributedSvdag;
+
+pub use error::SvdagError;
+
+pub use material_svdag::{MaterialSvdag, MaterialSvdagBuilder};
+
+pub use svdag::CompactReport;
+pub use svdag::PointerFormat;
 pub use svdag::Svdag;
 pub use svdag::SvdagNode;
 pub use svdag::SvdagPointer;
 pub use svdag::SvdagValue;
 
+pub use svdag_brush::{BoxBrush, Brush, SphereBrush, SvdagBrush};
+
 pub use svdag_builder::SvdagBuilder;
+
+pub use svdag_editor::SvdagEditor;
+
+pub use svdag_format::SvdagHeader;
+
+pub use svdag_iter::{OccupiedNodes, OccupiedVoxels};
+
+pub use svdag_mesh::TriangleMesh;
+
+pub use svdag_mesher::SvdagMesher;
+
+pub use svdag_neighbors::{Face, FaceMask, SurfaceVoxels};
+
+pub use svdag_node_table::{NodeId, NodeOrder, NodeTable, TableNode};
+
+pub use svdag_point_cloud::PointCloud;
+
+pub use svdag_point_cloud_importer::PointCloudImporter;
+
+pub use svdag_pool::{SvdagHandle, SvdagPool};
+
+pub use svdag_raycast::SvdagHit;
+
+pub use svdag_read::SvdagRead;
+
+pub use svdag_ref::SvdagRef;
+
+pub use svdag_stream_builder::{OctantCoverage, SvdagStreamBuilder};
+
+pub use svdag_vox::{VoxFile, VoxModel, VoxPalette};
+
+pub use svdag_voxelizer::MeshVoxelizer;
+
+pub use svdag_world::{ChunkPosition, SvdagWorld, WorldHit, WorldPosition};
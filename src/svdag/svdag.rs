@@ -1,16 +1,60 @@
-use super::SvdagBuilder;
+use super::{
+    svdag_boolean::{self, BooleanOp},
+    svdag_patch,
+    svdag_read::cube_dimensions,
+    BoxBrush, Brush, NodeOrder, NodeTable, SvdagBuilder, SvdagError, SvdagHit, SvdagRead,
+    SvdagStreamBuilder,
+};
 use crate::hashed_volume::Children;
 use crate::volume::VolumeDimensions;
 use crate::volume::{DensityVolume, IsVolume, VolumePosition};
-use std::fmt;
+use std::{convert::TryFrom, fmt};
 
-#[repr(C)]
 #[derive(Clone, Debug)]
 pub struct Svdag {
     pub depth: u8,
+    /// Logical extent of the volume inside the cube of side `2^depth`, voxels past it don't exist.
+    pub dimensions: VolumeDimensions,
+    pub pointer_format: PointerFormat,
     pub nodes: Vec<SvdagValue>,
 }
 
+/// Encoding of the child pointers that follow every non-leaf node in `Svdag::nodes`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PointerFormat {
+    /// One word holding a signed offset from the pointer's own index, reaches about 32K words.
+    Relative16,
+    /// Two words, low word first, holding the absolute index of the child node.
+    Absolute32,
+    /// Two words, low word first, holding a 29 bit absolute index and in the top 3 bits the
+    /// reflection of the child node, as produced by symmetry-aware building.
+    Mirrored32,
+}
+
+impl PointerFormat {
+    /// Number of `SvdagValue` words a single pointer occupies.
+    pub fn word_count(&self) -> usize {
+        match self {
+            PointerFormat::Relative16 => 1,
+            PointerFormat::Absolute32 => 2,
+            PointerFormat::Mirrored32 => 2,
+        }
+    }
+}
+
+/// Size of the node array before and after `Svdag::compact`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CompactReport {
+    pub bytes_before: usize,
+    pub bytes_after: usize,
+}
+
+impl CompactReport {
+    pub fn bytes_reclaimed(&self) -> usize {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct SvdagPointer {
@@ -24,21 +68,46 @@ pub struct SvdagNode {
     pub padding: u8,
 }
 
-#[repr(C)]
-#[derive(Copy, Clone)]
-pub union SvdagValue {
-    pub pointer: SvdagPointer,
-    pub node: SvdagNode,
+/// A single word of the node array, read either as a node or as (part of) a pointer.
+#[repr(transparent)]
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SvdagValue {
+    pub bits: u16,
+}
+
+impl SvdagValue {
+    pub fn from_node(node: SvdagNode) -> SvdagValue {
+        SvdagValue {
+            bits: node.children.child_bits as u16 | (node.padding as u16) << 8,
+        }
+    }
+
+    pub fn from_pointer(pointer: SvdagPointer) -> SvdagValue {
+        SvdagValue {
+            bits: pointer.value as u16,
+        }
+    }
+
+    pub fn node(&self) -> SvdagNode {
+        SvdagNode {
+            children: Children::new(self.bits as u8),
+            padding: (self.bits >> 8) as u8,
+        }
+    }
+
+    pub fn pointer(&self) -> SvdagPointer {
+        SvdagPointer {
+            value: self.bits as i16,
+        }
+    }
 }
 
 impl fmt::Debug for SvdagValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        unsafe {
-            f.debug_struct("Value")
-                .field("p", &self.pointer)
-                .field("n", &self.node)
-                .finish()
-        }
+        f.debug_struct("Value")
+            .field("p", &self.pointer())
+            .field("n", &self.node())
+            .finish()
     }
 }
 
@@ -46,73 +115,185 @@ impl Svdag {
     pub fn new() -> Svdag {
         Svdag {
             depth: 0,
+            dimensions: cube_dimensions(0),
+            pointer_format: PointerFormat::Relative16,
             nodes: Vec::new(),
         }
     }
 
-    pub fn get(&self, target_position: VolumePosition) -> bool {
-        self.get_recursive(
-            &target_position.clone(),
-            0,
-            0,
-            (&mut (0, 0, 0), &mut self.get_dimensions()),
-        )
-    }
-
-    fn get_recursive(
-        &self,
-        target_position: &VolumePosition,
-        node_index: usize,
-        current_depth: u8,
-        (filter_position, filter_dimensions): (&mut VolumePosition, &mut VolumeDimensions),
-    ) -> bool {
-        //Half the filter dimensions through reference for better performance
-        filter_dimensions.0 /= 2;
-        filter_dimensions.1 /= 2;
-        filter_dimensions.2 /= 2;
-
-        //Determine which child index to check
-        let mut child_index = 0;
-        if target_position.0 >= filter_position.0 + filter_dimensions.0 {
-            filter_position.0 += filter_dimensions.0;
-            child_index += 4;
-        }
-        if target_position.1 >= filter_position.1 + filter_dimensions.1 {
-            filter_position.1 += filter_dimensions.2;
-            child_index += 2;
-        }
-        if target_position.2 >= filter_position.2 + filter_dimensions.2 {
-            filter_position.2 += filter_dimensions.2;
-            child_index += 1;
+    /// Stores a pointer to `target_index` at `pointer_index`, failing if the pointer format can't reach it.
+    pub fn write_pointer(
+        &mut self,
+        pointer_index: usize,
+        target_index: usize,
+    ) -> Result<(), SvdagError> {
+        self.write_mirrored_pointer(pointer_index, target_index, 0)
+    }
+
+    /// Stores a pointer to `target_index` reflected by `mirror` at `pointer_index`. Only the
+    /// `Mirrored32` format can store a reflection other than 0.
+    pub fn write_mirrored_pointer(
+        &mut self,
+        pointer_index: usize,
+        target_index: usize,
+        mirror: u8,
+    ) -> Result<(), SvdagError> {
+        let overflow = SvdagError::PointerOverflow {
+            pointer_format: self.pointer_format,
+            pointer_index,
+            target_index,
+        };
+
+        if mirror != 0 && self.pointer_format != PointerFormat::Mirrored32 {
+            return Err(SvdagError::MirrorUnsupported {
+                pointer_format: self.pointer_format,
+            });
         }
 
-        unsafe {
-            let node = &self.nodes.get(node_index).unwrap().node;
+        match self.pointer_format {
+            PointerFormat::Relative16 => {
+                let offset = target_index as isize - pointer_index as isize;
+                let value = i16::try_from(offset).map_err(|_| overflow)?;
 
-            //Check if this node's child area is occupied
-            let is_child_occupied = node.children.get(child_index);
+                self.nodes[pointer_index] = SvdagValue::from_pointer(SvdagPointer { value });
+            }
+            PointerFormat::Absolute32 => {
+                let value = u32::try_from(target_index).map_err(|_| overflow)?;
 
-            //If it's not occupied there won't be a child node so the space is empty
-            if !is_child_occupied {
-                return false
+                self.nodes[pointer_index] = SvdagValue { bits: value as u16 };
+                self.nodes[pointer_index + 1] = SvdagValue {
+                    bits: (value >> 16) as u16,
+                };
             }
+            PointerFormat::Mirrored32 => {
+                if target_index >= 1 << 29 {
+                    return Err(overflow);
+                }
 
-            //Otherwise find the child area's consecutive index and pass it off to the recursion
-            let child_pointer_index = node_index + node.children.get_n(child_index) + 1;
+                self.nodes[pointer_index] = SvdagValue {
+                    bits: target_index as u16,
+                };
+                self.nodes[pointer_index + 1] = SvdagValue {
+                    bits: (target_index >> 16) as u16 | (mirror as u16 & 0b111) << 13,
+                };
+            }
+        }
 
-            if current_depth + 1 < self.depth {
-                let child_pointer = &self.nodes.get(child_pointer_index).unwrap().pointer;
+        Ok(())
+    }
 
-                self.get_recursive(
-                    target_position,
-                    (child_pointer_index as isize + child_pointer.value as isize) as usize,
-                    current_depth + 1,
-                    (filter_position, filter_dimensions),
-                )
-            } else {
-                is_child_occupied
-            }
+    /// Builds a graph of the given depth by sampling every voxel through `sample`.
+    pub fn from_fn(
+        depth: u8,
+        sample: impl FnMut(VolumePosition) -> bool,
+    ) -> Result<Svdag, SvdagError> {
+        SvdagStreamBuilder::new(depth).build_from_fn(sample)
+    }
+
+    /// Builds a graph of the given depth from a conservative signed distance function, see
+    /// `SvdagStreamBuilder::build_from_sdf`.
+    pub fn from_sdf(depth: u8, sdf: impl Fn([f32; 3]) -> f32) -> Result<Svdag, SvdagError> {
+        SvdagStreamBuilder::new(depth).build_from_sdf(sdf)
+    }
+
+    pub fn get(&self, target_position: VolumePosition) -> Option<bool> {
+        SvdagRead::get(self, target_position)
+    }
+
+    /// Expands the voxels in `[min, max)` into a dense volume, see `SvdagRead::extract`.
+    pub fn extract(&self, min: VolumePosition, max: VolumePosition) -> DensityVolume {
+        SvdagRead::extract(self, min, max)
+    }
+
+    pub fn raycast(&self, origin: [f32; 3], direction: [f32; 3], max_t: f32) -> Option<SvdagHit> {
+        SvdagRead::raycast(self, origin, direction, max_t)
+    }
+
+    /// Rewrites the node array so it only holds the nodes reachable from the root, each unique
+    /// subtree once, laid out in `order`. The narrowest pointer format that fits is picked again.
+    pub fn compact(&mut self, order: NodeOrder) -> Result<CompactReport, SvdagError> {
+        let bytes_before = self.nodes.len() * std::mem::size_of::<SvdagValue>();
+
+        let mut table = NodeTable::new();
+        let root = table.insert_svdag(self)?;
+        let dimensions = self.dimensions;
+        *self = table.to_svdag_in_order(root, None, order)?;
+        self.dimensions = dimensions;
+
+        Ok(CompactReport {
+            bytes_before,
+            bytes_after: self.nodes.len() * std::mem::size_of::<SvdagValue>(),
+        })
+    }
+
+    /// Voxels occupied in either graph. Fails if the graphs differ in depth or either of them is malformed.
+    pub fn union(&self, other: &Svdag) -> Result<Svdag, SvdagError> {
+        svdag_boolean::combine(self, other, BooleanOp::Union)
+    }
+
+    /// Voxels occupied in both graphs. Fails if the graphs differ in depth or either of them is malformed.
+    pub fn intersect(&self, other: &Svdag) -> Result<Svdag, SvdagError> {
+        svdag_boolean::combine(self, other, BooleanOp::Intersect)
+    }
+
+    /// Voxels occupied in this graph but not in `other`. Fails if the graphs differ in depth or either of them is malformed.
+    pub fn subtract(&self, other: &Svdag) -> Result<Svdag, SvdagError> {
+        svdag_boolean::combine(self, other, BooleanOp::Subtract)
+    }
+
+    /// Sets or clears a single voxel.
+    ///
+    /// Only the path to the voxel is copied: the new nodes are appended to the node array and
+    /// the root is rewritten to point at them, so an edit costs time proportional to the depth.
+    /// The replaced nodes stay in the array as unreachable words, and the copies aren't merged
+    /// with equal subtrees elsewhere, until `compact` rewrites the array.
+    pub fn set(&mut self, position: VolumePosition, value: bool) -> Result<(), SvdagError> {
+        let dimensions = self.dimensions;
+        if position.0 >= dimensions.0 || position.1 >= dimensions.1 || position.2 >= dimensions.2 {
+            return Err(SvdagError::OutOfBounds { position });
         }
+
+        svdag_patch::patch(self, &BoxBrush::voxel(position), value)
+    }
+
+    /// Occupies every voxel covered by `brush`, the parts outside of the dimensions are ignored.
+    /// Copies only the octants the brush reaches, like `set`.
+    pub fn fill(&mut self, brush: &impl Brush) -> Result<(), SvdagError> {
+        svdag_patch::patch(self, brush, true)
+    }
+
+    /// Empties every voxel covered by `brush`, the parts outside of the dimensions are ignored.
+    /// Copies only the octants the brush reaches, like `set`.
+    pub fn carve(&mut self, brush: &impl Brush) -> Result<(), SvdagError> {
+        svdag_patch::patch(self, brush, false)
+    }
+}
+
+impl SvdagRead for Svdag {
+    fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    fn pointer_format(&self) -> PointerFormat {
+        self.pointer_format
+    }
+
+    fn word_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn word(&self, index: usize) -> Option<SvdagValue> {
+        self.nodes.get(index).copied()
+    }
+
+    fn dimensions(&self) -> VolumeDimensions {
+        self.dimensions
+    }
+}
+
+impl Default for Svdag {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -121,14 +302,13 @@ impl From<&DensityVolume> for Svdag {
         SvdagBuilder::new()
             .create_layers(density_volume)
             .create_graph()
+            .expect("node array is too large for any pointer format")
             .finish()
     }
 }
 
 impl IsVolume for Svdag {
     fn get_dimensions(&self) -> VolumeDimensions {
-        let side_size = 2usize.pow(self.depth as u32);
-
-        (side_size, side_size, side_size)
+        self.dimensions
     }
 }
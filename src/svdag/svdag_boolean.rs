@@ -0,0 +1,221 @@
+use super::{
+    svdag_read::{read_node, NodeRef},
+    NodeId, NodeTable, Svdag, SvdagError, SvdagRead, TableNode,
+};
+use crate::{hashed_volume::Children, volume::VolumeDimensions};
+use std::collections::HashMap;
+
+/// Set operation applied voxel by voxel by `combine`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum BooleanOp {
+    Union,
+    Intersect,
+    Subtract,
+}
+
+impl BooleanOp {
+    fn apply(&self, a: u8, b: u8) -> u8 {
+        match self {
+            BooleanOp::Union => a | b,
+            BooleanOp::Intersect => a & b,
+            BooleanOp::Subtract => a & !b,
+        }
+    }
+
+    /// Extent of the result, nothing can be occupied past it.
+    fn dimensions(&self, a: VolumeDimensions, b: VolumeDimensions) -> VolumeDimensions {
+        match self {
+            BooleanOp::Union => (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)),
+            BooleanOp::Intersect => (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)),
+            BooleanOp::Subtract => a,
+        }
+    }
+}
+
+/// Combines two graphs of the same depth into a new deduplicated graph.
+pub(crate) fn combine<A, B>(a: &A, b: &B, op: BooleanOp) -> Result<Svdag, SvdagError>
+where
+    A: SvdagRead + ?Sized,
+    B: SvdagRead + ?Sized,
+{
+    let depth = a.depth();
+    if depth == 0 || depth as u32 >= usize::BITS {
+        return Err(SvdagError::InvalidDepth(depth));
+    }
+    if b.depth() != depth {
+        return Err(SvdagError::DepthMismatch {
+            expected: depth,
+            actual: b.depth(),
+        });
+    }
+
+    let mut combiner = Combiner {
+        op,
+        a: Input::new(a),
+        b: Input::new(b),
+        table: NodeTable::new(),
+        combined_nodes: HashMap::new(),
+    };
+
+    let root = match combiner.combine_nodes(Some((0, 0)), Some((0, 0)), depth)? {
+        Some((node_id, mirror)) => {
+            let root = combiner.table.node(node_id).mirrored(mirror);
+            combiner.table.insert(root)?
+        }
+        //A completely empty volume still needs a root node to be a valid graph
+        None => combiner.table.insert(TableNode::empty(depth))?,
+    };
+
+    let mut svdag = combiner.table.to_svdag(root, None)?;
+    svdag.dimensions = op.dimensions(a.dimensions(), b.dimensions());
+
+    Ok(svdag)
+}
+
+struct Input<'a, S: ?Sized> {
+    svdag: &'a S,
+    full_nodes: HashMap<usize, bool>,
+    /// Ids of the nodes already copied into the output table by node index.
+    node_ids: HashMap<usize, NodeId>,
+}
+
+impl<'a, S> Input<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    fn new(svdag: &'a S) -> Input<'a, S> {
+        Input {
+            svdag,
+            full_nodes: HashMap::new(),
+            node_ids: HashMap::new(),
+        }
+    }
+
+    fn read(
+        &self,
+        node: NodeRef,
+        height: u8,
+    ) -> Result<(Children, [Option<NodeRef>; 8]), SvdagError> {
+        read_node(self.svdag, node, height)
+    }
+
+    /// Checks whether every voxel under the node is occupied, which doesn't depend on reflections.
+    fn is_full(&mut self, node_index: usize, height: u8) -> Result<bool, SvdagError> {
+        if let Some(is_full) = self.full_nodes.get(&node_index) {
+            return Ok(*is_full);
+        }
+
+        let (children, child_nodes) = self.read((node_index, 0), height)?;
+
+        let mut is_full = children.count_occupied() == 8;
+        if is_full && height > 1 {
+            for (child_node_index, _) in child_nodes.iter().flatten() {
+                if !self.is_full(*child_node_index, height - 1)? {
+                    is_full = false;
+                    break;
+                }
+            }
+        }
+
+        self.full_nodes.insert(node_index, is_full);
+
+        Ok(is_full)
+    }
+
+    /// Copies the subtree into `table` unchanged, keeping its reflection in the pointer to it.
+    fn copy(
+        &mut self,
+        table: &mut NodeTable,
+        (node_index, mirror): NodeRef,
+        height: u8,
+    ) -> Result<Option<(NodeId, u8)>, SvdagError> {
+        let node_id =
+            table.insert_svdag_node(self.svdag, &mut self.node_ids, node_index, height)?;
+
+        Ok(Some((node_id, mirror)))
+    }
+}
+
+struct Combiner<'a, A: ?Sized, B: ?Sized> {
+    op: BooleanOp,
+    a: Input<'a, A>,
+    b: Input<'a, B>,
+    table: NodeTable,
+    combined_nodes: HashMap<(NodeRef, NodeRef), Option<(NodeId, u8)>>,
+}
+
+impl<'a, A, B> Combiner<'a, A, B>
+where
+    A: SvdagRead + ?Sized,
+    B: SvdagRead + ?Sized,
+{
+    /// Combines two nodes of the same height, `None` standing for an empty subtree. Returns the
+    /// combined node and its reflection or `None` if the result is empty.
+    fn combine_nodes(
+        &mut self,
+        a: Option<NodeRef>,
+        b: Option<NodeRef>,
+        height: u8,
+    ) -> Result<Option<(NodeId, u8)>, SvdagError> {
+        //Settle empty and solid subtrees without walking the other side
+        let (a, b) = match (self.op, a, b) {
+            (BooleanOp::Union, None, None) => return Ok(None),
+            (BooleanOp::Union, None, Some(b)) => return self.b.copy(&mut self.table, b, height),
+            (BooleanOp::Union, Some(a), None) => return self.a.copy(&mut self.table, a, height),
+            (BooleanOp::Intersect, None, _) | (BooleanOp::Intersect, _, None) => return Ok(None),
+            (BooleanOp::Subtract, None, _) => return Ok(None),
+            (BooleanOp::Subtract, Some(a), None) => return self.a.copy(&mut self.table, a, height),
+            (_, Some(a), Some(b)) => (a, b),
+        };
+
+        let is_a_full = self.a.is_full(a.0, height)?;
+        let is_b_full = self.b.is_full(b.0, height)?;
+
+        match self.op {
+            BooleanOp::Union if is_a_full || is_b_full => {
+                return Ok(Some((self.table.insert_solid(height)?, 0)))
+            }
+            BooleanOp::Intersect if is_a_full => return self.b.copy(&mut self.table, b, height),
+            BooleanOp::Intersect if is_b_full => return self.a.copy(&mut self.table, a, height),
+            BooleanOp::Subtract if is_b_full => return Ok(None),
+            _ => {}
+        }
+
+        if let Some(combined_node) = self.combined_nodes.get(&(a, b)) {
+            return Ok(*combined_node);
+        }
+
+        let (a_children, a_child_nodes) = self.a.read(a, height)?;
+        let (b_children, b_child_nodes) = self.b.read(b, height)?;
+
+        let mut node = TableNode::empty(height);
+
+        if height == 1 {
+            node.children =
+                Children::new(self.op.apply(a_children.child_bits, b_children.child_bits));
+        } else {
+            for child_index in 0..8 {
+                let child = self.combine_nodes(
+                    a_child_nodes[child_index],
+                    b_child_nodes[child_index],
+                    height - 1,
+                )?;
+
+                if let Some((child_id, child_mirror)) = child {
+                    node.children.set(child_index, true);
+                    node.child_ids[child_index] = child_id;
+                    node.child_mirrors[child_index] = child_mirror;
+                }
+            }
+        }
+
+        let combined_node = if node.children.have_occupied_children() {
+            Some((self.table.insert(node)?, 0))
+        } else {
+            None
+        };
+        self.combined_nodes.insert((a, b), combined_node);
+
+        Ok(combined_node)
+    }
+}
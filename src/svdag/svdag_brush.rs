@@ -0,0 +1,257 @@
+use super::{svdag_node_table::mirror_child_index, OctantCoverage, SvdagRead};
+use crate::volume::VolumePosition;
+
+/// A shape that edits fill or carve out of a graph, see `SvdagEditor::fill`.
+pub trait Brush {
+    /// How much of the octant at `origin` with side length `side` the brush covers. Octants
+    /// reported as `Empty` are left untouched, `Partial` is always a safe answer.
+    fn classify(&self, origin: VolumePosition, side: usize) -> OctantCoverage;
+
+    fn contains(&self, position: VolumePosition) -> bool;
+}
+
+/// All voxels in `[min, max)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BoxBrush {
+    pub min: VolumePosition,
+    pub max: VolumePosition,
+}
+
+impl BoxBrush {
+    pub fn new(min: VolumePosition, max: VolumePosition) -> BoxBrush {
+        BoxBrush { min, max }
+    }
+
+    /// The single voxel at `position`.
+    pub fn voxel(position: VolumePosition) -> BoxBrush {
+        BoxBrush::new(position, (position.0 + 1, position.1 + 1, position.2 + 1))
+    }
+}
+
+impl Brush for BoxBrush {
+    fn classify(&self, origin: VolumePosition, side: usize) -> OctantCoverage {
+        let octant_min = [origin.0, origin.1, origin.2];
+        let brush_min = [self.min.0, self.min.1, self.min.2];
+        let brush_max = [self.max.0, self.max.1, self.max.2];
+
+        let mut coverage = OctantCoverage::Full;
+        for axis in 0..3 {
+            let octant_max = octant_min[axis] + side;
+
+            if octant_max <= brush_min[axis] || octant_min[axis] >= brush_max[axis] {
+                return OctantCoverage::Empty;
+            }
+
+            if octant_min[axis] < brush_min[axis] || octant_max > brush_max[axis] {
+                coverage = OctantCoverage::Partial;
+            }
+        }
+
+        coverage
+    }
+
+    fn contains(&self, position: VolumePosition) -> bool {
+        (self.min.0..self.max.0).contains(&position.0)
+            && (self.min.1..self.max.1).contains(&position.1)
+            && (self.min.2..self.max.2).contains(&position.2)
+    }
+}
+
+/// All voxels whose center lies strictly within `radius` of `center`, voxel `(x, y, z)` being
+/// centered at `(x + 0.5, y + 0.5, z + 0.5)`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SphereBrush {
+    pub center: [f32; 3],
+    pub radius: f32,
+}
+
+impl SphereBrush {
+    pub fn new(center: [f32; 3], radius: f32) -> SphereBrush {
+        SphereBrush { center, radius }
+    }
+}
+
+impl Brush for SphereBrush {
+    fn classify(&self, origin: VolumePosition, side: usize) -> OctantCoverage {
+        let octant_min = [origin.0 as f32, origin.1 as f32, origin.2 as f32];
+
+        //Only voxel centers count, so measure against the box spanned by them
+        let mut nearest = 0.0;
+        let mut farthest = 0.0;
+        for (octant_min, center) in octant_min.iter().zip(self.center.iter()) {
+            let low = octant_min + 0.5;
+            let high = octant_min + side as f32 - 0.5;
+            let center = *center;
+
+            let nearest_offset = center - center.clamp(low, high);
+            let farthest_offset = (center - low).abs().max((center - high).abs());
+
+            nearest += nearest_offset * nearest_offset;
+            farthest += farthest_offset * farthest_offset;
+        }
+
+        let radius_squared = self.radius * self.radius;
+        if nearest >= radius_squared {
+            OctantCoverage::Empty
+        } else if farthest < radius_squared {
+            OctantCoverage::Full
+        } else {
+            OctantCoverage::Partial
+        }
+    }
+
+    fn contains(&self, position: VolumePosition) -> bool {
+        let position = [position.0, position.1, position.2];
+
+        let distance_squared: f32 = (0..3)
+            .map(|axis| {
+                let offset = position[axis] as f32 + 0.5 - self.center[axis];
+                offset * offset
+            })
+            .sum();
+
+        distance_squared < self.radius * self.radius
+    }
+}
+
+/// The occupied voxels of another graph, placed with its origin at `offset`.
+pub struct SvdagBrush<'a, S: ?Sized> {
+    pub svdag: &'a S,
+    pub offset: VolumePosition,
+}
+
+impl<'a, S> SvdagBrush<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    pub fn new(svdag: &'a S, offset: VolumePosition) -> SvdagBrush<'a, S> {
+        SvdagBrush { svdag, offset }
+    }
+
+    /// Checks whether the octant of the brush graph at `origin` with side `side` is known to be
+    /// empty. Only octants lined up with the brush's own octants can be looked up.
+    fn is_empty_octant(&self, origin: VolumePosition, side: usize) -> bool {
+        let depth = self.svdag.depth();
+        let pointer_word_count = self.svdag.pointer_format().word_count();
+        let mut node_index = 0;
+        let mut mirror = 0;
+
+        let mut level = depth as u32;
+        while 1 << level > side {
+            level -= 1;
+
+            let child_index = mirror_child_index(
+                (origin.0 >> level & 1) << 2
+                    | (origin.1 >> level & 1) << 1
+                    | (origin.2 >> level & 1),
+                mirror,
+            );
+
+            let node = match self.svdag.word(node_index) {
+                Some(word) => word.node(),
+                None => return false,
+            };
+
+            if !node.children.get(child_index) {
+                return true;
+            }
+
+            if level == 0 {
+                return false;
+            }
+
+            let child_pointer_index =
+                node_index + 1 + node.children.get_n(child_index) * pointer_word_count;
+            match self.svdag.read_mirrored_pointer(child_pointer_index) {
+                Some((child_node_index, child_mirror)) => {
+                    node_index = child_node_index;
+                    mirror ^= child_mirror;
+                }
+                None => return false,
+            }
+        }
+
+        false
+    }
+}
+
+impl<'a, S> Brush for SvdagBrush<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    fn classify(&self, origin: VolumePosition, side: usize) -> OctantCoverage {
+        if self.svdag.depth() == 0 || self.svdag.depth() as u32 >= usize::BITS {
+            return OctantCoverage::Empty;
+        }
+
+        let brush = BoxBrush::new(
+            self.offset,
+            (
+                self.offset.0 + (1 << self.svdag.depth()),
+                self.offset.1 + (1 << self.svdag.depth()),
+                self.offset.2 + (1 << self.svdag.depth()),
+            ),
+        );
+
+        match brush.classify(origin, side) {
+            OctantCoverage::Empty => OctantCoverage::Empty,
+            OctantCoverage::Full => {
+                let local_origin = (
+                    origin.0 - self.offset.0,
+                    origin.1 - self.offset.1,
+                    origin.2 - self.offset.2,
+                );
+                let is_aligned = local_origin.0.is_multiple_of(side)
+                    && local_origin.1.is_multiple_of(side)
+                    && local_origin.2.is_multiple_of(side);
+
+                if is_aligned && self.is_empty_octant(local_origin, side) {
+                    OctantCoverage::Empty
+                } else {
+                    OctantCoverage::Partial
+                }
+            }
+            OctantCoverage::Partial => OctantCoverage::Partial,
+        }
+    }
+
+    fn contains(&self, position: VolumePosition) -> bool {
+        if position.0 < self.offset.0 || position.1 < self.offset.1 || position.2 < self.offset.2 {
+            return false;
+        }
+
+        let local_position = (
+            position.0 - self.offset.0,
+            position.1 - self.offset.1,
+            position.2 - self.offset.2,
+        );
+
+        self.svdag.get(local_position) == Some(true)
+    }
+}
+
+/// The part of `brush` inside `bounds`, keeping edits within the dimensions of a volume.
+pub(crate) struct ClippedBrush<'a, B: ?Sized> {
+    pub brush: &'a B,
+    pub bounds: BoxBrush,
+}
+
+impl<'a, B> Brush for ClippedBrush<'a, B>
+where
+    B: Brush + ?Sized,
+{
+    fn classify(&self, origin: VolumePosition, side: usize) -> OctantCoverage {
+        match self.bounds.classify(origin, side) {
+            OctantCoverage::Empty => OctantCoverage::Empty,
+            OctantCoverage::Full => self.brush.classify(origin, side),
+            OctantCoverage::Partial => match self.brush.classify(origin, side) {
+                OctantCoverage::Empty => OctantCoverage::Empty,
+                _ => OctantCoverage::Partial,
+            },
+        }
+    }
+
+    fn contains(&self, position: VolumePosition) -> bool {
+        self.bounds.contains(position) && self.brush.contains(position)
+    }
+}
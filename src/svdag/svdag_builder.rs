@@ -1,20 +1,52 @@
-use super::{Svdag, SvdagNode, SvdagPointer, SvdagValue};
+use super::{
+    NodeId, NodeTable, PointerFormat, Svdag, SvdagError, SvdagNode, SvdagValue, TableNode,
+};
 
 use crate::{
     hashed_volume::HashedVolume,
-    volume::{DensityVolume, IsVolume, VolumeIndex, VolumePosition},
+    volume::{DensityVolume, IsVolume, VolumePosition},
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{BuildHasher, BuildHasherDefault},
 };
-use std::collections::HashMap;
 
-pub struct SvdagBuilder {
+/// Candidate graph nodes for a `(layer index, subtree hash)` pair. Hashes only narrow down
+/// the search, a candidate is reused only once its content compares equal.
+type NodeCandidates = HashMap<(usize, u64), Vec<NodeContent>>;
+
+/// A written graph node by its child mask and the graph indices of its children. Children are
+/// deduplicated before their parent, so equal content means equal subtrees.
+struct NodeContent {
+    child_bits: u8,
+    child_indices: [usize; 8],
+    node_index: usize,
+}
+
+pub struct SvdagBuilder<S = BuildHasherDefault<DefaultHasher>> {
+    hash_builder: S,
+    pointer_format: Option<PointerFormat>,
+    symmetry: bool,
     hash_volume_layers: Vec<HashedVolume>,
-    node_hashes: HashMap<u64, VolumeIndex>,
+    node_hashes: NodeCandidates,
     graph: Svdag,
 }
 
 impl SvdagBuilder {
     pub fn new() -> SvdagBuilder {
+        SvdagBuilder::with_hasher(BuildHasherDefault::default())
+    }
+}
+
+impl<S> SvdagBuilder<S>
+where
+    S: BuildHasher,
+{
+    pub fn with_hasher(hash_builder: S) -> SvdagBuilder<S> {
         SvdagBuilder {
+            hash_builder,
+            pointer_format: None,
+            symmetry: false,
             hash_volume_layers: Vec::new(),
             node_hashes: HashMap::new(),
             graph: Svdag::new(),
@@ -22,125 +54,207 @@ impl SvdagBuilder {
     }
 
     pub fn create_layers(&mut self, volume: &DensityVolume) -> &mut Self {
-        println!("Volume dimensions: {:?}", volume.get_dimensions());
         self.graph.depth = volume.depth;
+        self.graph.dimensions = volume.get_dimensions();
 
-        let mut hashed_volume = HashedVolume::from(volume);
+        let mut hashed_volume =
+            HashedVolume::from_density_volume_with_hasher(volume, &self.hash_builder);
 
-        loop {
-            let new_hashed_volume = HashedVolume::from_hashed_volume(&hashed_volume);
+        self.hash_volume_layers.clear();
+
+        while hashed_volume.depth > 0 {
+            let new_hashed_volume =
+                HashedVolume::from_hashed_volume_with_hasher(&hashed_volume, &self.hash_builder);
 
             self.hash_volume_layers.push(hashed_volume);
 
             hashed_volume = new_hashed_volume;
-
-            if hashed_volume.get_dimensions().0 == 1 {
-                self.hash_volume_layers.push(hashed_volume);
-                break;
-            }
         }
+        self.hash_volume_layers.push(hashed_volume);
         self.hash_volume_layers.reverse();
 
         self
     }
 
-    pub fn create_graph(&mut self) -> &mut Self {
+    /// Forces a pointer format, by default the narrowest format that fits the graph is used.
+    pub fn pointer_format(&mut self, pointer_format: PointerFormat) -> &mut Self {
+        self.pointer_format = Some(pointer_format);
+        self
+    }
+
+    /// Also shares subtrees that are reflections of each other along any combination of axes.
+    /// Graphs with reflected subtrees need the `Mirrored32` pointer format.
+    pub fn symmetry(&mut self, symmetry: bool) -> &mut Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    pub fn create_graph(&mut self) -> Result<&mut Self, SvdagError> {
+        if self.symmetry {
+            self.graph = self.build_symmetric_graph()?;
+            return Ok(self);
+        }
+
+        let graph = match self.pointer_format {
+            Some(pointer_format) => self.build_graph(pointer_format)?,
+            //Prefer compact relative pointers and only widen them once the graph outgrows them
+            None => match self.build_graph(PointerFormat::Relative16) {
+                Err(SvdagError::PointerOverflow { .. }) => {
+                    self.build_graph(PointerFormat::Absolute32)?
+                }
+                result => result?,
+            },
+        };
+        self.graph = graph;
+
+        Ok(self)
+    }
+
+    fn build_graph(&mut self, pointer_format: PointerFormat) -> Result<Svdag, SvdagError> {
         let mut graph = Svdag::new();
         graph.depth = self.graph.depth;
+        graph.dimensions = self.graph.dimensions;
+        graph.pointer_format = pointer_format;
 
-        let mut node_hashes: HashMap<u64, usize> = HashMap::new();
+        let mut node_hashes = NodeCandidates::new();
 
-        self.recurse_layers(&mut graph, &mut node_hashes, 0, (0, 0, 0));
-        self.graph = graph;
+        self.recurse_layers(&mut graph, &mut node_hashes, 0, (0, 0, 0))?;
         self.node_hashes = node_hashes;
 
-        self
+        Ok(graph)
+    }
+
+    fn build_symmetric_graph(&self) -> Result<Svdag, SvdagError> {
+        let mut table = NodeTable::new();
+
+        //The root has no parent pointer to hold a reflection, so it's stored as is
+        let root = self.insert_symmetric_node(&mut table, 0, (0, 0, 0))?;
+        let root = table.insert(table.node(root.0).mirrored(root.1))?;
+
+        let mut graph = table.to_svdag(root, self.pointer_format)?;
+        graph.dimensions = self.graph.dimensions;
+
+        Ok(graph)
+    }
+
+    /// Stores the subtree at `position` in layer `layer_index` up to reflection, returning the id
+    /// of the stored node and the reflection that turns it back into the subtree.
+    fn insert_symmetric_node(
+        &self,
+        table: &mut NodeTable,
+        layer_index: usize,
+        position: VolumePosition,
+    ) -> Result<(NodeId, u8), SvdagError> {
+        let layer = &self.hash_volume_layers[layer_index];
+        let node = layer.get(position);
+        let height = (self.hash_volume_layers.len() - layer_index) as u8;
+
+        let mut table_node = TableNode::empty(height);
+        table_node.children = node.children;
+
+        if height > 1 {
+            let children_positions = layer.calculate_children_positions(position);
+
+            for (child_index, child_position) in children_positions.iter().enumerate() {
+                if node.children.get(child_index) {
+                    let (child_id, child_mirror) =
+                        self.insert_symmetric_node(table, layer_index + 1, *child_position)?;
+
+                    table_node.child_ids[child_index] = child_id;
+                    table_node.child_mirrors[child_index] = child_mirror;
+                }
+            }
+        }
+
+        table.insert_symmetric(table_node)
     }
 
     fn recurse_layers(
         &self,
         new_graph: &mut Svdag,
-        node_hashes: &mut HashMap<u64, usize>,
+        node_hashes: &mut NodeCandidates,
         layer_index: usize,
         position: VolumePosition,
-    ) -> i16 {
+    ) -> Result<usize, SvdagError> {
         if layer_index >= self.hash_volume_layers.len() {
-            return 0;
+            return Ok(0);
         }
 
         let layer = self.hash_volume_layers.get(layer_index).unwrap();
         let node = layer.get(position);
+        let children_positions = layer.calculate_children_positions(position);
+        let children_count = node.children.count_occupied();
 
-        let duplicate_node: Option<&usize>;
+        //Store index of this node to return to parent after recursing trough all the node's children
+        let current_node_absolute_index = new_graph.nodes.len();
 
-        //Check if this a new node
-        duplicate_node = node_hashes.get(&node.hash);
+        //Preamptively push the node in the array so it maintains the parent index < child index rule
+        new_graph.nodes.push(SvdagValue::from_node(SvdagNode {
+            children: node.children,
+            padding: 0,
+        }));
 
-        //If checked node is new
-        if duplicate_node.is_none() {
-            let children_positions = layer.calculate_children_positions(position);
-            let children_count = node.children.count_occupied();
+        let mut child_indices = [0; 8];
 
-            //Store index of this node to return to parent after recursing trough all the node's children
-            let current_node_absolute_index = new_graph.nodes.len();
+        //Iterate over all hash layers to build the complete tree, +1 is because we don't need nodes for leaf children
+        if layer_index + 1 < self.hash_volume_layers.len() {
+            //Reserve space for all children
+            let pointer_word_count = new_graph.pointer_format.word_count();
+            new_graph.nodes.resize(
+                new_graph.nodes.len() + children_count * pointer_word_count,
+                SvdagValue::default(),
+            );
 
-            //Hash this node and store it's index for fast checking of duplicates
-            node_hashes.insert(node.hash, current_node_absolute_index);
+            let mut child_index_offset = 1; //Relative offset where in array to store child pointers
 
-            //Preamptively push the node in the array so it maintains the parent index < child index rule
-            new_graph.nodes.push(SvdagValue {
-                node: SvdagNode {
-                    children: node.children,
-                    padding: 0,
-                },
-            });
-
-            //Iterate over all hash layers to build the complete tree, +1 is because we don't need nodes for leaf children
-            if layer_index + 1 < self.hash_volume_layers.len() {
-                //Reserve space for all children
-                for _ in 0..children_count {
-                    new_graph.nodes.push(SvdagValue {
-                        pointer: SvdagPointer { value: 0 },
-                    })
+            //Recurse trough all children
+            for (child_position_index, child_index) in child_indices.iter_mut().enumerate() {
+                //Go to next child if this one is not occupied
+                if !node.children.get(child_position_index) {
+                    continue;
                 }
 
-                let mut child_index_offset = 1; //Relative offset where in array to store child pointers
-
-                //Recurse trough all children
-                for (child_position_index, _) in children_positions.iter().enumerate() {
-                    //Go to next child if this one is not occupied
-                    if !node.children.get(child_position_index) {
-                        continue;
-                    }
-
-                    //Recursively get this childs absolute array index
-                    let child_node_absolute_index = self.recurse_layers(
-                        new_graph,
-                        node_hashes,
-                        layer_index + 1,
-                        children_positions[child_position_index],
-                    );
-
-                    //Get child's index by adding the child offset to the this node's absolute index
-                    let child_offset_index =
-                        (current_node_absolute_index + child_index_offset) as isize;
-
-                    //Store a relative offset to the child node at the calculated child offset index
-                    new_graph.nodes[child_offset_index as usize] = SvdagValue {
-                        pointer: SvdagPointer {
-                            value: (child_node_absolute_index as isize - child_offset_index) as i16,
-                        },
-                    };
-
-                    child_index_offset += 1;
-                }
-            }
+                //Recursively get this childs absolute array index
+                let child_node_absolute_index = self.recurse_layers(
+                    new_graph,
+                    node_hashes,
+                    layer_index + 1,
+                    children_positions[child_position_index],
+                )?;
 
-            current_node_absolute_index as i16
+                //Get child's index by adding the child offset to the this node's absolute index
+                let child_offset_index = current_node_absolute_index + child_index_offset;
+
+                //Store a pointer to the child node at the calculated child offset index
+                new_graph.write_pointer(child_offset_index, child_node_absolute_index)?;
+
+                *child_index = child_node_absolute_index;
+                child_index_offset += pointer_word_count;
+            }
         }
-        //If checked node is a duplicate
-        else {
-            *duplicate_node.unwrap() as i16
+
+        //Check if this a new node, a matching hash is only a hint so compare the content as well
+        let candidates = node_hashes.entry((layer_index, node.hash)).or_default();
+        let duplicate_node = candidates.iter().find(|candidate| {
+            candidate.child_bits == node.children.child_bits
+                && candidate.child_indices == child_indices
+        });
+
+        if let Some(duplicate_node) = duplicate_node {
+            //A duplicate only has duplicate children, which wrote nothing, so dropping this
+            //node's own words restores the array
+            new_graph.nodes.truncate(current_node_absolute_index);
+
+            Ok(duplicate_node.node_index)
+        } else {
+            //Store this node's content under its hash for fast checking of duplicates
+            candidates.push(NodeContent {
+                child_bits: node.children.child_bits,
+                child_indices,
+                node_index: current_node_absolute_index,
+            });
+
+            Ok(current_node_absolute_index)
         }
     }
 
@@ -148,3 +262,9 @@ impl SvdagBuilder {
         self.graph.clone()
     }
 }
+
+impl Default for SvdagBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,231 @@
+use super::{
+    svdag_brush::ClippedBrush, svdag_node_table::child_origin, svdag_read::cube_dimensions,
+    BoxBrush, Brush, NodeId, NodeTable, OctantCoverage, PointerFormat, Svdag, SvdagError,
+    SvdagRead, TableNode,
+};
+use crate::volume::{VolumeDimensions, VolumePosition};
+
+/// Edits a graph in place of rebuilding it from a dense volume.
+///
+/// Every edit copies only the nodes on the paths to the changed voxels, everything else stays
+/// shared with the previous version. New nodes go through the `NodeTable`, so the result is as
+/// deduplicated as a fresh build. Reflected subtrees on an edited path are stored unreflected.
+pub struct SvdagEditor {
+    depth: u8,
+    dimensions: VolumeDimensions,
+    pointer_format: Option<PointerFormat>,
+    table: NodeTable,
+    /// The root node and its reflection, `None` while the whole volume is empty.
+    root: Option<(NodeId, u8)>,
+}
+
+impl SvdagEditor {
+    /// Starts from an empty volume of side `2^depth`.
+    pub fn new(depth: u8) -> Result<SvdagEditor, SvdagError> {
+        if depth == 0 || depth as u32 >= usize::BITS {
+            return Err(SvdagError::InvalidDepth(depth));
+        }
+
+        Ok(SvdagEditor {
+            depth,
+            dimensions: cube_dimensions(depth),
+            pointer_format: None,
+            table: NodeTable::new(),
+            root: None,
+        })
+    }
+
+    pub fn from_svdag<S>(svdag: &S) -> Result<SvdagEditor, SvdagError>
+    where
+        S: SvdagRead + ?Sized,
+    {
+        let mut editor = SvdagEditor::new(svdag.depth())?;
+        editor.dimensions = svdag.dimensions();
+
+        let root = editor.table.insert_svdag(svdag)?;
+        if editor.table.node(root).children.have_occupied_children() {
+            editor.root = Some((root, 0));
+        }
+
+        Ok(editor)
+    }
+
+    /// Forces a pointer format, by default the narrowest format that fits the graph is used.
+    pub fn pointer_format(&mut self, pointer_format: PointerFormat) -> &mut Self {
+        self.pointer_format = Some(pointer_format);
+        self
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Logical extent of the volume, edits never reach past it.
+    pub fn dimensions(&self) -> VolumeDimensions {
+        self.dimensions
+    }
+
+    /// The table holding every node created so far, including ones no longer reachable until
+    /// `collect_garbage` drops them.
+    pub fn table(&self) -> &NodeTable {
+        &self.table
+    }
+
+    /// Looks up the voxel at `position`, returning `None` if it lies outside the volume.
+    pub fn get(&self, position: VolumePosition) -> Option<bool> {
+        self.check_position(position).ok()?;
+
+        match self.root {
+            Some((node_id, mirror)) => self.table.get(node_id, mirror, position),
+            None => Some(false),
+        }
+    }
+
+    /// Sets or clears the voxel at `position`.
+    pub fn set(&mut self, position: VolumePosition, value: bool) -> Result<(), SvdagError> {
+        self.check_position(position)?;
+
+        self.apply(&BoxBrush::voxel(position), value)
+    }
+
+    /// Occupies every voxel covered by `brush`, the parts outside of the dimensions are ignored.
+    pub fn fill(&mut self, brush: &impl Brush) -> Result<&mut Self, SvdagError> {
+        self.apply(brush, true)?;
+        Ok(self)
+    }
+
+    /// Empties every voxel covered by `brush`, the parts outside of the dimensions are ignored.
+    pub fn carve(&mut self, brush: &impl Brush) -> Result<&mut Self, SvdagError> {
+        self.apply(brush, false)?;
+        Ok(self)
+    }
+
+    /// Drops the nodes of earlier states that the current one no longer uses, returning how many
+    /// were freed. Edits only ever add nodes, so long editing sessions should call this now and
+    /// then.
+    pub fn collect_garbage(&mut self) -> usize {
+        let node_count = self.table.len();
+        let new_ids = self
+            .table
+            .retain_reachable(self.root.map(|(node_id, _)| node_id));
+
+        if let Some((node_id, _)) = &mut self.root {
+            *node_id = new_ids[node_id];
+        }
+
+        node_count - self.table.len()
+    }
+
+    /// Flattens the current state into an `Svdag`.
+    pub fn to_svdag(&mut self) -> Result<Svdag, SvdagError> {
+        let root = match self.root {
+            Some((node_id, mirror)) => {
+                let root = self.table.node(node_id).mirrored(mirror);
+                self.table.insert(root)?
+            }
+            //A completely empty volume still needs a root node to be a valid graph
+            None => self.table.insert(TableNode::empty(self.depth))?,
+        };
+
+        let mut svdag = self.table.to_svdag(root, self.pointer_format)?;
+        svdag.dimensions = self.dimensions;
+
+        Ok(svdag)
+    }
+
+    fn check_position(&self, position: VolumePosition) -> Result<(), SvdagError> {
+        let dimensions = self.dimensions;
+
+        if position.0 >= dimensions.0 || position.1 >= dimensions.1 || position.2 >= dimensions.2 {
+            Err(SvdagError::OutOfBounds { position })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn apply(&mut self, brush: &impl Brush, value: bool) -> Result<(), SvdagError> {
+        //The padding past the dimensions has to stay empty
+        let brush = ClippedBrush {
+            brush,
+            bounds: BoxBrush::new((0, 0, 0), self.dimensions),
+        };
+
+        self.root = edit_octant(
+            &mut self.table,
+            self.root,
+            self.depth,
+            (0, 0, 0),
+            &brush,
+            value,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Writes `value` into the voxels of the octant at `origin` covered by `brush`, returning the
+/// edited octant. `node` is the octant's current node and reflection, `None` if it's empty.
+pub(crate) fn edit_octant(
+    table: &mut NodeTable,
+    node: Option<(NodeId, u8)>,
+    height: u8,
+    origin: VolumePosition,
+    brush: &impl Brush,
+    value: bool,
+) -> Result<Option<(NodeId, u8)>, SvdagError> {
+    match brush.classify(origin, 1 << height) {
+        //Untouched octants keep their node, which is what keeps them shared
+        OctantCoverage::Empty => return Ok(node),
+        OctantCoverage::Full if value => return Ok(Some((table.insert_solid(height)?, 0))),
+        OctantCoverage::Full => return Ok(None),
+        OctantCoverage::Partial => {}
+    }
+
+    //Copy the node, applying its reflection so the child indices match the volume
+    let old_node = match node {
+        Some((node_id, mirror)) => table.node(node_id).mirrored(mirror),
+        None => TableNode::empty(height),
+    };
+    let mut new_node = old_node;
+
+    if height == 1 {
+        for child_index in 0..8 {
+            if brush.contains(child_origin(origin, child_index, 1)) {
+                new_node.children.set(child_index, value);
+            }
+        }
+    } else {
+        let half_size = 1 << (height - 1);
+
+        for child_index in 0..8 {
+            let child = if old_node.children.get(child_index) {
+                Some((
+                    old_node.child_ids[child_index],
+                    old_node.child_mirrors[child_index],
+                ))
+            } else {
+                None
+            };
+
+            let child = edit_octant(
+                table,
+                child,
+                height - 1,
+                child_origin(origin, child_index, half_size),
+                brush,
+                value,
+            )?;
+
+            new_node.children.set(child_index, child.is_some());
+            let (child_id, child_mirror) = child.unwrap_or((0, 0));
+            new_node.child_ids[child_index] = child_id;
+            new_node.child_mirrors[child_index] = child_mirror;
+        }
+    }
+
+    if new_node.children.have_occupied_children() {
+        Ok(Some((table.insert(new_node)?, 0)))
+    } else {
+        Ok(None)
+    }
+}
@@ -0,0 +1,154 @@
+use super::{
+    svdag_node_table::{child_origin, mirror_child_index},
+    svdag_read::is_full_node,
+    Svdag, SvdagRead,
+};
+use crate::volume::{DensityVolume, VolumePosition};
+use std::collections::HashMap;
+
+/// Expands the region `[min, max)` of the graph into a dense volume, see `SvdagRead::extract`.
+pub fn extract<S>(svdag: &S, min: VolumePosition, max: VolumePosition) -> DensityVolume
+where
+    S: SvdagRead + ?Sized,
+{
+    let dimensions = if svdag.depth() as u32 >= usize::BITS {
+        (0, 0, 0)
+    } else {
+        svdag.dimensions()
+    };
+    let max = (
+        max.0.min(dimensions.0),
+        max.1.min(dimensions.1),
+        max.2.min(dimensions.2),
+    );
+
+    let region = (
+        max.0.saturating_sub(min.0),
+        max.1.saturating_sub(min.1),
+        max.2.saturating_sub(min.2),
+    );
+    let mut volume = DensityVolume::with_dimensions(region);
+
+    if region.0 == 0 || region.1 == 0 || region.2 == 0 || svdag.depth() == 0 {
+        return volume;
+    }
+
+    let mut extractor = Extractor {
+        svdag,
+        min,
+        max,
+        full_nodes: HashMap::new(),
+        volume: &mut volume,
+    };
+    extractor.fill_node(0, 0, svdag.depth(), (0, 0, 0));
+
+    volume
+}
+
+struct Extractor<'a, S: ?Sized> {
+    svdag: &'a S,
+    min: VolumePosition,
+    max: VolumePosition,
+    /// Whether the node at an index is completely filled, so it can be written as a single box.
+    full_nodes: HashMap<usize, bool>,
+    volume: &'a mut DensityVolume,
+}
+
+impl<'a, S> Extractor<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    fn fill_node(&mut self, node_index: usize, mirror: u8, height: u8, origin: VolumePosition) {
+        let node = match self.svdag.word(node_index) {
+            Some(word) => word.node(),
+            None => return,
+        };
+
+        let half_size = 1usize << (height - 1);
+        let pointer_word_count = self.svdag.pointer_format().word_count();
+        let mut pointer_index = node_index + 1;
+
+        //Slots are visited in stored order, reflecting them gives the octant they cover
+        for stored_index in 0..8 {
+            if !node.children.get(stored_index) {
+                continue;
+            }
+
+            let child_index = mirror_child_index(stored_index, mirror);
+            let child_origin = child_origin(origin, child_index, half_size);
+            let child_pointer_index = pointer_index;
+            pointer_index += pointer_word_count;
+
+            //Octants outside of the region are skipped without reading their subtree
+            if !self.intersects(child_origin, half_size) {
+                continue;
+            }
+
+            if height == 1 {
+                self.fill_box(child_origin, 1);
+                continue;
+            }
+
+            let (child_node_index, child_mirror) =
+                match self.svdag.read_mirrored_pointer(child_pointer_index) {
+                    Some(child_pointer) => child_pointer,
+                    None => continue,
+                };
+
+            //Reflecting a node doesn't change whether it's full
+            if self.is_full(child_node_index, height - 1) {
+                self.fill_box(child_origin, half_size);
+            } else {
+                self.fill_node(
+                    child_node_index,
+                    mirror ^ child_mirror,
+                    height - 1,
+                    child_origin,
+                );
+            }
+        }
+    }
+
+    fn is_full(&mut self, node_index: usize, height: u8) -> bool {
+        is_full_node(self.svdag, &mut self.full_nodes, node_index, height)
+    }
+
+    fn intersects(&self, origin: VolumePosition, size: usize) -> bool {
+        origin.0 < self.max.0
+            && origin.1 < self.max.1
+            && origin.2 < self.max.2
+            && origin.0 + size > self.min.0
+            && origin.1 + size > self.min.1
+            && origin.2 + size > self.min.2
+    }
+
+    /// Sets every voxel of the cube at `origin` that lies inside the region.
+    fn fill_box(&mut self, origin: VolumePosition, size: usize) {
+        let from = (
+            origin.0.max(self.min.0),
+            origin.1.max(self.min.1),
+            origin.2.max(self.min.2),
+        );
+        let to = (
+            (origin.0 + size).min(self.max.0),
+            (origin.1 + size).min(self.max.1),
+            (origin.2 + size).min(self.max.2),
+        );
+
+        for z in from.2..to.2 {
+            for y in from.1..to.1 {
+                for x in from.0..to.0 {
+                    *self
+                        .volume
+                        .get_mut((x - self.min.0, y - self.min.1, z - self.min.2)) = true;
+                }
+            }
+        }
+    }
+}
+
+impl From<&Svdag> for DensityVolume {
+    fn from(svdag: &Svdag) -> Self {
+        extract(svdag, (0, 0, 0), (usize::MAX, usize::MAX, usize::MAX))
+    }
+}
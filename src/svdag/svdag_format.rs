@@ -0,0 +1,268 @@
+//! Binary file format of a serialized `Svdag`.
+//!
+//! Every value is stored little-endian, regardless of the machine that wrote the file.
+//!
+//! | Offset | Size  | Field                                                      |
+//! |--------|-------|------------------------------------------------------------|
+//! | 0      | 4     | Magic bytes `SVDG`                                         |
+//! | 4      | 2     | Format version, `1` or `2`                                 |
+//! | 6      | 1     | Depth of the volume                                        |
+//! | 7      | 1     | Pointer format, `0` = `Relative16`, `1` = `Absolute32`,    |
+//! |        |       | `2` = `Mirrored32`                                         |
+//! | 8      | 8     | Number of 16 bit node words `n`                            |
+//! | 16     | 4     | CRC-32 (IEEE) of the node words as stored in the file      |
+//! | 20     | 2 * n | Node words                                                 |
+//!
+//! Version `2` is only written for volumes that don't fill their whole cube. It inserts the
+//! dimensions of the volume as three `u32` values at offset 20, moving the node words to offset
+//! 32. Version `1` volumes fill the cube of side `2^depth`.
+
+use super::{svdag_read::cube_dimensions, PointerFormat, Svdag, SvdagError, SvdagRead, SvdagValue};
+use crate::volume::VolumeDimensions;
+use std::{
+    convert::TryFrom,
+    io::{Read, Write},
+};
+
+pub(crate) const MAGIC: [u8; 4] = *b"SVDG";
+/// Version of files whose volume fills its whole cube.
+pub(crate) const CUBIC_VERSION: u16 = 1;
+/// Version of files that store the dimensions of the volume.
+pub(crate) const DIMENSIONS_VERSION: u16 = 2;
+pub(crate) const HEADER_SIZE: usize = 20;
+pub(crate) const DIMENSIONS_HEADER_SIZE: usize = 32;
+
+/// Decoded header that precedes the node words.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SvdagHeader {
+    pub version: u16,
+    pub depth: u8,
+    pub dimensions: VolumeDimensions,
+    pub pointer_format: PointerFormat,
+    pub node_count: u64,
+    pub checksum: u32,
+}
+
+impl SvdagHeader {
+    /// Size in bytes of the header, which depends on its version.
+    pub fn size(&self) -> usize {
+        if self.version == DIMENSIONS_VERSION {
+            DIMENSIONS_HEADER_SIZE
+        } else {
+            HEADER_SIZE
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.size()];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[6] = self.depth;
+        bytes[7] = pointer_format_to_code(self.pointer_format);
+        bytes[8..16].copy_from_slice(&self.node_count.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.checksum.to_le_bytes());
+
+        if self.version == DIMENSIONS_VERSION {
+            bytes[20..24].copy_from_slice(&(self.dimensions.0 as u32).to_le_bytes());
+            bytes[24..28].copy_from_slice(&(self.dimensions.1 as u32).to_le_bytes());
+            bytes[28..32].copy_from_slice(&(self.dimensions.2 as u32).to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<SvdagHeader, SvdagError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(SvdagError::Truncated {
+                expected: HEADER_SIZE as u64,
+                actual: bytes.len() as u64,
+            });
+        }
+
+        let mut magic = [0; 4];
+        magic.copy_from_slice(&bytes[0..4]);
+        if magic != MAGIC {
+            return Err(SvdagError::InvalidMagic(magic));
+        }
+
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        let depth = bytes[6];
+        let dimensions = match version {
+            CUBIC_VERSION => cube_dimensions(depth),
+            DIMENSIONS_VERSION => {
+                let dimension_bytes = bytes.get(HEADER_SIZE..DIMENSIONS_HEADER_SIZE).ok_or(
+                    SvdagError::Truncated {
+                        expected: DIMENSIONS_HEADER_SIZE as u64,
+                        actual: bytes.len() as u64,
+                    },
+                )?;
+                let dimension = |offset: usize| {
+                    let mut dimension = [0; 4];
+                    dimension.copy_from_slice(&dimension_bytes[offset..offset + 4]);
+                    u32::from_le_bytes(dimension) as usize
+                };
+
+                (dimension(0), dimension(4), dimension(8))
+            }
+            _ => return Err(SvdagError::UnsupportedVersion(version)),
+        };
+
+        let mut node_count = [0; 8];
+        node_count.copy_from_slice(&bytes[8..16]);
+        let mut checksum = [0; 4];
+        checksum.copy_from_slice(&bytes[16..20]);
+
+        Ok(SvdagHeader {
+            version,
+            depth,
+            dimensions,
+            pointer_format: pointer_format_from_code(bytes[7])?,
+            node_count: u64::from_le_bytes(node_count),
+            checksum: u32::from_le_bytes(checksum),
+        })
+    }
+
+    /// Size in bytes of the node words that follow the header.
+    pub fn payload_size(&self) -> Result<usize, SvdagError> {
+        self.node_count
+            .checked_mul(2)
+            .and_then(|size| usize::try_from(size).ok())
+            .ok_or(SvdagError::InvalidNodeCount(self.node_count))
+    }
+}
+
+fn pointer_format_to_code(pointer_format: PointerFormat) -> u8 {
+    match pointer_format {
+        PointerFormat::Relative16 => 0,
+        PointerFormat::Absolute32 => 1,
+        PointerFormat::Mirrored32 => 2,
+    }
+}
+
+fn pointer_format_from_code(code: u8) -> Result<PointerFormat, SvdagError> {
+    match code {
+        0 => Ok(PointerFormat::Relative16),
+        1 => Ok(PointerFormat::Absolute32),
+        2 => Ok(PointerFormat::Mirrored32),
+        _ => Err(SvdagError::UnknownPointerFormat(code)),
+    }
+}
+
+/// CRC-32 with the IEEE polynomial, processed a byte at a time through `CRC32_TABLE`.
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc = (crc >> 8) ^ CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize];
+    }
+    !crc
+}
+
+/// Remainder of every byte value, so `crc32` doesn't have to divide bit by bit.
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+}
+
+impl Svdag {
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), SvdagError> {
+        let mut payload = Vec::with_capacity(self.nodes.len() * 2);
+        for value in &self.nodes {
+            payload.extend_from_slice(&value.bits.to_le_bytes());
+        }
+
+        //Cubic volumes keep the original header, so older readers can still load them
+        let dimensions = self.dimensions;
+        let version = if dimensions == cube_dimensions(self.depth) {
+            CUBIC_VERSION
+        } else if u32::try_from(dimensions.0.max(dimensions.1).max(dimensions.2)).is_ok() {
+            DIMENSIONS_VERSION
+        } else {
+            return Err(SvdagError::InvalidDimensions(dimensions));
+        };
+
+        let header = SvdagHeader {
+            version,
+            depth: self.depth,
+            dimensions,
+            pointer_format: self.pointer_format,
+            node_count: self.nodes.len() as u64,
+            checksum: crc32(&payload),
+        };
+
+        writer.write_all(&header.to_bytes())?;
+        writer.write_all(&payload)?;
+
+        Ok(())
+    }
+
+    pub fn read_from(reader: impl Read) -> Result<Svdag, SvdagError> {
+        let mut reader = reader;
+
+        let mut header_bytes = Vec::with_capacity(DIMENSIONS_HEADER_SIZE);
+        (&mut reader)
+            .take(HEADER_SIZE as u64)
+            .read_to_end(&mut header_bytes)?;
+
+        //The version decides whether the dimensions follow the fixed fields
+        if header_bytes.len() == HEADER_SIZE
+            && u16::from_le_bytes([header_bytes[4], header_bytes[5]]) == DIMENSIONS_VERSION
+        {
+            (&mut reader)
+                .take((DIMENSIONS_HEADER_SIZE - HEADER_SIZE) as u64)
+                .read_to_end(&mut header_bytes)?;
+        }
+
+        let header = SvdagHeader::from_bytes(&header_bytes)?;
+        let header_size = header.size();
+
+        //Don't trust the node count for allocations, let the buffer grow with the data actually read
+        let payload_size = header.payload_size()?;
+        let mut payload = Vec::new();
+        reader.take(payload_size as u64).read_to_end(&mut payload)?;
+
+        if payload.len() < payload_size {
+            //A corrupt node count can claim almost all of `u64`, so the total mustn't overflow
+            return Err(SvdagError::Truncated {
+                expected: (header_size as u64).saturating_add(payload_size as u64),
+                actual: (header_size as u64).saturating_add(payload.len() as u64),
+            });
+        }
+
+        let checksum = crc32(&payload);
+        if checksum != header.checksum {
+            return Err(SvdagError::ChecksumMismatch {
+                expected: header.checksum,
+                actual: checksum,
+            });
+        }
+
+        let svdag = Svdag {
+            depth: header.depth,
+            dimensions: header.dimensions,
+            pointer_format: header.pointer_format,
+            nodes: payload
+                .chunks_exact(2)
+                .map(|word| SvdagValue {
+                    bits: u16::from_le_bytes([word[0], word[1]]),
+                })
+                .collect(),
+        };
+        svdag.validate()?;
+
+        Ok(svdag)
+    }
+}
@@ -0,0 +1,202 @@
+use super::{
+    svdag_node_table::{child_origin, mirror_child_index},
+    SvdagRead,
+};
+use crate::volume::VolumePosition;
+
+/// An occupied octant waiting to be visited.
+#[derive(Copy, Clone, Debug)]
+struct Octant {
+    /// Node of the octant, for voxels the leaf holding them.
+    node_index: usize,
+    mirror: u8,
+    origin: VolumePosition,
+    level: u8,
+}
+
+/// Depth-first walk over the occupied octants of one level that overlap a region.
+///
+/// Children are pushed in reverse child index order, so octants come out in Morton order. Only
+/// children set in the masks are ever pushed, empty space is never visited.
+struct OctantWalker<'a, S: ?Sized> {
+    svdag: &'a S,
+    level: u8,
+    min: VolumePosition,
+    /// Exclusive end of the region, clipped to the dimensions.
+    max: VolumePosition,
+    stack: Vec<Octant>,
+}
+
+impl<'a, S> OctantWalker<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    fn new(
+        svdag: &'a S,
+        level: u8,
+        min: VolumePosition,
+        max: VolumePosition,
+    ) -> OctantWalker<'a, S> {
+        let depth = svdag.depth();
+        let dimensions = svdag.dimensions();
+        let mut walker = OctantWalker {
+            svdag,
+            level,
+            min,
+            max: (
+                max.0.min(dimensions.0),
+                max.1.min(dimensions.1),
+                max.2.min(dimensions.2),
+            ),
+            stack: Vec::new(),
+        };
+
+        let has_children = svdag
+            .word(0)
+            .is_some_and(|word| word.node().children.have_occupied_children());
+        if depth > 0
+            && (depth as u32) < usize::BITS
+            && level <= depth
+            && has_children
+            && walker.overlaps((0, 0, 0), depth)
+        {
+            walker.stack.push(Octant {
+                node_index: 0,
+                mirror: 0,
+                origin: (0, 0, 0),
+                level: 0,
+            });
+        }
+
+        walker
+    }
+
+    /// Whether the octant of side `2^height` at `origin` overlaps the region.
+    fn overlaps(&self, origin: VolumePosition, height: u8) -> bool {
+        let side_size = 1usize << height;
+
+        origin.0 < self.max.0
+            && origin.1 < self.max.1
+            && origin.2 < self.max.2
+            && origin.0 + side_size > self.min.0
+            && origin.1 + side_size > self.min.1
+            && origin.2 + side_size > self.min.2
+    }
+
+    fn next_octant(&mut self) -> Option<(Octant, usize)> {
+        let depth = self.svdag.depth();
+        let pointer_word_count = self.svdag.pointer_format().word_count();
+
+        while let Some(octant) = self.stack.pop() {
+            let height = depth - octant.level;
+            if octant.level == self.level {
+                return Some((octant, 1 << height));
+            }
+
+            let children = match self.svdag.word(octant.node_index) {
+                Some(word) => word.node().children,
+                None => continue,
+            };
+
+            for child_index in (0..8).rev() {
+                let stored_index = mirror_child_index(child_index, octant.mirror);
+                if !children.get(stored_index) {
+                    continue;
+                }
+
+                let origin = child_origin(octant.origin, child_index, 1 << (height - 1));
+                if !self.overlaps(origin, height - 1) {
+                    continue;
+                }
+
+                //Voxels have no node of their own, they keep pointing at their leaf
+                let (node_index, mirror) = if height == 1 {
+                    (octant.node_index, octant.mirror)
+                } else {
+                    let child_pointer_index =
+                        octant.node_index + 1 + children.get_n(stored_index) * pointer_word_count;
+                    match self.svdag.read_mirrored_pointer(child_pointer_index) {
+                        Some((node_index, child_mirror)) => {
+                            (node_index, octant.mirror ^ child_mirror)
+                        }
+                        None => continue,
+                    }
+                };
+
+                self.stack.push(Octant {
+                    node_index,
+                    mirror,
+                    origin,
+                    level: octant.level + 1,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator over the positions of the occupied voxels in Morton order, see
+/// `SvdagRead::iter_occupied`.
+pub struct OccupiedVoxels<'a, S: ?Sized> {
+    walker: OctantWalker<'a, S>,
+}
+
+impl<'a, S> OccupiedVoxels<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    pub(crate) fn new(svdag: &'a S, min: VolumePosition, max: VolumePosition) -> Self {
+        OccupiedVoxels {
+            walker: OctantWalker::new(svdag, svdag.depth(), min, max),
+        }
+    }
+}
+
+impl<'a, S> Iterator for OccupiedVoxels<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    type Item = VolumePosition;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walker.next_octant().map(|(octant, _)| octant.origin)
+    }
+}
+
+/// Iterator over the occupied octants of one level in Morton order as their origin, side size
+/// and node index, see `SvdagRead::iter_nodes_at_level`.
+pub struct OccupiedNodes<'a, S: ?Sized> {
+    walker: OctantWalker<'a, S>,
+}
+
+impl<'a, S> OccupiedNodes<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    pub(crate) fn new(svdag: &'a S, level: u8, min: VolumePosition, max: VolumePosition) -> Self {
+        //Voxels have no nodes, so there is nothing to yield at the depth
+        let level = if level < svdag.depth() {
+            level
+        } else {
+            u8::MAX
+        };
+
+        OccupiedNodes {
+            walker: OctantWalker::new(svdag, level, min, max),
+        }
+    }
+}
+
+impl<'a, S> Iterator for OccupiedNodes<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    type Item = (VolumePosition, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walker
+            .next_octant()
+            .map(|(octant, side_size)| (octant.origin, side_size, octant.node_index))
+    }
+}
@@ -0,0 +1,280 @@
+use super::SvdagError;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+/// An indexed triangle mesh.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TriangleMesh {
+    pub vertices: Vec<[f32; 3]>,
+    /// Vertex indices of every triangle.
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    pub fn new() -> TriangleMesh {
+        TriangleMesh::default()
+    }
+
+    /// Corner positions of the triangle at `index`.
+    pub fn triangle(&self, index: usize) -> [[f32; 3]; 3] {
+        let [a, b, c] = self.triangles[index];
+
+        [self.vertices[a], self.vertices[b], self.vertices[c]]
+    }
+
+    /// Smallest and largest coordinates of the vertices used by triangles, `None` without triangles.
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let mut vertices = self
+            .triangles
+            .iter()
+            .flatten()
+            .map(|index| self.vertices[*index]);
+        let first = vertices.next()?;
+
+        Some(vertices.fold((first, first), |(mut min, mut max), vertex| {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex[axis]);
+                max[axis] = max[axis].max(vertex[axis]);
+            }
+            (min, max)
+        }))
+    }
+
+    /// Reads the vertices and faces of a Wavefront OBJ file, everything else is ignored.
+    /// Polygons are split into triangle fans.
+    pub fn read_obj(reader: impl Read) -> Result<TriangleMesh, SvdagError> {
+        let mut mesh = TriangleMesh::new();
+
+        for (line_index, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let invalid = |reason: &str| {
+                SvdagError::InvalidMesh(format!("line {}: {}", line_index + 1, reason))
+            };
+
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let mut vertex = [0.0; 3];
+                    for coordinate in vertex.iter_mut() {
+                        *coordinate = tokens
+                            .next()
+                            .and_then(|token| token.parse().ok())
+                            .ok_or_else(|| invalid("expected three vertex coordinates"))?;
+                    }
+                    mesh.vertices.push(vertex);
+                }
+                Some("f") => {
+                    let face = tokens
+                        .map(|token| obj_vertex_index(token, mesh.vertices.len()))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| invalid("face refers to a missing vertex"))?;
+
+                    if face.len() < 3 {
+                        return Err(invalid("face has fewer than three vertices"));
+                    }
+
+                    for corner in 1..face.len() - 1 {
+                        mesh.triangles
+                            .push([face[0], face[corner], face[corner + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(mesh)
+    }
+
+    /// Reads a binary or ASCII STL file. STL stores every triangle with its own corners, so
+    /// vertices aren't shared.
+    pub fn read_stl(mut reader: impl Read) -> Result<TriangleMesh, SvdagError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        //Binary files may start with `solid` too, the exact size tells them apart
+        let is_binary = bytes.get(80..84).is_some_and(|count| {
+            let triangle_count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]);
+            84 + triangle_count as u64 * 50 == bytes.len() as u64
+        });
+
+        if is_binary || !bytes.starts_with(b"solid") {
+            read_binary_stl(&bytes)
+        } else {
+            read_ascii_stl(&bytes)
+        }
+    }
+
+    /// Writes the mesh as a Wavefront OBJ file.
+    pub fn write_obj(&self, writer: impl Write) -> Result<(), SvdagError> {
+        let mut writer = BufWriter::new(writer);
+
+        for vertex in self.vertices.iter() {
+            writeln!(writer, "v {} {} {}", vertex[0], vertex[1], vertex[2])?;
+        }
+
+        //OBJ indices count from 1
+        for triangle in self.triangles.iter() {
+            writeln!(
+                writer,
+                "f {} {} {}",
+                triangle[0] + 1,
+                triangle[1] + 1,
+                triangle[2] + 1
+            )?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the mesh as a binary little endian PLY file.
+    pub fn write_ply(&self, writer: impl Write) -> Result<(), SvdagError> {
+        if self.vertices.len() > u32::MAX as usize {
+            return Err(SvdagError::InvalidMesh(format!(
+                "{} vertices don't fit into 32 bit PLY indices",
+                self.vertices.len()
+            )));
+        }
+
+        let mut writer = BufWriter::new(writer);
+
+        write!(
+            writer,
+            "ply\n\
+             format binary_little_endian 1.0\n\
+             element vertex {}\n\
+             property float x\n\
+             property float y\n\
+             property float z\n\
+             element face {}\n\
+             property list uchar uint vertex_indices\n\
+             end_header\n",
+            self.vertices.len(),
+            self.triangles.len()
+        )?;
+
+        for vertex in self.vertices.iter() {
+            for coordinate in vertex.iter() {
+                writer.write_all(&coordinate.to_le_bytes())?;
+            }
+        }
+
+        for triangle in self.triangles.iter() {
+            writer.write_all(&[3])?;
+            for index in triangle.iter() {
+                writer.write_all(&(*index as u32).to_le_bytes())?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn push_triangle(&mut self, corners: [[f32; 3]; 3]) {
+        let first_index = self.vertices.len();
+
+        self.vertices.extend_from_slice(&corners);
+        self.triangles
+            .push([first_index, first_index + 1, first_index + 2]);
+    }
+}
+
+/// Resolves a face corner like `3`, `3/1/2` or `-1` to a vertex index.
+fn obj_vertex_index(token: &str, vertex_count: usize) -> Option<usize> {
+    let index: isize = token.split('/').next()?.parse().ok()?;
+
+    //Positive indices count from 1, negative ones back from the latest vertex
+    let index = match index {
+        0 => return None,
+        index if index > 0 => index as usize - 1,
+        index => vertex_count.checked_sub(index.unsigned_abs())?,
+    };
+
+    Some(index).filter(|index| *index < vertex_count)
+}
+
+fn read_binary_stl(bytes: &[u8]) -> Result<TriangleMesh, SvdagError> {
+    if bytes.len() < 84 {
+        return Err(SvdagError::Truncated {
+            expected: 84,
+            actual: bytes.len() as u64,
+        });
+    }
+
+    let triangle_count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as u64;
+    let expected = 84 + triangle_count * 50;
+    if (bytes.len() as u64) < expected {
+        return Err(SvdagError::Truncated {
+            expected,
+            actual: bytes.len() as u64,
+        });
+    }
+
+    let mut mesh = TriangleMesh::new();
+
+    //Every record is a normal, three corners and a 2 byte attribute, the normal is recomputed anyway
+    for record in bytes[84..expected as usize].chunks_exact(50) {
+        let coordinate = |offset: usize| {
+            f32::from_le_bytes([
+                record[offset],
+                record[offset + 1],
+                record[offset + 2],
+                record[offset + 3],
+            ])
+        };
+
+        let mut corners = [[0.0; 3]; 3];
+        for (corner_index, corner) in corners.iter_mut().enumerate() {
+            for (axis, coordinate_value) in corner.iter_mut().enumerate() {
+                *coordinate_value = coordinate(12 + corner_index * 12 + axis * 4);
+            }
+        }
+        mesh.push_triangle(corners);
+    }
+
+    Ok(mesh)
+}
+
+fn read_ascii_stl(bytes: &[u8]) -> Result<TriangleMesh, SvdagError> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| SvdagError::InvalidMesh("ASCII STL isn't valid UTF-8".to_string()))?;
+
+    let mut mesh = TriangleMesh::new();
+    let mut corners = Vec::with_capacity(3);
+
+    for (line_index, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("vertex") => {
+                let mut corner = [0.0; 3];
+                for coordinate in corner.iter_mut() {
+                    *coordinate = tokens
+                        .next()
+                        .and_then(|token| token.parse().ok())
+                        .ok_or_else(|| {
+                            SvdagError::InvalidMesh(format!(
+                                "line {}: expected three vertex coordinates",
+                                line_index + 1
+                            ))
+                        })?;
+                }
+                corners.push(corner);
+            }
+            Some("endloop") => {
+                if corners.len() != 3 {
+                    return Err(SvdagError::InvalidMesh(format!(
+                        "line {}: facet has {} vertices instead of 3",
+                        line_index + 1,
+                        corners.len()
+                    )));
+                }
+
+                mesh.push_triangle([corners[0], corners[1], corners[2]]);
+                corners.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(mesh)
+}
@@ -0,0 +1,772 @@
+use super::{
+    svdag_node_table::mirror_child_index, svdag_read::is_full_node, SvdagError, SvdagRead,
+    TriangleMesh,
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// Deepest graph whose corner coordinates fit the signed positions used while meshing.
+const MAX_DEPTH: u8 = 60;
+
+type Position = [i64; 3];
+
+/// Turns graphs into triangle meshes.
+///
+/// Surfaces are found by walking pairs of neighbouring octants through the graph. Octants on
+/// both sides of a face that are completely empty or completely full end the walk right away,
+/// so the work grows with the area of the surface instead of the volume.
+pub struct SvdagMesher {
+    greedy: bool,
+    origin: [f32; 3],
+    voxel_size: f32,
+}
+
+impl SvdagMesher {
+    pub fn new() -> SvdagMesher {
+        SvdagMesher {
+            greedy: false,
+            origin: [0.0; 3],
+            voxel_size: 1.0,
+        }
+    }
+
+    /// Merges coplanar voxel faces into larger rectangles. Fewer triangles, but the merged
+    /// faces no longer share all vertices with their neighbours.
+    pub fn greedy(&mut self, greedy: bool) -> &mut Self {
+        self.greedy = greedy;
+        self
+    }
+
+    /// Maps the voxel position `p` to `origin + p * voxel_size` in the mesh.
+    pub fn placement(&mut self, origin: [f32; 3], voxel_size: f32) -> &mut Self {
+        self.origin = origin;
+        self.voxel_size = voxel_size;
+        self
+    }
+
+    /// Mesh of the faces between occupied voxels and empty ones, including the outside of the
+    /// graph's dimensions.
+    pub fn voxel_faces<S>(&self, svdag: &S) -> Result<TriangleMesh, SvdagError>
+    where
+        S: SvdagRead + ?Sized,
+    {
+        let mut quads = Vec::new();
+        walk_faces(svdag, |quad| quads.push(quad))?;
+
+        if self.greedy {
+            quads = merge_quads(quads);
+        }
+
+        let mut mesh_builder = MeshBuilder::new(self);
+        for quad in quads.iter() {
+            if self.greedy {
+                mesh_builder.add_quad(quad);
+            } else {
+                quad.for_each_unit_face(|unit_quad| mesh_builder.add_quad(&unit_quad));
+            }
+        }
+
+        Ok(mesh_builder.mesh)
+    }
+
+    /// Smooth surface through the voxel centers by marching cubes. Only the cubes whose corners
+    /// are neither all occupied nor all empty are crossed by the surface, and like the faces
+    /// they are found without visiting the uniform octants around them.
+    pub fn marching_cubes<S>(&self, svdag: &S) -> Result<TriangleMesh, SvdagError>
+    where
+        S: SvdagRead + ?Sized,
+    {
+        let cases = marching_cubes_cases();
+        let mut mesh_builder = MeshBuilder::new(self);
+
+        walk_cubes(svdag, |cube, case| {
+            for triangle in cases[case].iter() {
+                let corners = triangle.map(|edge| mesh_builder.edge_vertex(cube, edge));
+                mesh_builder.mesh.triangles.push(corners);
+            }
+        })?;
+
+        Ok(mesh_builder.mesh)
+    }
+}
+
+impl Default for SvdagMesher {
+    fn default() -> Self {
+        SvdagMesher::new()
+    }
+}
+
+/// A square or, after merging, rectangular face perpendicular to `axis`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Quad {
+    axis: usize,
+    /// Whether the occupied side lies below the plane, so the face points towards +axis.
+    positive: bool,
+    plane: i64,
+    /// Smallest corner and extent along the two other axes, see `tangent_axes`.
+    min: [i64; 2],
+    size: [i64; 2],
+}
+
+impl Quad {
+    /// The axes after `axis` in cyclic order, which keeps their cross product along +axis.
+    fn tangent_axes(&self) -> [usize; 2] {
+        [(self.axis + 1) % 3, (self.axis + 2) % 3]
+    }
+
+    fn for_each_unit_face(&self, mut f: impl FnMut(Quad)) {
+        for u in self.min[0]..self.min[0] + self.size[0] {
+            for v in self.min[1]..self.min[1] + self.size[1] {
+                f(Quad {
+                    min: [u, v],
+                    size: [1, 1],
+                    ..*self
+                });
+            }
+        }
+    }
+}
+
+/// Joins rectangles that share a full edge, first along one tangent axis and then the other,
+/// until nothing changes anymore.
+fn merge_quads(mut quads: Vec<Quad>) -> Vec<Quad> {
+    loop {
+        let quad_count = quads.len();
+        quads = merge_quads_along(quads, 0);
+        quads = merge_quads_along(quads, 1);
+
+        if quads.len() == quad_count {
+            return quads;
+        }
+    }
+}
+
+fn merge_quads_along(mut quads: Vec<Quad>, along: usize) -> Vec<Quad> {
+    let across = 1 - along;
+    quads.sort_unstable_by_key(|quad| {
+        (
+            quad.axis,
+            quad.positive,
+            quad.plane,
+            quad.min[across],
+            quad.size[across],
+            quad.min[along],
+        )
+    });
+
+    let mut merged_quads: Vec<Quad> = Vec::with_capacity(quads.len());
+    for quad in quads {
+        match merged_quads.last_mut() {
+            Some(last)
+                if last.axis == quad.axis
+                    && last.positive == quad.positive
+                    && last.plane == quad.plane
+                    && last.min[across] == quad.min[across]
+                    && last.size[across] == quad.size[across]
+                    && last.min[along] + last.size[along] == quad.min[along] =>
+            {
+                last.size[along] += quad.size[along];
+            }
+            _ => merged_quads.push(quad),
+        }
+    }
+
+    merged_quads
+}
+
+/// Collects vertices shared between faces and triangles. Vertices are keyed by their position
+/// in quarter voxels, which is fine enough for voxel corners and marching cubes edge midpoints.
+struct MeshBuilder {
+    origin: [f32; 3],
+    voxel_size: f32,
+    vertices: HashMap<Position, usize>,
+    mesh: TriangleMesh,
+}
+
+impl MeshBuilder {
+    fn new(mesher: &SvdagMesher) -> MeshBuilder {
+        MeshBuilder {
+            origin: mesher.origin,
+            voxel_size: mesher.voxel_size,
+            vertices: HashMap::new(),
+            mesh: TriangleMesh::new(),
+        }
+    }
+
+    fn vertex(&mut self, key: Position) -> usize {
+        let mesh = &mut self.mesh;
+        let origin = self.origin;
+        let voxel_size = self.voxel_size;
+
+        *self.vertices.entry(key).or_insert_with(|| {
+            mesh.vertices.push([
+                origin[0] + key[0] as f32 / 4.0 * voxel_size,
+                origin[1] + key[1] as f32 / 4.0 * voxel_size,
+                origin[2] + key[2] as f32 / 4.0 * voxel_size,
+            ]);
+            mesh.vertices.len() - 1
+        })
+    }
+
+    fn add_quad(&mut self, quad: &Quad) {
+        let [u, v] = quad.tangent_axes();
+        let corner = |du: i64, dv: i64| {
+            let mut key = [0; 3];
+            key[quad.axis] = quad.plane * 4;
+            key[u] = (quad.min[0] + du * quad.size[0]) * 4;
+            key[v] = (quad.min[1] + dv * quad.size[1]) * 4;
+            key
+        };
+
+        //Counter-clockwise around +axis
+        let corners = [
+            self.vertex(corner(0, 0)),
+            self.vertex(corner(1, 0)),
+            self.vertex(corner(1, 1)),
+            self.vertex(corner(0, 1)),
+        ];
+
+        if quad.positive {
+            self.mesh
+                .triangles
+                .push([corners[0], corners[1], corners[2]]);
+            self.mesh
+                .triangles
+                .push([corners[0], corners[2], corners[3]]);
+        } else {
+            self.mesh
+                .triangles
+                .push([corners[0], corners[2], corners[1]]);
+            self.mesh
+                .triangles
+                .push([corners[0], corners[3], corners[2]]);
+        }
+    }
+
+    /// Vertex halfway between the centers of two corner voxels of the cube at `cube`.
+    fn edge_vertex(&mut self, cube: Position, edge: (usize, usize)) -> usize {
+        let (from, to) = (corner_offset(edge.0), corner_offset(edge.1));
+        let mut key = [0; 3];
+        for axis in 0..3 {
+            key[axis] = 4 * cube[axis] + 2 * (from[axis] + to[axis]) + 2;
+        }
+
+        self.vertex(key)
+    }
+}
+
+fn corner_offset(corner: usize) -> Position {
+    [
+        (corner >> 2 & 1) as i64,
+        (corner >> 1 & 1) as i64,
+        (corner & 1) as i64,
+    ]
+}
+
+/// Triangles of every marching cubes case as the cube edges their corners lie on, indexed by
+/// the mask of occupied corners.
+///
+/// Rather than a hand written table the cases are derived face by face. Going around a face
+/// counter-clockwise seen from outside, the surface runs from every edge entering the occupied
+/// corners to the next edge leaving them. On faces with two diagonal occupied corners this cuts
+/// both corners off, and since both cubes sharing a face pair its edges the same way the surface
+/// has no holes. The segments of all faces join into loops which are split into triangle fans.
+fn marching_cubes_cases() -> Vec<Vec<[(usize, usize); 3]>> {
+    (0..256)
+        .map(|case: usize| {
+            let is_occupied = |corner: usize| case >> corner & 1 == 1;
+            let mut segments = BTreeMap::new();
+
+            for axis in 0..3 {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+                for side in 0..2 {
+                    let mut corners: Vec<usize> = [(0, 0), (1, 0), (1, 1), (0, 1)]
+                        .iter()
+                        .map(|(du, dv)| side << (2 - axis) | du << (2 - u) | dv << (2 - v))
+                        .collect();
+
+                    //The lower face is seen from the other side
+                    if side == 0 {
+                        corners.reverse();
+                    }
+
+                    let crossings: Vec<((usize, usize), bool)> = (0..4)
+                        .filter_map(|index| {
+                            let (from, to) = (corners[index], corners[(index + 1) % 4]);
+                            if is_occupied(from) == is_occupied(to) {
+                                return None;
+                            }
+
+                            Some(((from.min(to), from.max(to)), is_occupied(to)))
+                        })
+                        .collect();
+
+                    for (index, (edge, is_entering)) in crossings.iter().enumerate() {
+                        if *is_entering {
+                            segments.insert(*edge, crossings[(index + 1) % crossings.len()].0);
+                        }
+                    }
+                }
+            }
+
+            let mut triangles = Vec::new();
+            while let Some((&start, _)) = segments.iter().next() {
+                let mut polygon = vec![start];
+                let mut edge = segments.remove(&start).unwrap_or(start);
+
+                while edge != start {
+                    polygon.push(edge);
+                    edge = match segments.remove(&edge) {
+                        Some(next_edge) => next_edge,
+                        None => break,
+                    };
+                }
+
+                for corner in 1..polygon.len().saturating_sub(1) {
+                    triangles.push([polygon[0], polygon[corner], polygon[corner + 1]]);
+                }
+            }
+
+            triangles
+        })
+        .collect()
+}
+
+/// Content of an octant while walking faces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Octant {
+    Empty,
+    /// Completely occupied and inside the graph's dimensions.
+    Full,
+    Node {
+        index: usize,
+        mirror: u8,
+    },
+    /// Completely occupied but crossing the boundary of the dimensions, so it's split further.
+    ClippedFull,
+}
+
+impl Octant {
+    fn is_uniform(self) -> bool {
+        matches!(self, Octant::Empty | Octant::Full)
+    }
+}
+
+/// Calls `emit` with every face between a full and an empty octant. Emitted faces are as
+/// large as the octants allow.
+fn walk_faces<S>(svdag: &S, emit: impl FnMut(Quad)) -> Result<(), SvdagError>
+where
+    S: SvdagRead + ?Sized,
+{
+    let depth = svdag.depth();
+    if depth > MAX_DEPTH {
+        return Err(SvdagError::InvalidDepth(depth));
+    }
+
+    if depth == 0 || svdag.word(0).is_none() {
+        return Ok(());
+    }
+
+    let mut walker = FaceWalker {
+        octants: OctantReader::new(svdag),
+        emit,
+    };
+
+    let root = walker.octants.classify(
+        Octant::Node {
+            index: 0,
+            mirror: 0,
+        },
+        [0; 3],
+        depth,
+    );
+    walker.walk_cell(root, [0; 3], depth);
+
+    //The outside of the graph is empty
+    let side_size = 1i64 << depth;
+    for axis in 0..3 {
+        let mut below = [0; 3];
+        below[axis] = -side_size;
+
+        walker.walk_face(Octant::Empty, root, below, depth, axis);
+        walker.walk_face(root, Octant::Empty, [0; 3], depth, axis);
+    }
+
+    Ok(())
+}
+
+struct FaceWalker<'a, S: ?Sized, F> {
+    octants: OctantReader<'a, S>,
+    emit: F,
+}
+
+impl<'a, S, F> FaceWalker<'a, S, F>
+where
+    S: SvdagRead + ?Sized,
+    F: FnMut(Quad),
+{
+    /// Visits the faces between the children of an octant, and inside them.
+    fn walk_cell(&mut self, octant: Octant, origin: Position, height: u8) {
+        if octant.is_uniform() {
+            return;
+        }
+
+        let children = self.octants.children(octant, origin, height);
+
+        for (child_index, child) in children.iter().enumerate() {
+            self.walk_cell(
+                *child,
+                child_origin(origin, child_index, height - 1),
+                height - 1,
+            );
+        }
+
+        for axis in 0..3 {
+            let axis_bit = 4 >> axis;
+
+            for child_index in (0..8).filter(|child_index| child_index & axis_bit == 0) {
+                self.walk_face(
+                    children[child_index],
+                    children[child_index | axis_bit],
+                    child_origin(origin, child_index, height - 1),
+                    height - 1,
+                    axis,
+                );
+            }
+        }
+    }
+
+    /// Visits the faces between `low` at `origin` and its neighbour `high` right above it
+    /// along `axis`, both of the given height.
+    fn walk_face(&mut self, low: Octant, high: Octant, origin: Position, height: u8, axis: usize) {
+        let side_size = 1i64 << height;
+
+        match (low, high) {
+            (Octant::Full, Octant::Empty) | (Octant::Empty, Octant::Full) => {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+                (self.emit)(Quad {
+                    axis,
+                    positive: low == Octant::Full,
+                    plane: origin[axis] + side_size,
+                    min: [origin[u], origin[v]],
+                    size: [side_size, side_size],
+                });
+            }
+            (low, high) if low.is_uniform() && high.is_uniform() => {}
+            _ => {
+                let mut high_origin = origin;
+                high_origin[axis] += side_size;
+
+                let low_children = self.octants.children(low, origin, height);
+                let high_children = self.octants.children(high, high_origin, height);
+                let axis_bit = 4 >> axis;
+
+                //The upper children of the lower octant touch the lower children of the upper one
+                for child_index in (0..8).filter(|child_index| child_index & axis_bit == 0) {
+                    self.walk_face(
+                        low_children[child_index | axis_bit],
+                        high_children[child_index],
+                        child_origin(origin, child_index | axis_bit, height - 1),
+                        height - 1,
+                        axis,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Calls `emit` with every marching cubes cube whose corners are neither all occupied nor all
+/// empty, named by the voxel at its smallest corner, and the mask of its occupied corners.
+fn walk_cubes<S>(svdag: &S, emit: impl FnMut(Position, usize)) -> Result<(), SvdagError>
+where
+    S: SvdagRead + ?Sized,
+{
+    let depth = svdag.depth();
+    if depth > MAX_DEPTH {
+        return Err(SvdagError::InvalidDepth(depth));
+    }
+
+    if depth == 0 || svdag.word(0).is_none() {
+        return Ok(());
+    }
+
+    let mut walker = CubeWalker {
+        octants: OctantReader::new(svdag),
+        emit,
+    };
+
+    let root = walker.octants.classify(
+        Octant::Node {
+            index: 0,
+            mirror: 0,
+        },
+        [0; 3],
+        depth,
+    );
+
+    //The outside of the graph is empty. Along every axis a block either lies inside the root
+    //or straddles one of its two sides, which covers every cube touching an occupied voxel.
+    let side_size = 1i64 << depth;
+    let blocks = [(0, false), (-1, true), (0, true)];
+
+    for &(x, x_spanned) in blocks.iter() {
+        for &(y, y_spanned) in blocks.iter() {
+            for &(z, z_spanned) in blocks.iter() {
+                let start = [x, y, z];
+                let spanned = [x_spanned, y_spanned, z_spanned];
+
+                let mut octants = [Octant::Empty; 8];
+                for (corner, octant) in octants.iter_mut().enumerate() {
+                    let offset = corner_offset(corner);
+                    if (0..3).all(|axis| start[axis] + spanned[axis] as i64 * offset[axis] == 0) {
+                        *octant = root;
+                    }
+                }
+
+                walker.walk_block(
+                    octants,
+                    [x * side_size, y * side_size, z * side_size],
+                    depth,
+                    spanned,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Blocks a non-spanned axis of a block splits into: the lower and upper children, each
+/// non-spanned, and the pair of them spanning the plane between.
+const SPLIT_BLOCKS: [(i64, bool); 3] = [(0, false), (1, false), (0, true)];
+/// A spanned axis keeps spanning the plane between the two middle children.
+const SPANNED_BLOCKS: [(i64, bool); 1] = [(1, true)];
+
+struct CubeWalker<'a, S: ?Sized, F> {
+    octants: OctantReader<'a, S>,
+    emit: F,
+}
+
+impl<'a, S, F> CubeWalker<'a, S, F>
+where
+    S: SvdagRead + ?Sized,
+    F: FnMut(Position, usize),
+{
+    /// Visits the cubes around the voxel corners shared inside a block of octants of the given
+    /// height, `octants` holding them by corner index with the first one at `origin`.
+    ///
+    /// Along a spanned axis the block is two octants wide and only the corners on the plane
+    /// between them belong to it. Along the other axes both halves of `octants` are the same
+    /// octant and the corners strictly inside it belong to the block. A single octant, the faces,
+    /// edges and corner between octants are thereby each walked once, and blocks of uniform
+    /// octants that agree end the walk right away.
+    fn walk_block(
+        &mut self,
+        octants: [Octant; 8],
+        origin: Position,
+        height: u8,
+        spanned: [bool; 3],
+    ) {
+        if octants.iter().all(|octant| *octant == Octant::Empty)
+            || octants.iter().all(|octant| *octant == Octant::Full)
+        {
+            return;
+        }
+
+        if height == 0 {
+            //Only voxels around a shared corner make up a cube
+            if spanned == [true; 3] {
+                let case = (0..8)
+                    .filter(|corner| octants[*corner] == Octant::Full)
+                    .fold(0, |case, corner| case | 1 << corner);
+                (self.emit)(origin, case);
+            }
+            return;
+        }
+
+        //The children form a grid four children wide along spanned axes and two along the others
+        let mut children = [[Octant::Empty; 8]; 8];
+        for corner in 0..8 {
+            if (0..3).all(|axis| spanned[axis] || corner & 4 >> axis == 0) {
+                children[corner] = self.octants.children(
+                    octants[corner],
+                    child_origin(origin, corner, height),
+                    height,
+                );
+            }
+        }
+
+        let grid_child = |position: Position| {
+            let mut corner = 0;
+            let mut child_index = 0;
+            for axis in 0..3 {
+                if spanned[axis] {
+                    corner |= (position[axis] as usize >> 1) << (2 - axis);
+                    child_index |= (position[axis] as usize & 1) << (2 - axis);
+                } else {
+                    child_index |= (position[axis] as usize) << (2 - axis);
+                }
+            }
+
+            children[corner][child_index]
+        };
+
+        let half_size = 1i64 << (height - 1);
+        let blocks = |axis: usize| match spanned[axis] {
+            true => &SPANNED_BLOCKS[..],
+            false => &SPLIT_BLOCKS[..],
+        };
+
+        for &(x, x_spanned) in blocks(0) {
+            for &(y, y_spanned) in blocks(1) {
+                for &(z, z_spanned) in blocks(2) {
+                    let start = [x, y, z];
+                    let child_spanned = [x_spanned, y_spanned, z_spanned];
+
+                    let mut child_octants = [Octant::Empty; 8];
+                    for (corner, child_octant) in child_octants.iter_mut().enumerate() {
+                        let offset = corner_offset(corner);
+                        *child_octant = grid_child([
+                            start[0] + child_spanned[0] as i64 * offset[0],
+                            start[1] + child_spanned[1] as i64 * offset[1],
+                            start[2] + child_spanned[2] as i64 * offset[2],
+                        ]);
+                    }
+
+                    self.walk_block(
+                        child_octants,
+                        [
+                            origin[0] + x * half_size,
+                            origin[1] + y * half_size,
+                            origin[2] + z * half_size,
+                        ],
+                        height - 1,
+                        child_spanned,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Resolves the octants of a graph for the walkers, clipped to its dimensions.
+struct OctantReader<'a, S: ?Sized> {
+    svdag: &'a S,
+    dimensions: Position,
+    /// Whether the node at an index is completely filled, reflecting a node keeps it full.
+    full_nodes: HashMap<usize, bool>,
+}
+
+impl<'a, S> OctantReader<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    fn new(svdag: &'a S) -> OctantReader<'a, S> {
+        let dimensions = svdag.dimensions();
+
+        OctantReader {
+            svdag,
+            dimensions: [
+                dimensions.0 as i64,
+                dimensions.1 as i64,
+                dimensions.2 as i64,
+            ],
+            full_nodes: HashMap::new(),
+        }
+    }
+
+    /// Children of an octant of height at least 1 by child index.
+    fn children(&mut self, octant: Octant, origin: Position, height: u8) -> [Octant; 8] {
+        let mut children = match octant {
+            Octant::Empty => [Octant::Empty; 8],
+            Octant::Full | Octant::ClippedFull => [Octant::Full; 8],
+            Octant::Node { index, mirror } => self.node_children(index, mirror, height),
+        };
+
+        for (child_index, child) in children.iter_mut().enumerate() {
+            *child = self.classify(
+                *child,
+                child_origin(origin, child_index, height - 1),
+                height - 1,
+            );
+        }
+
+        children
+    }
+
+    fn node_children(&mut self, node_index: usize, mirror: u8, height: u8) -> [Octant; 8] {
+        let mut children = [Octant::Empty; 8];
+        let node = match self.svdag.word(node_index) {
+            Some(word) => word.node(),
+            None => return children,
+        };
+
+        let pointer_word_count = self.svdag.pointer_format().word_count();
+        let mut pointer_index = node_index + 1;
+
+        for stored_index in 0..8 {
+            if !node.children.get(stored_index) {
+                continue;
+            }
+
+            let child_index = mirror_child_index(stored_index, mirror);
+            children[child_index] = if height == 1 {
+                Octant::Full
+            } else {
+                match self.svdag.read_mirrored_pointer(pointer_index) {
+                    Some((child_node_index, child_mirror)) => Octant::Node {
+                        index: child_node_index,
+                        mirror: mirror ^ child_mirror,
+                    },
+                    None => Octant::Empty,
+                }
+            };
+
+            pointer_index += pointer_word_count;
+        }
+
+        children
+    }
+
+    /// Resolves full nodes and clips the octant to the graph's dimensions.
+    fn classify(&mut self, octant: Octant, origin: Position, height: u8) -> Octant {
+        let side_size = 1i64 << height;
+        let dimensions = self.dimensions;
+
+        if (0..3).any(|axis| origin[axis] >= dimensions[axis] || origin[axis] + side_size <= 0) {
+            return Octant::Empty;
+        }
+
+        let is_inside =
+            (0..3).all(|axis| origin[axis] >= 0 && origin[axis] + side_size <= dimensions[axis]);
+
+        let is_full = match octant {
+            Octant::Node { index, .. } => self.is_full(index, height),
+            Octant::Full | Octant::ClippedFull => true,
+            Octant::Empty => false,
+        };
+
+        match (is_full, is_inside) {
+            (true, true) => Octant::Full,
+            (true, false) => Octant::ClippedFull,
+            (false, _) => octant,
+        }
+    }
+
+    fn is_full(&mut self, node_index: usize, height: u8) -> bool {
+        is_full_node(self.svdag, &mut self.full_nodes, node_index, height)
+    }
+}
+
+fn child_origin(origin: Position, child_index: usize, height: u8) -> Position {
+    let offset = corner_offset(child_index);
+    let side_size = 1i64 << height;
+
+    [
+        origin[0] + offset[0] * side_size,
+        origin[1] + offset[1] * side_size,
+        origin[2] + offset[2] * side_size,
+    ]
+}
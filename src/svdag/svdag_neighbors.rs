@@ -0,0 +1,390 @@
+use super::{svdag_node_table::mirror_child_index, svdag_read::is_full_node, SvdagRead};
+use crate::volume::{VolumeDimensions, VolumePosition};
+use std::collections::HashMap;
+
+/// One of the six sides of a voxel, named by the direction it faces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Face {
+    NegativeX,
+    PositiveX,
+    NegativeY,
+    PositiveY,
+    NegativeZ,
+    PositiveZ,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::NegativeX,
+        Face::PositiveX,
+        Face::NegativeY,
+        Face::PositiveY,
+        Face::NegativeZ,
+        Face::PositiveZ,
+    ];
+
+    /// Axis the face is perpendicular to, 0 for x, 1 for y and 2 for z.
+    pub fn axis(self) -> usize {
+        self as usize / 2
+    }
+
+    pub fn is_positive(self) -> bool {
+        self as usize % 2 == 1
+    }
+
+    /// Position of the neighbour behind this face, `None` if it would be negative.
+    pub fn neighbor(self, position: VolumePosition) -> Option<VolumePosition> {
+        let mut coordinates = [position.0, position.1, position.2];
+        let coordinate = &mut coordinates[self.axis()];
+
+        *coordinate = if self.is_positive() {
+            coordinate.checked_add(1)?
+        } else {
+            coordinate.checked_sub(1)?
+        };
+
+        Some((coordinates[0], coordinates[1], coordinates[2]))
+    }
+}
+
+/// A set of voxel faces, bit `face as u8` stands for `face`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FaceMask {
+    pub face_bits: u8,
+}
+
+impl FaceMask {
+    pub fn new(face_bits: u8) -> FaceMask {
+        FaceMask {
+            face_bits: face_bits & 0b11_1111,
+        }
+    }
+
+    pub fn get(&self, face: Face) -> bool {
+        (self.face_bits >> face as u8) & 1 > 0
+    }
+
+    pub fn set(&mut self, face: Face, has_face: bool) {
+        match has_face {
+            true => self.face_bits |= 1 << face as u8,
+            false => self.face_bits &= !(1 << face as u8),
+        };
+    }
+
+    pub fn count(&self) -> usize {
+        self.face_bits.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.face_bits == 0
+    }
+
+    /// The faces not in this set.
+    pub fn inverted(&self) -> FaceMask {
+        FaceMask::new(!self.face_bits)
+    }
+}
+
+/// A node on the path from the root, with the reflection it's seen through.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct PathNode {
+    index: usize,
+    mirror: u8,
+}
+
+/// Which of the neighbours of the voxel at `position` are occupied, see `SvdagRead::neighbors`.
+pub(crate) fn neighbors<S>(svdag: &S, position: VolumePosition) -> Option<FaceMask>
+where
+    S: SvdagRead + ?Sized,
+{
+    let depth = svdag.depth();
+    let dimensions = svdag.dimensions();
+    if depth == 0 || depth as u32 >= usize::BITS || !is_inside(position, dimensions) {
+        return None;
+    }
+
+    //Remember the nodes on the way down, each neighbour only walks down from where the paths part
+    let mut path = vec![PathNode {
+        index: 0,
+        mirror: 0,
+    }];
+    while path.len() < depth as usize {
+        let level = path.len() - 1;
+        match walk(svdag, path[level], level, level + 1, position) {
+            Some(node) => path.push(node),
+            None => break,
+        }
+    }
+
+    Some(occupied_neighbors(
+        svdag,
+        &mut HashMap::new(),
+        &path,
+        position,
+        depth as usize,
+        0,
+    ))
+}
+
+/// Mask of the occupied neighbours of the voxel or, with a `height` above 0, of the neighbouring
+/// octants that are completely full. `path` holds the nodes from the root towards `position` as
+/// far as they exist.
+fn occupied_neighbors<S>(
+    svdag: &S,
+    full_nodes: &mut HashMap<usize, bool>,
+    path: &[PathNode],
+    position: VolumePosition,
+    depth: usize,
+    height: usize,
+) -> FaceMask
+where
+    S: SvdagRead + ?Sized,
+{
+    let dimensions = svdag.dimensions();
+    let side_size = 1usize << height;
+    let mut mask = FaceMask::default();
+
+    for face in Face::ALL.iter() {
+        let mut coordinates = [position.0, position.1, position.2];
+        let axis = face.axis();
+        let coordinate = coordinates[axis];
+
+        coordinates[axis] = match face.is_positive() {
+            true => coordinate + side_size,
+            false => match coordinate.checked_sub(side_size) {
+                Some(coordinate) => coordinate,
+                None => continue,
+            },
+        };
+        let neighbor = (coordinates[0], coordinates[1], coordinates[2]);
+
+        //Octants reaching past the dimensions aren't full, voxels past them are empty
+        let far_corner = (
+            neighbor.0 + side_size - 1,
+            neighbor.1 + side_size - 1,
+            neighbor.2 + side_size - 1,
+        );
+        if !is_inside(far_corner, dimensions) {
+            continue;
+        }
+
+        //Both share every node above the highest bit that differs between them
+        let highest_differing_bit =
+            (usize::BITS - 1 - (coordinate ^ coordinates[axis]).leading_zeros()) as usize;
+        let ancestor_level = depth - 1 - highest_differing_bit;
+        let ancestor = match path.get(ancestor_level) {
+            Some(ancestor) => *ancestor,
+            //The path ended in an empty octant above the ancestor, which holds the neighbour too
+            None => continue,
+        };
+
+        let is_occupied = match walk(svdag, ancestor, ancestor_level, depth - height, neighbor) {
+            Some(node) if height > 0 => is_full_node(svdag, full_nodes, node.index, height as u8),
+            Some(_) => true,
+            None => false,
+        };
+        mask.set(*face, is_occupied);
+    }
+
+    mask
+}
+
+/// Walks from `node` at `level` towards `position` down to the node at `target_level`, or
+/// the voxel itself if that's the depth. `None` if the way leads into empty space.
+fn walk<S>(
+    svdag: &S,
+    mut node: PathNode,
+    level: usize,
+    target_level: usize,
+    position: VolumePosition,
+) -> Option<PathNode>
+where
+    S: SvdagRead + ?Sized,
+{
+    let depth = svdag.depth() as usize;
+    let pointer_word_count = svdag.pointer_format().word_count();
+
+    for level in level..target_level {
+        let bit = depth - 1 - level;
+        let child_index = mirror_child_index(
+            (position.0 >> bit & 1) << 2 | (position.1 >> bit & 1) << 1 | (position.2 >> bit & 1),
+            node.mirror,
+        );
+
+        let children = svdag.word(node.index)?.node().children;
+        if !children.get(child_index) {
+            return None;
+        }
+
+        //The voxel itself has no node, the leaf stands in for it
+        if level == depth - 1 {
+            return Some(node);
+        }
+
+        let child_pointer_index = node.index + 1 + children.get_n(child_index) * pointer_word_count;
+        let (child_node_index, child_mirror) = svdag.read_mirrored_pointer(child_pointer_index)?;
+        node = PathNode {
+            index: child_node_index,
+            mirror: node.mirror ^ child_mirror,
+        };
+    }
+
+    Some(node)
+}
+
+fn is_inside(position: VolumePosition, dimensions: VolumeDimensions) -> bool {
+    position.0 < dimensions.0 && position.1 < dimensions.1 && position.2 < dimensions.2
+}
+
+/// Iterator over the occupied voxels with at least one empty neighbour, see
+/// `SvdagRead::surface_voxels`.
+///
+/// The graph is walked depth-first in child index order. The nodes on the way down double as
+/// the path for neighbour queries, and full octants whose six neighbouring octants are full too
+/// are skipped as a whole since none of their voxels can be exposed.
+pub struct SurfaceVoxels<'a, S: ?Sized> {
+    svdag: &'a S,
+    depth: usize,
+    /// Nodes from the root down to the current one with their origin and the next child index to visit.
+    stack: Vec<(PathNode, VolumePosition, usize)>,
+    path: Vec<PathNode>,
+    full_nodes: HashMap<usize, bool>,
+}
+
+impl<'a, S> SurfaceVoxels<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    pub(crate) fn new(svdag: &'a S) -> SurfaceVoxels<'a, S> {
+        let depth = svdag.depth();
+        let mut stack = Vec::new();
+
+        if depth > 0 && (depth as u32) < usize::BITS && svdag.word(0).is_some() {
+            stack.push((
+                PathNode {
+                    index: 0,
+                    mirror: 0,
+                },
+                (0, 0, 0),
+                0,
+            ));
+        }
+
+        SurfaceVoxels {
+            svdag,
+            depth: depth as usize,
+            stack,
+            path: vec![PathNode {
+                index: 0,
+                mirror: 0,
+            }],
+            full_nodes: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, S> Iterator for SurfaceVoxels<'a, S>
+where
+    S: SvdagRead + ?Sized,
+{
+    type Item = (VolumePosition, FaceMask);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let dimensions = self.svdag.dimensions();
+        let pointer_word_count = self.svdag.pointer_format().word_count();
+
+        while let Some((node, origin, child_index)) = self.stack.last().copied() {
+            if child_index == 8 {
+                self.stack.pop();
+                self.path.truncate(self.stack.len().max(1));
+                continue;
+            }
+
+            let level = self.stack.len() - 1;
+            let height = self.depth - level;
+            let half_size = 1usize << (height - 1);
+            self.stack[level].2 += 1;
+
+            let children = match self.svdag.word(node.index) {
+                Some(word) => word.node().children,
+                None => continue,
+            };
+
+            let stored_index = mirror_child_index(child_index, node.mirror);
+            if !children.get(stored_index) {
+                continue;
+            }
+
+            let child_origin = (
+                origin.0 + (child_index >> 2 & 1) * half_size,
+                origin.1 + (child_index >> 1 & 1) * half_size,
+                origin.2 + (child_index & 1) * half_size,
+            );
+
+            if height == 1 {
+                if !is_inside(child_origin, dimensions) {
+                    continue;
+                }
+
+                let exposed_faces = occupied_neighbors(
+                    self.svdag,
+                    &mut self.full_nodes,
+                    &self.path,
+                    child_origin,
+                    self.depth,
+                    0,
+                )
+                .inverted();
+                if !exposed_faces.is_empty() {
+                    return Some((child_origin, exposed_faces));
+                }
+                continue;
+            }
+
+            let child_pointer_index =
+                node.index + 1 + children.get_n(stored_index) * pointer_word_count;
+            let (child_node_index, child_mirror) =
+                match self.svdag.read_mirrored_pointer(child_pointer_index) {
+                    Some(child_pointer) => child_pointer,
+                    None => continue,
+                };
+
+            //Skip full octants buried under full neighbours
+            let child_height = height - 1;
+            let far_corner = (
+                child_origin.0 + half_size - 1,
+                child_origin.1 + half_size - 1,
+                child_origin.2 + half_size - 1,
+            );
+            if is_inside(far_corner, dimensions)
+                && is_full_node(
+                    self.svdag,
+                    &mut self.full_nodes,
+                    child_node_index,
+                    child_height as u8,
+                )
+                && occupied_neighbors(
+                    self.svdag,
+                    &mut self.full_nodes,
+                    &self.path,
+                    child_origin,
+                    self.depth,
+                    child_height,
+                )
+                .count()
+                    == 6
+            {
+                continue;
+            }
+
+            let child = PathNode {
+                index: child_node_index,
+                mirror: node.mirror ^ child_mirror,
+            };
+            self.stack.push((child, child_origin, 0));
+            self.path.push(child);
+        }
+
+        None
+    }
+}
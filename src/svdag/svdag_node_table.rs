@@ -0,0 +1,644 @@
+use super::{
+    svdag_read::cube_dimensions, PointerFormat, Svdag, SvdagError, SvdagNode, SvdagRead, SvdagValue,
+};
+use crate::{hashed_volume::Children, volume::VolumePosition};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    convert::TryFrom,
+    mem,
+};
+
+pub type NodeId = u32;
+
+/// Layout of the nodes in a flattened node array, parents always come before their children.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NodeOrder {
+    /// Every node is followed by its first unvisited subtree, keeping the nodes visited by a
+    /// single lookup close together.
+    DepthFirst,
+    /// Level by level from the root, keeping the upper levels in a small prefix of the array.
+    BreadthFirst,
+}
+
+/// A node keyed purely by its content, so structurally equal subtrees get the same id.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TableNode {
+    /// Number of levels below this node, leaf nodes whose children are voxels have height 1.
+    pub height: u8,
+    pub children: Children,
+    /// Ids of the child nodes by child index, unoccupied slots and leaf nodes hold 0.
+    pub child_ids: [NodeId; 8],
+    /// Reflection applied to each child node, see `mirror_child_index`.
+    pub child_mirrors: [u8; 8],
+}
+
+impl TableNode {
+    pub fn empty(height: u8) -> TableNode {
+        TableNode {
+            height,
+            children: Children::default(),
+            child_ids: [0; 8],
+            child_mirrors: [0; 8],
+        }
+    }
+
+    pub fn leaf(children: Children) -> TableNode {
+        TableNode {
+            children,
+            ..TableNode::empty(1)
+        }
+    }
+
+    /// The same node reflected along the axes in `mirror`.
+    pub fn mirrored(&self, mirror: u8) -> TableNode {
+        let mut node = TableNode::empty(self.height);
+
+        for child_index in 0..8 {
+            let source_index = mirror_child_index(child_index, mirror);
+
+            node.children
+                .set(child_index, self.children.get(source_index));
+            node.child_ids[child_index] = self.child_ids[source_index];
+
+            //Mirroring an already mirrored child combines both reflections
+            if self.height > 1 && self.children.get(source_index) {
+                node.child_mirrors[child_index] = self.child_mirrors[source_index] ^ mirror;
+            }
+        }
+
+        node
+    }
+
+    fn sort_key(&self) -> (u8, [NodeId; 8], [u8; 8]) {
+        (self.children.child_bits, self.child_ids, self.child_mirrors)
+    }
+}
+
+/// Hash-consing store of unique nodes, filled bottom-up and flattened into an `Svdag` at the end.
+#[derive(Clone, Debug, Default)]
+pub struct NodeTable {
+    nodes: Vec<TableNode>,
+    /// Per node a bit for every reflection that leaves the node unchanged, bit 0 is always set.
+    /// Only filled for the children of nodes stored through `insert_symmetric`.
+    symmetries: HashMap<NodeId, u8>,
+    node_ids: HashMap<TableNode, NodeId>,
+}
+
+impl NodeTable {
+    pub fn new() -> NodeTable {
+        NodeTable {
+            nodes: Vec::new(),
+            symmetries: HashMap::new(),
+            node_ids: HashMap::new(),
+        }
+    }
+
+    /// Returns the id of an equal node already in the table or stores the node under a new id.
+    pub fn insert(&mut self, node: TableNode) -> Result<NodeId, SvdagError> {
+        if let Some(node_id) = self.node_ids.get(&node) {
+            return Ok(*node_id);
+        }
+
+        let node_id = NodeId::try_from(self.nodes.len()).map_err(|_| SvdagError::TooManyNodes)?;
+        self.nodes.push(node);
+        self.node_ids.insert(node, node_id);
+
+        Ok(node_id)
+    }
+
+    /// Stores the node up to reflection, returning the id of the stored node and the reflection
+    /// that turns it back into `node`. All reflections of a node share a single entry.
+    pub fn insert_symmetric(&mut self, node: TableNode) -> Result<(NodeId, u8), SvdagError> {
+        if node.height > 1 {
+            for child_index in 0..8 {
+                if node.children.get(child_index) {
+                    self.symmetry(node.child_ids[child_index]);
+                }
+            }
+        }
+
+        let (mirror, canonical_node) = (0..8)
+            .map(|mirror| (mirror, self.normalized(node.mirrored(mirror))))
+            .min_by_key(|(_, mirrored_node)| mirrored_node.sort_key())
+            .unwrap();
+
+        Ok((self.insert(canonical_node)?, mirror))
+    }
+
+    /// Rewrites every child reflection to the smallest one giving the same child. A child that is
+    /// symmetric itself can be reached through several reflections, picking one keeps equal
+    /// subtrees equal nodes. The symmetries of the children have to be known already.
+    fn normalized(&self, mut node: TableNode) -> TableNode {
+        if node.height == 1 {
+            return node;
+        }
+
+        for child_index in 0..8 {
+            if !node.children.get(child_index) {
+                continue;
+            }
+
+            let symmetry = self.symmetries[&node.child_ids[child_index]];
+            let mirror = node.child_mirrors[child_index];
+
+            node.child_mirrors[child_index] = (0..8)
+                .filter(|symmetric_mirror| symmetry >> symmetric_mirror & 1 == 1)
+                .map(|symmetric_mirror| mirror ^ symmetric_mirror)
+                .min()
+                .unwrap();
+        }
+
+        node
+    }
+
+    /// Symmetries of the stored node `node_id`, computed along with those of its children on
+    /// first use.
+    fn symmetry(&mut self, node_id: NodeId) -> u8 {
+        if let Some(symmetry) = self.symmetries.get(&node_id) {
+            return *symmetry;
+        }
+
+        let node = self.nodes[node_id as usize];
+        if node.height > 1 {
+            for child_index in 0..8 {
+                if node.children.get(child_index) {
+                    self.symmetry(node.child_ids[child_index]);
+                }
+            }
+        }
+
+        let symmetry = self.symmetry_of(&node);
+        self.symmetries.insert(node_id, symmetry);
+
+        symmetry
+    }
+
+    fn symmetry_of(&self, node: &TableNode) -> u8 {
+        let normalized_node = self.normalized(*node);
+
+        (0..8)
+            .filter(|mirror| self.normalized(node.mirrored(*mirror)) == normalized_node)
+            .fold(0, |symmetry, mirror| symmetry | 1 << mirror)
+    }
+
+    /// Returns the id of the completely filled node of the given height.
+    pub fn insert_solid(&mut self, height: u8) -> Result<NodeId, SvdagError> {
+        let mut node_id = self.insert(TableNode::leaf(Children::new(0b1111_1111)))?;
+
+        for height in 2..=height {
+            node_id = self.insert(TableNode {
+                height,
+                children: Children::new(0b1111_1111),
+                child_ids: [node_id; 8],
+                child_mirrors: [0; 8],
+            })?;
+        }
+
+        Ok(node_id)
+    }
+
+    /// Stores every node of an existing graph, returning the id of its root. Reflections are
+    /// kept as they are, so nothing is shared that wasn't shared in the graph already.
+    pub fn insert_svdag<S>(&mut self, svdag: &S) -> Result<NodeId, SvdagError>
+    where
+        S: SvdagRead + ?Sized,
+    {
+        if svdag.depth() == 0 || svdag.depth() as u32 >= usize::BITS {
+            return Err(SvdagError::InvalidDepth(svdag.depth()));
+        }
+
+        self.insert_svdag_node(svdag, &mut HashMap::new(), 0, svdag.depth())
+    }
+
+    /// Stores the subtree of height `height` at `node_index` of `svdag`, `node_ids` remembers the
+    /// ids of nodes stored so far so shared subtrees are only walked once.
+    pub(crate) fn insert_svdag_node<S>(
+        &mut self,
+        svdag: &S,
+        node_ids: &mut HashMap<usize, NodeId>,
+        node_index: usize,
+        height: u8,
+    ) -> Result<NodeId, SvdagError>
+    where
+        S: SvdagRead + ?Sized,
+    {
+        if let Some(node_id) = node_ids.get(&node_index) {
+            return Ok(*node_id);
+        }
+
+        let mut node = TableNode::empty(height);
+        node.children = svdag
+            .word(node_index)
+            .ok_or(SvdagError::InvalidNode { index: node_index })?
+            .node()
+            .children;
+
+        if height > 1 {
+            let pointer_word_count = svdag.pointer_format().word_count();
+            let mut pointer_index = node_index + 1;
+
+            for child_index in 0..8 {
+                if !node.children.get(child_index) {
+                    continue;
+                }
+
+                let (child_node_index, child_mirror) =
+                    svdag
+                        .read_mirrored_pointer(pointer_index)
+                        .ok_or(SvdagError::InvalidPointer { pointer_index })?;
+
+                node.child_ids[child_index] =
+                    self.insert_svdag_node(svdag, node_ids, child_node_index, height - 1)?;
+                node.child_mirrors[child_index] = child_mirror;
+
+                pointer_index += pointer_word_count;
+            }
+        }
+
+        let node_id = self.insert(node)?;
+        node_ids.insert(node_index, node_id);
+
+        Ok(node_id)
+    }
+
+    /// Looks up a voxel in the subtree under `root` seen with the reflection `root_mirror`,
+    /// returning `None` if it lies outside the subtree.
+    pub fn get(&self, root: NodeId, root_mirror: u8, position: VolumePosition) -> Option<bool> {
+        let height = self.node(root).height;
+
+        let side_size = 1usize << height;
+        if position.0 >= side_size || position.1 >= side_size || position.2 >= side_size {
+            return None;
+        }
+
+        let mut node_id = root;
+        let mut mirror = root_mirror;
+
+        for level in (0..height).rev() {
+            let child_index = mirror_child_index(
+                (position.0 >> level & 1) << 2
+                    | (position.1 >> level & 1) << 1
+                    | (position.2 >> level & 1),
+                mirror,
+            );
+
+            let node = self.node(node_id);
+            if !node.children.get(child_index) {
+                return Some(false);
+            }
+
+            if level == 0 {
+                return Some(true);
+            }
+
+            node_id = node.child_ids[child_index];
+            mirror ^= node.child_mirrors[child_index];
+        }
+
+        None
+    }
+
+    /// Drops every node that can't be reached from `roots` and renumbers the rest, returning the
+    /// new id of every kept node by its old id. Ids of dropped nodes are no longer valid.
+    pub fn retain_reachable(
+        &mut self,
+        roots: impl IntoIterator<Item = NodeId>,
+    ) -> HashMap<NodeId, NodeId> {
+        let mut is_reachable = vec![false; self.nodes.len()];
+        let mut stack: Vec<NodeId> = roots.into_iter().collect();
+
+        while let Some(node_id) = stack.pop() {
+            if mem::replace(&mut is_reachable[node_id as usize], true) {
+                continue;
+            }
+
+            let node = self.node(node_id);
+            if node.height > 1 {
+                for child_index in 0..8 {
+                    if node.children.get(child_index) {
+                        stack.push(node.child_ids[child_index]);
+                    }
+                }
+            }
+        }
+
+        let nodes = mem::take(&mut self.nodes);
+        let symmetries = mem::take(&mut self.symmetries);
+        self.node_ids.clear();
+
+        //Children are always stored before their parents, so their new ids are known in time
+        let mut new_ids = HashMap::new();
+        for (old_id, mut node) in nodes.into_iter().enumerate() {
+            if !is_reachable[old_id] {
+                continue;
+            }
+
+            if node.height > 1 {
+                for child_index in 0..8 {
+                    if node.children.get(child_index) {
+                        node.child_ids[child_index] = new_ids[&node.child_ids[child_index]];
+                    }
+                }
+            }
+
+            let old_id = old_id as NodeId;
+            let new_id = self.nodes.len() as NodeId;
+            self.nodes.push(node);
+            self.node_ids.insert(node, new_id);
+            new_ids.insert(old_id, new_id);
+
+            if let Some(symmetry) = symmetries.get(&old_id) {
+                self.symmetries.insert(new_id, *symmetry);
+            }
+        }
+
+        new_ids
+    }
+
+    pub fn node(&self, node_id: NodeId) -> &TableNode {
+        &self.nodes[node_id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Flattens the subtree under `root` into an `Svdag`, using the narrowest pointer format that
+    /// fits unless one is given.
+    pub fn to_svdag(
+        &self,
+        root: NodeId,
+        pointer_format: Option<PointerFormat>,
+    ) -> Result<Svdag, SvdagError> {
+        self.to_svdag_in_order(root, pointer_format, NodeOrder::DepthFirst)
+    }
+
+    /// Like `to_svdag`, laying out the nodes in the given order.
+    pub fn to_svdag_in_order(
+        &self,
+        root: NodeId,
+        pointer_format: Option<PointerFormat>,
+        order: NodeOrder,
+    ) -> Result<Svdag, SvdagError> {
+        self.flatten_with_format(root, pointer_format, order, false)
+    }
+
+    /// Like `to_svdag`, but every leaf node is followed by one word per occupied voxel holding the
+    /// leaf's child id for that voxel.
+    pub(crate) fn to_svdag_with_leaf_payload(
+        &self,
+        root: NodeId,
+        pointer_format: Option<PointerFormat>,
+    ) -> Result<Svdag, SvdagError> {
+        self.flatten_with_format(root, pointer_format, NodeOrder::DepthFirst, true)
+    }
+
+    fn flatten_with_format(
+        &self,
+        root: NodeId,
+        pointer_format: Option<PointerFormat>,
+        order: NodeOrder,
+        has_leaf_payload: bool,
+    ) -> Result<Svdag, SvdagError> {
+        let flatten = |pointer_format| self.flatten(root, pointer_format, order, has_leaf_payload);
+
+        match pointer_format {
+            Some(pointer_format) => flatten(pointer_format),
+            //Reflected children can only be stored in mirrored pointers
+            None if self.has_mirrors(root) => flatten(PointerFormat::Mirrored32),
+            None => match flatten(PointerFormat::Relative16) {
+                Err(SvdagError::PointerOverflow { .. }) => flatten(PointerFormat::Absolute32),
+                result => result,
+            },
+        }
+    }
+
+    /// Checks whether any node under `root` has a reflected child.
+    fn has_mirrors(&self, root: NodeId) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![root];
+
+        while let Some(node_id) = stack.pop() {
+            if !visited.insert(node_id) {
+                continue;
+            }
+
+            let node = self.node(node_id);
+            if node.height == 1 {
+                continue;
+            }
+
+            for child_index in 0..8 {
+                if node.children.get(child_index) {
+                    if node.child_mirrors[child_index] != 0 {
+                        return true;
+                    }
+
+                    stack.push(node.child_ids[child_index]);
+                }
+            }
+        }
+
+        false
+    }
+
+    fn flatten(
+        &self,
+        root: NodeId,
+        pointer_format: PointerFormat,
+        order: NodeOrder,
+        has_leaf_payload: bool,
+    ) -> Result<Svdag, SvdagError> {
+        let mut svdag = Svdag::new();
+        svdag.depth = self.node(root).height;
+        svdag.dimensions = cube_dimensions(svdag.depth);
+        svdag.pointer_format = pointer_format;
+
+        match order {
+            NodeOrder::DepthFirst => {
+                self.flatten_recursive(&mut svdag, &mut HashMap::new(), root, has_leaf_payload)?;
+            }
+            NodeOrder::BreadthFirst => {
+                self.flatten_breadth_first(&mut svdag, root, has_leaf_payload)?;
+            }
+        }
+
+        Ok(svdag)
+    }
+
+    /// Number of words the node takes up in the node array, including its pointers or payload.
+    fn word_count(
+        &self,
+        node: &TableNode,
+        pointer_format: PointerFormat,
+        has_leaf_payload: bool,
+    ) -> usize {
+        let child_count = node.children.count_occupied();
+
+        if node.height > 1 {
+            1 + child_count * pointer_format.word_count()
+        } else if has_leaf_payload {
+            1 + child_count
+        } else {
+            1
+        }
+    }
+
+    fn flatten_breadth_first(
+        &self,
+        svdag: &mut Svdag,
+        root: NodeId,
+        has_leaf_payload: bool,
+    ) -> Result<(), SvdagError> {
+        //Nodes get their index when first discovered, which keeps parents before their children
+        let mut node_indices = HashMap::new();
+        let mut queue = VecDeque::new();
+        let mut next_index = 0;
+
+        node_indices.insert(root, next_index);
+        next_index += self.word_count(self.node(root), svdag.pointer_format, has_leaf_payload);
+        queue.push_back(root);
+
+        let mut order = Vec::new();
+        while let Some(node_id) = queue.pop_front() {
+            order.push(node_id);
+
+            let node = self.node(node_id);
+            if node.height == 1 {
+                continue;
+            }
+
+            for child_index in 0..8 {
+                let child_id = node.child_ids[child_index];
+                if !node.children.get(child_index) || node_indices.contains_key(&child_id) {
+                    continue;
+                }
+
+                node_indices.insert(child_id, next_index);
+                next_index +=
+                    self.word_count(self.node(child_id), svdag.pointer_format, has_leaf_payload);
+                queue.push_back(child_id);
+            }
+        }
+
+        svdag.nodes.resize(next_index, SvdagValue::default());
+
+        for node_id in order {
+            let node = self.node(node_id);
+            let node_index = node_indices[&node_id];
+
+            svdag.nodes[node_index] = SvdagValue::from_node(SvdagNode {
+                children: node.children,
+                padding: 0,
+            });
+
+            let mut word_index = node_index + 1;
+            for child_index in 0..8 {
+                if !node.children.get(child_index) {
+                    continue;
+                }
+
+                if node.height > 1 {
+                    svdag.write_mirrored_pointer(
+                        word_index,
+                        node_indices[&node.child_ids[child_index]],
+                        node.child_mirrors[child_index],
+                    )?;
+                    word_index += svdag.pointer_format.word_count();
+                } else if has_leaf_payload {
+                    svdag.nodes[word_index] = SvdagValue {
+                        bits: node.child_ids[child_index] as u16,
+                    };
+                    word_index += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn flatten_recursive(
+        &self,
+        svdag: &mut Svdag,
+        node_indices: &mut HashMap<NodeId, usize>,
+        node_id: NodeId,
+        has_leaf_payload: bool,
+    ) -> Result<usize, SvdagError> {
+        if let Some(node_index) = node_indices.get(&node_id) {
+            return Ok(*node_index);
+        }
+
+        let node = *self.node(node_id);
+
+        //Push the node before its children to keep the parent index < child index rule
+        let node_index = svdag.nodes.len();
+        node_indices.insert(node_id, node_index);
+        svdag.nodes.push(SvdagValue::from_node(SvdagNode {
+            children: node.children,
+            padding: 0,
+        }));
+
+        if node.height > 1 {
+            let pointer_word_count = svdag.pointer_format.word_count();
+            svdag.nodes.resize(
+                svdag.nodes.len() + node.children.count_occupied() * pointer_word_count,
+                SvdagValue::default(),
+            );
+
+            let mut pointer_index = node_index + 1;
+            for child_index in 0..8 {
+                if !node.children.get(child_index) {
+                    continue;
+                }
+
+                let child_node_index = self.flatten_recursive(
+                    svdag,
+                    node_indices,
+                    node.child_ids[child_index],
+                    has_leaf_payload,
+                )?;
+                svdag.write_mirrored_pointer(
+                    pointer_index,
+                    child_node_index,
+                    node.child_mirrors[child_index],
+                )?;
+
+                pointer_index += pointer_word_count;
+            }
+        } else if has_leaf_payload {
+            for child_index in 0..8 {
+                if node.children.get(child_index) {
+                    svdag.nodes.push(SvdagValue {
+                        bits: node.child_ids[child_index] as u16,
+                    });
+                }
+            }
+        }
+
+        Ok(node_index)
+    }
+}
+
+/// Child index seen in place of `child_index` once its parent is reflected by `mirror`.
+///
+/// Mirror bits line up with child index bits: 4 reflects along x, 2 along y and 1 along z.
+pub fn mirror_child_index(child_index: usize, mirror: u8) -> usize {
+    child_index ^ mirror as usize
+}
+
+/// Position of child `child_index` of the octant at `origin` whose children have side `half_size`.
+pub fn child_origin(
+    origin: VolumePosition,
+    child_index: usize,
+    half_size: usize,
+) -> VolumePosition {
+    (
+        origin.0 + (child_index >> 2 & 1) * half_size,
+        origin.1 + (child_index >> 1 & 1) * half_size,
+        origin.2 + (child_index & 1) * half_size,
+    )
+}
@@ -0,0 +1,266 @@
+use super::{
+    svdag_brush::ClippedBrush,
+    svdag_node_table::child_origin,
+    svdag_read::{read_node, NodeRef},
+    BoxBrush, Brush, NodeTable, OctantCoverage, PointerFormat, Svdag, SvdagError, SvdagNode,
+    SvdagValue,
+};
+use crate::hashed_volume::Children;
+use std::collections::HashMap;
+
+/// Writes `value` into the voxels covered by `brush` without rebuilding the graph.
+///
+/// The nodes on the edited paths are copied to the end of the node array and the root, which
+/// always lives at index 0, is rewritten in place to point at them. Everything else is left
+/// where it is, so the cost depends on the edited region and not on the size of the graph. The
+/// replaced nodes stay behind as unreachable words and the copies come after the children they
+/// point to, both until the graph is compacted.
+pub(crate) fn patch(svdag: &mut Svdag, brush: &impl Brush, value: bool) -> Result<(), SvdagError> {
+    let depth = svdag.depth;
+    if depth == 0 || depth as u32 >= usize::BITS {
+        return Err(SvdagError::InvalidDepth(depth));
+    }
+
+    //The padding past the dimensions has to stay empty
+    let brush = ClippedBrush {
+        brush,
+        bounds: BoxBrush::new((0, 0, 0), svdag.dimensions),
+    };
+
+    match try_patch(svdag, &brush, value) {
+        //Appended nodes point back across the whole array, which relative pointers may not
+        //reach. Widening them once keeps later edits from running into the same limit.
+        Err(SvdagError::PointerOverflow { .. }) => {
+            let pointer_format = match svdag.pointer_format {
+                PointerFormat::Relative16 => PointerFormat::Absolute32,
+                pointer_format => pointer_format,
+            };
+            rebuild(svdag, 0, Some(pointer_format))?;
+
+            try_patch(svdag, &brush, value)
+        }
+        result => result,
+    }
+}
+
+/// Patches the graph, leaving it as it was if anything fails.
+fn try_patch(svdag: &mut Svdag, brush: &impl Brush, value: bool) -> Result<(), SvdagError> {
+    let word_count = svdag.nodes.len();
+    let root_word_count = node_word_count(svdag, 0)?;
+
+    let mut patcher = Patcher {
+        svdag,
+        solid_nodes: HashMap::new(),
+    };
+    let depth = patcher.svdag.depth;
+    let root = patcher.patch_octant(Some((0, 0)), depth, (0, 0, 0), brush, value);
+
+    match root {
+        //The brush didn't change anything
+        Ok(Some((0, 0))) => Ok(()),
+        Ok(root) => place_root(svdag, root, root_word_count, word_count),
+        Err(error) => {
+            svdag.nodes.truncate(word_count);
+            Err(error)
+        }
+    }
+}
+
+/// Moves the edited root to index 0. A root with more children than the old one doesn't fit in
+/// its place, the graph is then rebuilt around it.
+fn place_root(
+    svdag: &mut Svdag,
+    root: Option<NodeRef>,
+    root_word_count: usize,
+    word_count: usize,
+) -> Result<(), SvdagError> {
+    let (children, child_nodes) = match root {
+        Some(root) => read_node(svdag, root, svdag.depth)?,
+        None => (Children::default(), [None; 8]),
+    };
+
+    let pointer_word_count = svdag.pointer_format.word_count();
+    let new_root_word_count = if svdag.depth > 1 {
+        1 + children.count_occupied() * pointer_word_count
+    } else {
+        1
+    };
+
+    //An empty root is a single word, so only a root with children can outgrow its place
+    if new_root_word_count > root_word_count {
+        if let Some((root_index, _)) = root {
+            return rebuild(svdag, root_index, None);
+        }
+    }
+
+    let old_root = svdag.nodes[..root_word_count].to_vec();
+    svdag.nodes[0] = SvdagValue::from_node(SvdagNode {
+        children,
+        padding: 0,
+    });
+
+    let mut pointer_index = 1;
+    for (child_index, child_mirror) in child_nodes.iter().flatten() {
+        if let Err(error) = svdag.write_mirrored_pointer(pointer_index, *child_index, *child_mirror)
+        {
+            svdag.nodes[..root_word_count].copy_from_slice(&old_root);
+            svdag.nodes.truncate(word_count);
+            return Err(error);
+        }
+
+        pointer_index += pointer_word_count;
+    }
+
+    //A new root is always the last node appended, its copy at index 0 replaces it
+    if let Some((root_index, _)) = root {
+        if root_index >= word_count {
+            svdag.nodes.truncate(root_index);
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the node array with the deduplicated graph under the node at `root_index`.
+fn rebuild(
+    svdag: &mut Svdag,
+    root_index: usize,
+    pointer_format: Option<PointerFormat>,
+) -> Result<(), SvdagError> {
+    let mut table = NodeTable::new();
+    let root = table.insert_svdag_node(svdag, &mut HashMap::new(), root_index, svdag.depth)?;
+
+    let dimensions = svdag.dimensions;
+    *svdag = table.to_svdag(root, pointer_format)?;
+    svdag.dimensions = dimensions;
+
+    Ok(())
+}
+
+/// Number of words the node at `node_index` takes up, including its pointers.
+fn node_word_count(svdag: &Svdag, node_index: usize) -> Result<usize, SvdagError> {
+    let children = svdag
+        .nodes
+        .get(node_index)
+        .ok_or(SvdagError::InvalidNode { index: node_index })?
+        .node()
+        .children;
+
+    if svdag.depth > 1 {
+        Ok(1 + children.count_occupied() * svdag.pointer_format.word_count())
+    } else {
+        Ok(1)
+    }
+}
+
+struct Patcher<'a> {
+    svdag: &'a mut Svdag,
+    /// Index of the appended completely filled node by height.
+    solid_nodes: HashMap<u8, usize>,
+}
+
+impl<'a> Patcher<'a> {
+    /// Writes `value` into the voxels of the octant at `origin` covered by `brush`, returning the
+    /// edited octant. `node` is the octant's current node, `None` if it's empty. Edited nodes are
+    /// appended unreflected, untouched ones are returned as they are.
+    fn patch_octant(
+        &mut self,
+        node: Option<NodeRef>,
+        height: u8,
+        origin: (usize, usize, usize),
+        brush: &impl Brush,
+        value: bool,
+    ) -> Result<Option<NodeRef>, SvdagError> {
+        match brush.classify(origin, 1 << height) {
+            //Untouched octants keep their node, which is what keeps them shared
+            OctantCoverage::Empty => return Ok(node),
+            OctantCoverage::Full if value => return Ok(Some((self.solid_node(height)?, 0))),
+            OctantCoverage::Full => return Ok(None),
+            OctantCoverage::Partial => {}
+        }
+
+        let (old_children, old_child_nodes) = match node {
+            Some(node) => read_node(&*self.svdag, node, height)?,
+            None => (Children::default(), [None; 8]),
+        };
+        let mut children = old_children;
+        let mut child_nodes = old_child_nodes;
+
+        if height == 1 {
+            for child_index in 0..8 {
+                if brush.contains(child_origin(origin, child_index, 1)) {
+                    children.set(child_index, value);
+                }
+            }
+        } else {
+            let half_size = 1 << (height - 1);
+
+            for (child_index, child_node) in child_nodes.iter_mut().enumerate() {
+                *child_node = self.patch_octant(
+                    *child_node,
+                    height - 1,
+                    child_origin(origin, child_index, half_size),
+                    brush,
+                    value,
+                )?;
+                children.set(child_index, child_node.is_some());
+            }
+        }
+
+        if children == old_children && child_nodes == old_child_nodes {
+            Ok(node)
+        } else if children.have_occupied_children() {
+            Ok(Some((self.append(height, children, &child_nodes)?, 0)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the index of a completely filled node of the given height, appending it on first use.
+    fn solid_node(&mut self, height: u8) -> Result<usize, SvdagError> {
+        if let Some(node_index) = self.solid_nodes.get(&height) {
+            return Ok(*node_index);
+        }
+
+        let child_nodes = if height > 1 {
+            [Some((self.solid_node(height - 1)?, 0)); 8]
+        } else {
+            [None; 8]
+        };
+        let node_index = self.append(height, Children::new(0b1111_1111), &child_nodes)?;
+        self.solid_nodes.insert(height, node_index);
+
+        Ok(node_index)
+    }
+
+    /// Appends a node and the pointers to its children, returning its index.
+    fn append(
+        &mut self,
+        height: u8,
+        children: Children,
+        child_nodes: &[Option<NodeRef>; 8],
+    ) -> Result<usize, SvdagError> {
+        let svdag = &mut *self.svdag;
+        let node_index = svdag.nodes.len();
+        svdag.nodes.push(SvdagValue::from_node(SvdagNode {
+            children,
+            padding: 0,
+        }));
+
+        if height > 1 {
+            let pointer_word_count = svdag.pointer_format.word_count();
+            svdag.nodes.resize(
+                node_index + 1 + children.count_occupied() * pointer_word_count,
+                SvdagValue::default(),
+            );
+
+            let mut pointer_index = node_index + 1;
+            for (child_index, child_mirror) in child_nodes.iter().flatten() {
+                svdag.write_mirrored_pointer(pointer_index, *child_index, *child_mirror)?;
+                pointer_index += pointer_word_count;
+            }
+        }
+
+        Ok(node_index)
+    }
+}
@@ -0,0 +1,460 @@
+use super::SvdagError;
+use std::io::{BufRead, BufReader, Read};
+
+/// Points of a scan with optional colors.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PointCloud {
+    /// Kept in double precision, scans are often georeferenced with large coordinates.
+    pub positions: Vec<[f64; 3]>,
+    /// Either empty or one RGB color per position.
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl PointCloud {
+    pub fn new() -> PointCloud {
+        PointCloud::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn has_colors(&self) -> bool {
+        !self.colors.is_empty()
+    }
+
+    /// Smallest and largest coordinates of the finite positions, `None` if there are none.
+    pub fn bounds(&self) -> Option<([f64; 3], [f64; 3])> {
+        let mut positions = self
+            .positions
+            .iter()
+            .filter(|position| position.iter().all(|coordinate| coordinate.is_finite()));
+        let first = *positions.next()?;
+
+        Some(
+            positions.fold((first, first), |(mut min, mut max), position| {
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(position[axis]);
+                    max[axis] = max[axis].max(position[axis]);
+                }
+                (min, max)
+            }),
+        )
+    }
+
+    /// Reads a text file with a point per line as `x y z` or `x y z r g b`, separated by spaces
+    /// or commas. Columns past the color, like normals, are ignored. Lines starting with `#` or
+    /// `//` are comments and a column header may come first, like in ASCII exports of LAS files.
+    pub fn read_xyz(reader: impl Read) -> Result<PointCloud, SvdagError> {
+        let mut point_cloud = PointCloud::new();
+        let mut has_header = false;
+
+        for (line_index, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+
+            let invalid = |reason: &str| {
+                SvdagError::InvalidPointCloud(format!("line {}: {}", line_index + 1, reason))
+            };
+
+            let columns = line
+                .split(|character: char| character == ',' || character.is_whitespace())
+                .filter(|column| !column.is_empty())
+                .map(|column| column.parse::<f64>().ok())
+                .collect::<Option<Vec<_>>>();
+
+            let columns = match columns {
+                Some(columns) => columns,
+                //Only the very first line may be a header
+                None if point_cloud.is_empty() && !has_header => {
+                    has_header = true;
+                    continue;
+                }
+                None => return Err(invalid("expected numeric columns")),
+            };
+
+            if columns.len() < 3 {
+                return Err(invalid("expected at least three coordinates"));
+            }
+
+            let has_color = columns.len() >= 6;
+            if !point_cloud.is_empty() && has_color != point_cloud.has_colors() {
+                return Err(invalid("either every point or none has a color"));
+            }
+
+            point_cloud
+                .positions
+                .push([columns[0], columns[1], columns[2]]);
+
+            if has_color {
+                let mut color = [0; 3];
+                for (channel, value) in color.iter_mut().zip(&columns[3..6]) {
+                    if value.fract() != 0.0 || !(0.0..=255.0).contains(value) {
+                        return Err(invalid("colors have to be integers from 0 to 255"));
+                    }
+                    *channel = *value as u8;
+                }
+                point_cloud.colors.push(color);
+            }
+        }
+
+        Ok(point_cloud)
+    }
+
+    /// Reads the `x`, `y`, `z` and optional `red`, `green`, `blue` properties of the vertices of
+    /// an ASCII or binary PLY file. Faces and other elements are ignored.
+    pub fn read_ply(mut reader: impl Read) -> Result<PointCloud, SvdagError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        let (header, body_start) = PlyHeader::parse(&bytes)?;
+        let body = &bytes[body_start..];
+
+        match header.format {
+            PlyFormat::Ascii => read_ascii_ply_vertices(&header, body),
+            PlyFormat::BinaryLittleEndian => read_binary_ply_vertices(&header, body, false),
+            PlyFormat::BinaryBigEndian => read_binary_ply_vertices(&header, body, true),
+        }
+    }
+}
+
+fn invalid_ply(reason: impl Into<String>) -> SvdagError {
+    SvdagError::InvalidPointCloud(format!("PLY {}", reason.into()))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PlyScalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyScalar {
+    fn parse(name: &str) -> Option<PlyScalar> {
+        Some(match name {
+            "char" | "int8" => PlyScalar::I8,
+            "uchar" | "uint8" => PlyScalar::U8,
+            "short" | "int16" => PlyScalar::I16,
+            "ushort" | "uint16" => PlyScalar::U16,
+            "int" | "int32" => PlyScalar::I32,
+            "uint" | "uint32" => PlyScalar::U32,
+            "float" | "float32" => PlyScalar::F32,
+            "double" | "float64" => PlyScalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            PlyScalar::I8 | PlyScalar::U8 => 1,
+            PlyScalar::I16 | PlyScalar::U16 => 2,
+            PlyScalar::I32 | PlyScalar::U32 | PlyScalar::F32 => 4,
+            PlyScalar::F64 => 8,
+        }
+    }
+
+    /// Decodes a value from exactly `size()` bytes.
+    fn read(self, bytes: &[u8], big_endian: bool) -> f64 {
+        let mut buffer = [0; 8];
+        buffer[..bytes.len()].copy_from_slice(bytes);
+        if big_endian {
+            buffer[..bytes.len()].reverse();
+        }
+
+        match self {
+            PlyScalar::I8 => buffer[0] as i8 as f64,
+            PlyScalar::U8 => buffer[0] as f64,
+            PlyScalar::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            PlyScalar::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            PlyScalar::I32 => {
+                i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+            }
+            PlyScalar::U32 => {
+                u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+            }
+            PlyScalar::F32 => {
+                f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+            }
+            PlyScalar::F64 => f64::from_le_bytes(buffer),
+        }
+    }
+
+    /// Maps a color channel to 0-255, 16 bit colors are scaled down and floats are taken as 0-1.
+    fn to_color_channel(self, value: f64) -> u8 {
+        let value = match self {
+            PlyScalar::U16 | PlyScalar::I16 => value / 257.0,
+            PlyScalar::F32 | PlyScalar::F64 => value * 255.0,
+            _ => value,
+        };
+
+        value.round().clamp(0.0, 255.0) as u8
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PlyProperty {
+    name: String,
+    scalar: PlyScalar,
+    /// Scalar type of the length prefix of list properties.
+    list_length: Option<PlyScalar>,
+}
+
+#[derive(Clone, Debug)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+struct PlyHeader {
+    format: PlyFormat,
+    elements: Vec<PlyElement>,
+}
+
+impl PlyHeader {
+    /// Parses the header, returning it together with the offset of the body.
+    fn parse(bytes: &[u8]) -> Result<(PlyHeader, usize), SvdagError> {
+        if !bytes.starts_with(b"ply") {
+            return Err(invalid_ply("file has to start with `ply`"));
+        }
+
+        let mut format = None;
+        let mut elements: Vec<PlyElement> = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let line_end = bytes[offset..]
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .map(|position| offset + position)
+                .ok_or_else(|| invalid_ply("header has no `end_header`"))?;
+            let line = std::str::from_utf8(&bytes[offset..line_end])
+                .map_err(|_| invalid_ply("header isn't valid UTF-8"))?;
+            offset = line_end + 1;
+
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                ["end_header"] => break,
+                ["format", name, _] => {
+                    format = Some(match *name {
+                        "ascii" => PlyFormat::Ascii,
+                        "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                        "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                        _ => return Err(invalid_ply(format!("format `{}` is unknown", name))),
+                    })
+                }
+                ["element", name, count] => elements.push(PlyElement {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| invalid_ply(format!("element count `{}`", count)))?,
+                    properties: Vec::new(),
+                }),
+                ["property", "list", length, scalar, name] => {
+                    let property = PlyProperty {
+                        name: name.to_string(),
+                        scalar: parse_scalar(scalar)?,
+                        list_length: Some(parse_scalar(length)?),
+                    };
+                    push_property(&mut elements, property)?;
+                }
+                ["property", scalar, name] => {
+                    let property = PlyProperty {
+                        name: name.to_string(),
+                        scalar: parse_scalar(scalar)?,
+                        list_length: None,
+                    };
+                    push_property(&mut elements, property)?;
+                }
+                _ => {}
+            }
+        }
+
+        let format = format.ok_or_else(|| invalid_ply("header has no format"))?;
+
+        Ok((PlyHeader { format, elements }, offset))
+    }
+}
+
+fn parse_scalar(name: &str) -> Result<PlyScalar, SvdagError> {
+    PlyScalar::parse(name)
+        .ok_or_else(|| invalid_ply(format!("property type `{}` is unknown", name)))
+}
+
+fn push_property(elements: &mut [PlyElement], property: PlyProperty) -> Result<(), SvdagError> {
+    elements
+        .last_mut()
+        .ok_or_else(|| invalid_ply("property comes before any element"))?
+        .properties
+        .push(property);
+
+    Ok(())
+}
+
+/// Where the coordinates and colors are found among the properties of the vertex element.
+struct VertexLayout {
+    coordinates: [usize; 3],
+    colors: Option<[usize; 3]>,
+}
+
+impl VertexLayout {
+    fn new(element: &PlyElement) -> Result<VertexLayout, SvdagError> {
+        let find = |name: &str| {
+            element
+                .properties
+                .iter()
+                .position(|property| property.name == name && property.list_length.is_none())
+        };
+
+        let mut coordinates = [0; 3];
+        for (index, name) in coordinates.iter_mut().zip(["x", "y", "z"].iter()) {
+            *index = find(name)
+                .ok_or_else(|| invalid_ply(format!("vertices have no `{}` property", name)))?;
+        }
+
+        let colors = match (find("red"), find("green"), find("blue")) {
+            (Some(red), Some(green), Some(blue)) => Some([red, green, blue]),
+            _ => None,
+        };
+
+        Ok(VertexLayout {
+            coordinates,
+            colors,
+        })
+    }
+
+    fn push(&self, element: &PlyElement, values: &[f64], point_cloud: &mut PointCloud) {
+        point_cloud.positions.push([
+            values[self.coordinates[0]],
+            values[self.coordinates[1]],
+            values[self.coordinates[2]],
+        ]);
+
+        if let Some(colors) = self.colors {
+            let channel = |index: usize| {
+                element.properties[index]
+                    .scalar
+                    .to_color_channel(values[index])
+            };
+            point_cloud
+                .colors
+                .push([channel(colors[0]), channel(colors[1]), channel(colors[2])]);
+        }
+    }
+}
+
+fn read_ascii_ply_vertices(header: &PlyHeader, body: &[u8]) -> Result<PointCloud, SvdagError> {
+    let body = std::str::from_utf8(body).map_err(|_| invalid_ply("body isn't valid UTF-8"))?;
+    let mut tokens = body.split_whitespace();
+    let mut next_value = || -> Result<f64, SvdagError> {
+        let token = tokens
+            .next()
+            .ok_or_else(|| invalid_ply("body ends before all elements"))?;
+        token
+            .parse()
+            .map_err(|_| invalid_ply(format!("value `{}` isn't a number", token)))
+    };
+
+    let mut point_cloud = PointCloud::new();
+
+    for element in header.elements.iter() {
+        let layout = if element.name == "vertex" {
+            Some(VertexLayout::new(element)?)
+        } else {
+            None
+        };
+        let mut values = vec![0.0; element.properties.len()];
+
+        for _ in 0..element.count {
+            for (value, property) in values.iter_mut().zip(element.properties.iter()) {
+                if property.list_length.is_some() {
+                    let length = next_value()? as usize;
+                    for _ in 0..length {
+                        next_value()?;
+                    }
+                } else {
+                    *value = next_value()?;
+                }
+            }
+
+            if let Some(layout) = &layout {
+                layout.push(element, &values, &mut point_cloud);
+            }
+        }
+
+        if layout.is_some() {
+            return Ok(point_cloud);
+        }
+    }
+
+    Err(invalid_ply("file has no vertex element"))
+}
+
+fn read_binary_ply_vertices(
+    header: &PlyHeader,
+    body: &[u8],
+    big_endian: bool,
+) -> Result<PointCloud, SvdagError> {
+    let mut offset = 0;
+    let mut next_value = |scalar: PlyScalar| -> Result<f64, SvdagError> {
+        let bytes = body
+            .get(offset..offset + scalar.size())
+            .ok_or_else(|| invalid_ply("body ends before all elements"))?;
+        offset += scalar.size();
+        Ok(scalar.read(bytes, big_endian))
+    };
+
+    let mut point_cloud = PointCloud::new();
+
+    for element in header.elements.iter() {
+        let layout = if element.name == "vertex" {
+            Some(VertexLayout::new(element)?)
+        } else {
+            None
+        };
+        let mut values = vec![0.0; element.properties.len()];
+
+        for _ in 0..element.count {
+            for (value, property) in values.iter_mut().zip(element.properties.iter()) {
+                match property.list_length {
+                    Some(length_scalar) => {
+                        let length = next_value(length_scalar)? as usize;
+                        for _ in 0..length {
+                            next_value(property.scalar)?;
+                        }
+                    }
+                    None => *value = next_value(property.scalar)?,
+                }
+            }
+
+            if let Some(layout) = &layout {
+                layout.push(element, &values, &mut point_cloud);
+            }
+        }
+
+        if layout.is_some() {
+            return Ok(point_cloud);
+        }
+    }
+
+    Err(invalid_ply("file has no vertex element"))
+}
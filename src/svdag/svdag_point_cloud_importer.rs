@@ -0,0 +1,266 @@
+use super::{
+    svdag_node_table::{NodeId, NodeTable, TableNode},
+    AttributedSvdag, PointCloud, PointerFormat, Svdag, SvdagError,
+};
+use crate::{hashed_volume::Children, volume::VolumeDimensions};
+
+/// Deepest graph whose voxel keys fit into a `u128`.
+const MAX_DEPTH: u8 = 42;
+
+/// Bins point clouds into voxels and builds graphs from them.
+///
+/// Every point gets the Morton key of its voxel, which equals the child index path from the
+/// root. After sorting the keys the occupied voxels of every octant lie next to each other, so
+/// the graph is built bottom-up from runs of keys and memory only grows with the number of
+/// points, never with the volume.
+pub struct PointCloudImporter {
+    depth: u8,
+    bounds: Option<([f64; 3], [f64; 3])>,
+    min_points: usize,
+    pointer_format: Option<PointerFormat>,
+}
+
+impl PointCloudImporter {
+    pub fn new(depth: u8) -> PointCloudImporter {
+        PointCloudImporter {
+            depth,
+            bounds: None,
+            min_points: 1,
+            pointer_format: None,
+        }
+    }
+
+    /// Region to import, points outside of it are dropped. By default the bounds of the points
+    /// are used. Voxels are cubes sized so the longest side of the box fits the volume, the
+    /// graph's dimensions cover the box.
+    pub fn bounds(&mut self, min: [f64; 3], max: [f64; 3]) -> &mut Self {
+        self.bounds = Some((min, max));
+        self
+    }
+
+    /// Number of points a voxel needs to be occupied, to filter out stray points.
+    pub fn min_points(&mut self, min_points: usize) -> &mut Self {
+        self.min_points = min_points.max(1);
+        self
+    }
+
+    /// Forces a pointer format, by default the narrowest format that fits the graph is used.
+    pub fn pointer_format(&mut self, pointer_format: PointerFormat) -> &mut Self {
+        self.pointer_format = Some(pointer_format);
+        self
+    }
+
+    pub fn build(&self, point_cloud: &PointCloud) -> Result<Svdag, SvdagError> {
+        let (voxels, dimensions) = self.bin_points(point_cloud, false)?;
+
+        self.build_geometry(&voxels, dimensions)
+    }
+
+    /// Builds the graph with the average color of the points in every voxel.
+    pub fn build_colored(
+        &self,
+        point_cloud: &PointCloud,
+    ) -> Result<AttributedSvdag<[u8; 3]>, SvdagError> {
+        if !point_cloud.has_colors() {
+            return Err(SvdagError::InvalidPointCloud(
+                "points have no colors".to_string(),
+            ));
+        }
+
+        let (voxels, dimensions) = self.bin_points(point_cloud, true)?;
+        let geometry = self.build_geometry(&voxels, dimensions)?;
+
+        //Voxels are sorted in child index order, the order attributes are stored in
+        let colors = voxels
+            .iter()
+            .map(|voxel| {
+                let average = |channel: usize| {
+                    ((voxel.color_sums[channel] + voxel.count / 2) / voxel.count) as u8
+                };
+                [average(0), average(1), average(2)]
+            })
+            .collect();
+
+        Ok(AttributedSvdag::from_geometry(geometry, colors))
+    }
+
+    /// Sorted occupied voxels and the dimensions of the imported region.
+    fn bin_points(
+        &self,
+        point_cloud: &PointCloud,
+        with_colors: bool,
+    ) -> Result<(Vec<Voxel>, VolumeDimensions), SvdagError> {
+        if self.depth == 0 || self.depth > MAX_DEPTH || self.depth as u32 >= usize::BITS {
+            return Err(SvdagError::InvalidDepth(self.depth));
+        }
+
+        if with_colors && point_cloud.colors.len() != point_cloud.positions.len() {
+            return Err(SvdagError::InvalidPointCloud(format!(
+                "{} colors for {} points",
+                point_cloud.colors.len(),
+                point_cloud.positions.len()
+            )));
+        }
+
+        let side_size = 1usize << self.depth;
+        let (min, max) = match self.bounds.or_else(|| point_cloud.bounds()) {
+            Some(bounds) => bounds,
+            None => return Ok((Vec::new(), (side_size, side_size, side_size))),
+        };
+
+        if (0..3)
+            .any(|axis| !min[axis].is_finite() || !max[axis].is_finite() || min[axis] > max[axis])
+        {
+            return Err(SvdagError::InvalidPointCloud(format!(
+                "bounding box from {:?} to {:?} is empty or infinite",
+                min, max
+            )));
+        }
+
+        let extents = [max[0] - min[0], max[1] - min[1], max[2] - min[2]];
+        let longest_extent = extents[0].max(extents[1]).max(extents[2]);
+        let voxel_size = if longest_extent > 0.0 {
+            longest_extent / side_size as f64
+        } else {
+            1.0
+        };
+
+        let side = |axis: usize| ((extents[axis] / voxel_size).ceil() as usize).clamp(1, side_size);
+        let dimensions = [side(0), side(1), side(2)];
+
+        let mut keys: Vec<(u128, [u8; 3])> = point_cloud
+            .positions
+            .iter()
+            .enumerate()
+            .filter(|(_, position)| {
+                (0..3).all(|axis| position[axis] >= min[axis] && position[axis] <= max[axis])
+            })
+            .map(|(index, position)| {
+                //Points on the upper faces of the box belong to the last voxel
+                let mut voxel_position = [0; 3];
+                for axis in 0..3 {
+                    let coordinate = ((position[axis] - min[axis]) / voxel_size) as usize;
+                    voxel_position[axis] = coordinate.min(dimensions[axis] - 1);
+                }
+
+                let color = if with_colors {
+                    point_cloud.colors[index]
+                } else {
+                    [0; 3]
+                };
+                (morton_key(voxel_position, self.depth), color)
+            })
+            .collect();
+
+        keys.sort_unstable_by_key(|(key, _)| *key);
+
+        let mut voxels: Vec<Voxel> = Vec::new();
+        for (key, color) in keys {
+            match voxels.last_mut() {
+                Some(voxel) if voxel.key == key => voxel.add(color),
+                _ => {
+                    //The previous voxel is complete, drop it if it has too few points
+                    if voxels
+                        .last()
+                        .is_some_and(|voxel| voxel.count < self.min_points as u64)
+                    {
+                        voxels.pop();
+                    }
+
+                    let mut voxel = Voxel {
+                        key,
+                        count: 0,
+                        color_sums: [0; 3],
+                    };
+                    voxel.add(color);
+                    voxels.push(voxel);
+                }
+            }
+        }
+
+        if voxels
+            .last()
+            .is_some_and(|voxel| voxel.count < self.min_points as u64)
+        {
+            voxels.pop();
+        }
+
+        Ok((voxels, (dimensions[0], dimensions[1], dimensions[2])))
+    }
+
+    fn build_geometry(
+        &self,
+        voxels: &[Voxel],
+        dimensions: VolumeDimensions,
+    ) -> Result<Svdag, SvdagError> {
+        let mut table = NodeTable::new();
+        let root = if voxels.is_empty() {
+            table.insert(TableNode::empty(self.depth))?
+        } else {
+            build_octant(&mut table, self.depth, voxels)?
+        };
+
+        let mut svdag = table.to_svdag(root, self.pointer_format)?;
+        svdag.dimensions = dimensions;
+
+        Ok(svdag)
+    }
+}
+
+/// An occupied voxel with the points binned into it.
+struct Voxel {
+    key: u128,
+    count: u64,
+    color_sums: [u64; 3],
+}
+
+impl Voxel {
+    fn add(&mut self, color: [u8; 3]) {
+        self.count += 1;
+        for (sum, channel) in self.color_sums.iter_mut().zip(color.iter()) {
+            *sum += *channel as u64;
+        }
+    }
+}
+
+/// Interleaves the coordinate bits from the top level down, three bits per level in child
+/// index order.
+fn morton_key(position: [usize; 3], depth: u8) -> u128 {
+    (0..depth).rev().fold(0, |key, level| {
+        let child_index = (position[0] >> level & 1) << 2
+            | (position[1] >> level & 1) << 1
+            | (position[2] >> level & 1);
+        key << 3 | child_index as u128
+    })
+}
+
+/// Builds the node of height `height` from its sorted, non-empty voxels.
+fn build_octant(table: &mut NodeTable, height: u8, voxels: &[Voxel]) -> Result<NodeId, SvdagError> {
+    let shift = 3 * (height as u32 - 1);
+    let child_index_of = |voxel: &Voxel| (voxel.key >> shift & 0b111) as usize;
+
+    if height == 1 {
+        let mask = voxels
+            .iter()
+            .fold(0u8, |mask, voxel| mask | 1 << child_index_of(voxel));
+        return table.insert(TableNode::leaf(Children::new(mask)));
+    }
+
+    let mut node = TableNode::empty(height);
+    let mut start = 0;
+
+    while start < voxels.len() {
+        let child_index = child_index_of(&voxels[start]);
+        let end = start
+            + voxels[start..]
+                .iter()
+                .take_while(|voxel| child_index_of(voxel) == child_index)
+                .count();
+
+        node.children.set(child_index, true);
+        node.child_ids[child_index] = build_octant(table, height - 1, &voxels[start..end])?;
+        start = end;
+    }
+
+    table.insert(node)
+}
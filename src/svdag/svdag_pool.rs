@@ -0,0 +1,245 @@
+use super::{
+    svdag_brush::ClippedBrush, svdag_editor::edit_octant, svdag_raycast::raycast_table,
+    svdag_read::cube_dimensions, BoxBrush, Brush, NodeId, NodeTable, OctantCoverage, PointerFormat,
+    Svdag, SvdagError, SvdagHit, SvdagRead, SvdagStreamBuilder, TableNode,
+};
+use crate::volume::{DensityVolume, IsVolume, VolumeDimensions, VolumePosition};
+use std::mem;
+
+/// A graph stored in an `SvdagPool`, only meaningful together with the pool that returned it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SvdagHandle {
+    pub root: NodeId,
+    pub depth: u8,
+    /// Logical extent of the graph inside the cube of side `2^depth`, voxels past it don't exist.
+    pub dimensions: VolumeDimensions,
+}
+
+/// One deduplicated node store shared by many graphs.
+///
+/// Every graph added to the pool goes through the same `NodeTable`, so a subtree that occurs in
+/// several of them, be it empty space, solid ground or a repeated prop, is stored only once.
+/// Equal graphs get equal handles. Edits return new handles and leave the old graphs in the
+/// pool, `collect_garbage` drops the nodes of every graph that is no longer needed.
+#[derive(Clone, Debug, Default)]
+pub struct SvdagPool {
+    table: NodeTable,
+}
+
+impl SvdagPool {
+    pub fn new() -> SvdagPool {
+        SvdagPool {
+            table: NodeTable::new(),
+        }
+    }
+
+    /// Adds the graph of the given depth with every voxel sampled through `sample`.
+    pub fn insert_fn(
+        &mut self,
+        depth: u8,
+        sample: impl FnMut(VolumePosition) -> bool,
+    ) -> Result<SvdagHandle, SvdagError> {
+        self.insert_octants(depth, |_, _| OctantCoverage::Partial, sample)
+    }
+
+    /// Adds a graph built top-down from octant classifications, see
+    /// `SvdagStreamBuilder::build_from_octants`.
+    pub fn insert_octants(
+        &mut self,
+        depth: u8,
+        classify: impl FnMut(VolumePosition, usize) -> OctantCoverage,
+        sample: impl FnMut(VolumePosition) -> bool,
+    ) -> Result<SvdagHandle, SvdagError> {
+        self.build(depth, cube_dimensions(depth), classify, sample)
+    }
+
+    pub fn insert_volume(&mut self, volume: &DensityVolume) -> Result<SvdagHandle, SvdagError> {
+        self.build(
+            volume.depth,
+            volume.get_dimensions(),
+            |_, _| OctantCoverage::Partial,
+            |position| *volume.get(position),
+        )
+    }
+
+    /// Adds an existing graph, sharing its nodes with everything already in the pool.
+    pub fn insert_svdag<S>(&mut self, svdag: &S) -> Result<SvdagHandle, SvdagError>
+    where
+        S: SvdagRead + ?Sized,
+    {
+        let root = self.table.insert_svdag(svdag)?;
+
+        Ok(SvdagHandle {
+            root,
+            depth: svdag.depth(),
+            dimensions: svdag.dimensions(),
+        })
+    }
+
+    /// Looks up a voxel of the graph behind `handle`, returning `None` if it lies outside the volume.
+    pub fn get(&self, handle: SvdagHandle, position: VolumePosition) -> Option<bool> {
+        check_position(handle, position).ok()?;
+
+        self.table.get(handle.root, 0, position)
+    }
+
+    /// Casts a ray through the graph behind `handle`, see `SvdagRead::raycast`.
+    pub fn raycast(
+        &self,
+        handle: SvdagHandle,
+        origin: [f32; 3],
+        direction: [f32; 3],
+        max_t: f32,
+    ) -> Option<SvdagHit> {
+        raycast_table(&self.table, handle.root, origin, direction, max_t)
+    }
+
+    /// Returns the graph behind `handle` with the voxel at `position` set or cleared. Only the
+    /// path to the voxel is copied, the rest stays shared with the original graph.
+    pub fn set(
+        &mut self,
+        handle: SvdagHandle,
+        position: VolumePosition,
+        value: bool,
+    ) -> Result<SvdagHandle, SvdagError> {
+        check_position(handle, position)?;
+
+        self.edit(handle, &BoxBrush::voxel(position), value)
+    }
+
+    /// Returns the graph behind `handle` with every voxel covered by `brush` occupied, the parts
+    /// outside of its dimensions are ignored.
+    pub fn fill(
+        &mut self,
+        handle: SvdagHandle,
+        brush: &impl Brush,
+    ) -> Result<SvdagHandle, SvdagError> {
+        self.edit(handle, brush, true)
+    }
+
+    /// Returns the graph behind `handle` with every voxel covered by `brush` emptied, the parts
+    /// outside of its dimensions are ignored.
+    pub fn carve(
+        &mut self,
+        handle: SvdagHandle,
+        brush: &impl Brush,
+    ) -> Result<SvdagHandle, SvdagError> {
+        self.edit(handle, brush, false)
+    }
+
+    fn edit(
+        &mut self,
+        handle: SvdagHandle,
+        brush: &impl Brush,
+        value: bool,
+    ) -> Result<SvdagHandle, SvdagError> {
+        //The padding past the dimensions has to stay empty
+        let brush = ClippedBrush {
+            brush,
+            bounds: BoxBrush::new((0, 0, 0), handle.dimensions),
+        };
+
+        let root = if self.is_empty_graph(handle) {
+            None
+        } else {
+            Some((handle.root, 0))
+        };
+
+        let root = match edit_octant(
+            &mut self.table,
+            root,
+            handle.depth,
+            (0, 0, 0),
+            &brush,
+            value,
+        )? {
+            Some((node_id, 0)) => node_id,
+            Some((node_id, mirror)) => {
+                let root = self.table.node(node_id).mirrored(mirror);
+                self.table.insert(root)?
+            }
+            //A completely empty volume still needs a root node to be a valid graph
+            None => self.table.insert(TableNode::empty(handle.depth))?,
+        };
+
+        Ok(SvdagHandle { root, ..handle })
+    }
+
+    /// Checks whether the graph behind `handle` has no occupied voxels.
+    pub fn is_empty_graph(&self, handle: SvdagHandle) -> bool {
+        !self
+            .table
+            .node(handle.root)
+            .children
+            .have_occupied_children()
+    }
+
+    /// Flattens the graph behind `handle` into a standalone `Svdag`, using the narrowest pointer
+    /// format that fits unless one is given.
+    pub fn to_svdag(
+        &self,
+        handle: SvdagHandle,
+        pointer_format: Option<PointerFormat>,
+    ) -> Result<Svdag, SvdagError> {
+        let svdag = self.table.to_svdag(handle.root, pointer_format)?;
+
+        Ok(Svdag {
+            dimensions: handle.dimensions,
+            ..svdag
+        })
+    }
+
+    /// Drops every node not used by the graphs behind `handles` and points the handles at the
+    /// renumbered roots, returning how many nodes were freed. Every other handle becomes invalid.
+    pub fn collect_garbage(&mut self, handles: &mut [SvdagHandle]) -> usize {
+        let node_count = self.table.len();
+        let new_ids = self
+            .table
+            .retain_reachable(handles.iter().map(|handle| handle.root));
+
+        for handle in handles.iter_mut() {
+            handle.root = new_ids[&handle.root];
+        }
+
+        node_count - self.table.len()
+    }
+
+    /// Number of unique nodes stored for all graphs together.
+    pub fn node_count(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn table(&self) -> &NodeTable {
+        &self.table
+    }
+
+    fn build(
+        &mut self,
+        depth: u8,
+        dimensions: VolumeDimensions,
+        classify: impl FnMut(VolumePosition, usize) -> OctantCoverage,
+        sample: impl FnMut(VolumePosition) -> bool,
+    ) -> Result<SvdagHandle, SvdagError> {
+        let mut builder = SvdagStreamBuilder::with_table(depth, mem::take(&mut self.table));
+        let root = builder
+            .dimensions(dimensions)
+            .build_root_from_octants(classify, sample);
+        self.table = builder.into_table();
+
+        Ok(SvdagHandle {
+            root: root?,
+            depth,
+            dimensions,
+        })
+    }
+}
+
+fn check_position(handle: SvdagHandle, position: VolumePosition) -> Result<(), SvdagError> {
+    let dimensions = handle.dimensions;
+
+    if position.0 >= dimensions.0 || position.1 >= dimensions.1 || position.2 >= dimensions.2 {
+        Err(SvdagError::OutOfBounds { position })
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use super::{svdag_node_table::mirror_child_index, NodeId, NodeTable, SvdagRead};
+use crate::volume::VolumePosition;
+
+/// First occupied voxel along a ray.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SvdagHit {
+    pub position: VolumePosition,
+    /// Ray parameter at which the voxel is entered, a world distance if the direction is normalized.
+    pub distance: f32,
+    /// Normal of the voxel face the ray entered through, all zero if the ray starts inside the voxel.
+    pub normal: [i32; 3],
+    /// Number of octree cells visited, empty octants count once regardless of their size.
+    pub steps: usize,
+}
+
+/// Casts a ray through the graph, voxel `(x, y, z)` spans `[x, x + 1)` on every axis.
+///
+/// Every step descends from the root to the cell holding the current voxel. An empty octant is
+/// skipped as a whole by jumping to the voxel right behind its exit face.
+pub fn raycast<S>(svdag: &S, origin: [f32; 3], direction: [f32; 3], max_t: f32) -> Option<SvdagHit>
+where
+    S: SvdagRead + ?Sized,
+{
+    let pointer_word_count = svdag.pointer_format().word_count();
+
+    raycast_cells(svdag.depth(), origin, direction, max_t, |position| {
+        let mut node_index = 0;
+        let mut mirror = 0;
+
+        for level in (0..svdag.depth()).rev() {
+            let child_index = mirror_child_index(
+                (position[0] >> level & 1) << 2
+                    | (position[1] >> level & 1) << 1
+                    | (position[2] >> level & 1),
+                mirror,
+            );
+
+            let node = svdag.word(node_index)?.node();
+
+            if !node.children.get(child_index) {
+                return Some(Some(level));
+            }
+
+            if level > 0 {
+                let child_pointer_index =
+                    node_index + 1 + node.children.get_n(child_index) * pointer_word_count;
+                let (child_node_index, child_mirror) =
+                    svdag.read_mirrored_pointer(child_pointer_index)?;
+                node_index = child_node_index;
+                mirror ^= child_mirror;
+            }
+        }
+
+        Some(None)
+    })
+}
+
+/// Like `raycast`, through the subtree under `root` of a `NodeTable`.
+pub(crate) fn raycast_table(
+    table: &NodeTable,
+    root: NodeId,
+    origin: [f32; 3],
+    direction: [f32; 3],
+    max_t: f32,
+) -> Option<SvdagHit> {
+    let depth = table.node(root).height;
+
+    raycast_cells(depth, origin, direction, max_t, |position| {
+        let mut node_id = root;
+        let mut mirror = 0;
+
+        for level in (0..depth).rev() {
+            let child_index = mirror_child_index(
+                (position[0] >> level & 1) << 2
+                    | (position[1] >> level & 1) << 1
+                    | (position[2] >> level & 1),
+                mirror,
+            );
+
+            let node = table.node(node_id);
+
+            if !node.children.get(child_index) {
+                return Some(Some(level));
+            }
+
+            if level > 0 {
+                node_id = node.child_ids[child_index];
+                mirror ^= node.child_mirrors[child_index];
+            }
+        }
+
+        Some(None)
+    })
+}
+
+/// Walks the ray through a volume of side `2^depth`. For every step `find_cell` gets the current
+/// voxel and returns the level of the largest empty cell holding it, `None` if the voxel itself
+/// is occupied, or fails if the graph is malformed.
+fn raycast_cells(
+    depth: u8,
+    origin: [f32; 3],
+    direction: [f32; 3],
+    max_t: f32,
+    mut find_cell: impl FnMut([usize; 3]) -> Option<Option<u8>>,
+) -> Option<SvdagHit> {
+    if depth == 0 || depth as u32 >= usize::BITS {
+        return None;
+    }
+    let side_size = 1usize << depth;
+
+    //Clip the ray against the volume's bounding box
+    let mut t = 0.0f32;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = [0; 3];
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if origin[axis] < 0.0 || origin[axis] >= side_size as f32 {
+                return None;
+            }
+            continue;
+        }
+
+        let t0 = -origin[axis] / direction[axis];
+        let t1 = (side_size as f32 - origin[axis]) / direction[axis];
+        let (t_near, t_far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+        if t_near > t {
+            t = t_near;
+            normal = [0; 3];
+            normal[axis] = -direction[axis].signum() as i32;
+        }
+        t_exit = t_exit.min(t_far);
+    }
+
+    if t > t_exit || t > max_t {
+        return None;
+    }
+
+    let mut position = [0; 3];
+    for axis in 0..3 {
+        position[axis] = if normal[axis] != 0 {
+            //Entering through a face of the bounding box puts the ray in the outermost voxel layer
+            if direction[axis] > 0.0 {
+                0
+            } else {
+                side_size - 1
+            }
+        } else {
+            clamp_to_cell(origin[axis] + direction[axis] * t, 0, side_size)
+        };
+    }
+
+    let mut steps = 0;
+
+    loop {
+        steps += 1;
+
+        //Find the deepest cell containing the current voxel, either an empty octant or the voxel itself
+        let empty_cell_level = find_cell(position)?;
+
+        let level = match empty_cell_level {
+            Some(level) => level,
+            None => {
+                return Some(SvdagHit {
+                    position: (position[0], position[1], position[2]),
+                    distance: t,
+                    normal,
+                    steps,
+                })
+            }
+        };
+
+        //Leave the empty cell through the face the ray hits first
+        let cell_size = 1usize << level;
+        let cell_min = [
+            position[0] & !(cell_size - 1),
+            position[1] & !(cell_size - 1),
+            position[2] & !(cell_size - 1),
+        ];
+
+        let mut exit_axis = 0;
+        let mut t_cell_exit = f32::INFINITY;
+        for axis in 0..3 {
+            let boundary = if direction[axis] > 0.0 {
+                (cell_min[axis] + cell_size) as f32
+            } else if direction[axis] < 0.0 {
+                cell_min[axis] as f32
+            } else {
+                continue;
+            };
+
+            let t_axis = (boundary - origin[axis]) / direction[axis];
+            if t_axis < t_cell_exit {
+                t_cell_exit = t_axis;
+                exit_axis = axis;
+            }
+        }
+
+        if !t_cell_exit.is_finite() || t_cell_exit > max_t {
+            return None;
+        }
+
+        for axis in 0..3 {
+            if axis == exit_axis {
+                position[axis] = if direction[axis] > 0.0 {
+                    cell_min[axis] + cell_size
+                } else if cell_min[axis] == 0 {
+                    return None;
+                } else {
+                    cell_min[axis] - 1
+                };
+
+                if position[axis] >= side_size {
+                    return None;
+                }
+            } else {
+                position[axis] = clamp_to_cell(
+                    origin[axis] + direction[axis] * t_cell_exit,
+                    cell_min[axis],
+                    cell_size,
+                );
+            }
+        }
+
+        t = t.max(t_cell_exit);
+        normal = [0; 3];
+        normal[exit_axis] = -direction[exit_axis].signum() as i32;
+    }
+}
+
+/// Voxel coordinate of `value`, kept inside the cell `[cell_min, cell_min + cell_size)` to absorb rounding.
+fn clamp_to_cell(value: f32, cell_min: usize, cell_size: usize) -> usize {
+    let voxel = value.floor().max(0.0) as usize;
+
+    voxel.clamp(cell_min, cell_min + cell_size - 1)
+}
@@ -0,0 +1,327 @@
+use super::{
+    svdag_extract, svdag_neighbors, svdag_node_table::mirror_child_index, svdag_raycast, FaceMask,
+    OccupiedNodes, OccupiedVoxels, PointerFormat, SurfaceVoxels, SvdagError, SvdagHit, SvdagValue,
+};
+use crate::{
+    hashed_volume::Children,
+    volume::{DensityVolume, VolumeDimensions, VolumePosition},
+};
+use std::collections::{HashMap, HashSet};
+
+/// Read access to a node array laid out like `Svdag::nodes`, wherever the words are stored.
+pub trait SvdagRead {
+    fn depth(&self) -> u8;
+
+    fn pointer_format(&self) -> PointerFormat;
+
+    /// Number of words in the node array.
+    fn word_count(&self) -> usize;
+
+    fn word(&self, index: usize) -> Option<SvdagValue>;
+
+    /// Logical extent of the volume, by default the whole cube of side `2^depth`.
+    fn dimensions(&self) -> VolumeDimensions {
+        cube_dimensions(self.depth())
+    }
+
+    /// Resolves the pointer stored at `pointer_index` to the absolute index of the node it points to.
+    fn read_pointer(&self, pointer_index: usize) -> Option<usize> {
+        self.read_mirrored_pointer(pointer_index)
+            .map(|(target_index, _)| target_index)
+    }
+
+    /// Resolves the pointer stored at `pointer_index` to the absolute index of the node it points
+    /// to and the reflection applied to that node, which is 0 for all but `Mirrored32` pointers.
+    fn read_mirrored_pointer(&self, pointer_index: usize) -> Option<(usize, u8)> {
+        match self.pointer_format() {
+            PointerFormat::Relative16 => {
+                let pointer = self.word(pointer_index)?.pointer();
+                let target_index = pointer_index as isize + pointer.value as isize;
+
+                if target_index < 0 {
+                    None
+                } else {
+                    Some((target_index as usize, 0))
+                }
+            }
+            PointerFormat::Absolute32 => {
+                let low = self.word(pointer_index)?.bits as usize;
+                let high = self.word(pointer_index + 1)?.bits as usize;
+
+                Some((high << 16 | low, 0))
+            }
+            PointerFormat::Mirrored32 => {
+                let low = self.word(pointer_index)?.bits as usize;
+                let high = self.word(pointer_index + 1)?.bits;
+
+                Some(((high as usize & 0x1fff) << 16 | low, (high >> 13) as u8))
+            }
+        }
+    }
+
+    /// Checks that every node reachable from the root and all of its pointers lie inside the node array.
+    fn validate(&self) -> Result<(), SvdagError> {
+        validate_dimensions(self.depth(), self.dimensions())?;
+
+        if self.depth() == 0 {
+            return Ok(());
+        }
+
+        let pointer_word_count = self.pointer_format().word_count();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(0, 0)];
+
+        while let Some((node_index, current_depth)) = stack.pop() {
+            if !visited.insert((node_index, current_depth)) {
+                continue;
+            }
+
+            let node = self
+                .word(node_index)
+                .ok_or(SvdagError::InvalidNode { index: node_index })?
+                .node();
+
+            //Leaf nodes have no pointers to follow
+            if current_depth + 1 >= self.depth() {
+                continue;
+            }
+
+            for child_number in 0..node.children.count_occupied() {
+                let pointer_index = node_index + 1 + child_number * pointer_word_count;
+
+                let child_index = self
+                    .read_pointer(pointer_index)
+                    .filter(|child_index| *child_index < self.word_count())
+                    .ok_or(SvdagError::InvalidPointer { pointer_index })?;
+
+                stack.push((child_index, current_depth + 1));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Looks up the voxel at `target_position`, returning `None` if it lies outside the volume
+    /// or the path to it runs outside the node array.
+    fn get(&self, target_position: VolumePosition) -> Option<bool> {
+        let depth = self.depth();
+        if depth == 0 || depth as u32 >= usize::BITS {
+            return None;
+        }
+
+        let dimensions = self.dimensions();
+        if target_position.0 >= dimensions.0
+            || target_position.1 >= dimensions.1
+            || target_position.2 >= dimensions.2
+        {
+            return None;
+        }
+
+        let pointer_word_count = self.pointer_format().word_count();
+        let mut node_index = 0;
+        let mut mirror = 0;
+
+        //Each level halves the area, so the child index comes straight from the position's bits
+        for level in (0..depth).rev() {
+            let child_index = mirror_child_index(
+                (target_position.0 >> level & 1) << 2
+                    | (target_position.1 >> level & 1) << 1
+                    | (target_position.2 >> level & 1),
+                mirror,
+            );
+
+            let node = self.word(node_index)?.node();
+
+            //If it's not occupied there won't be a child node so the space is empty
+            if !node.children.get(child_index) {
+                return Some(false);
+            }
+
+            //Leaf nodes store the voxels themselves
+            if level == 0 {
+                return Some(true);
+            }
+
+            let child_pointer_index =
+                node_index + 1 + node.children.get_n(child_index) * pointer_word_count;
+            let (child_node_index, child_mirror) =
+                self.read_mirrored_pointer(child_pointer_index)?;
+            node_index = child_node_index;
+            mirror ^= child_mirror;
+        }
+
+        None
+    }
+
+    /// Casts a ray from `origin` along `direction` up to the ray parameter `max_t`, returning the
+    /// first occupied voxel.
+    fn raycast(&self, origin: [f32; 3], direction: [f32; 3], max_t: f32) -> Option<SvdagHit> {
+        svdag_raycast::raycast(self, origin, direction, max_t)
+    }
+
+    /// Expands the voxels in `[min, max)` into a dense volume whose origin is `min`. The region is
+    /// clipped to the dimensions of the graph and the volume has the dimensions of what's left.
+    fn extract(&self, min: VolumePosition, max: VolumePosition) -> DensityVolume {
+        svdag_extract::extract(self, min, max)
+    }
+
+    /// Which of the six neighbours of the voxel at `position` are occupied, neighbours outside of
+    /// the dimensions count as empty. `None` if `position` itself is outside. The path from the
+    /// root is walked once and shared by all six lookups.
+    fn neighbors(&self, position: VolumePosition) -> Option<FaceMask> {
+        svdag_neighbors::neighbors(self, position)
+    }
+
+    /// Iterates over the occupied voxels that have at least one exposed face, with the mask of
+    /// those faces, in child index order.
+    fn surface_voxels(&self) -> SurfaceVoxels<'_, Self> {
+        SurfaceVoxels::new(self)
+    }
+
+    /// Iterates over the positions of the occupied voxels in Morton order, which is the child
+    /// index order of the octants from the root down.
+    fn iter_occupied(&self) -> OccupiedVoxels<'_, Self> {
+        OccupiedVoxels::new(self, (0, 0, 0), (usize::MAX, usize::MAX, usize::MAX))
+    }
+
+    /// Like `iter_occupied`, restricted to the voxels in `[min, max)`.
+    fn iter_occupied_in(
+        &self,
+        min: VolumePosition,
+        max: VolumePosition,
+    ) -> OccupiedVoxels<'_, Self> {
+        OccupiedVoxels::new(self, min, max)
+    }
+
+    /// Iterates over the occupied octants `level` steps below the root in Morton order, as their
+    /// origin, side size and node index. The root is level 0 and leaves are one level above the
+    /// depth, deeper levels yield nothing. Octants seen through a mirrored pointer yield the
+    /// stored node, which holds them reflected.
+    fn iter_nodes_at_level(&self, level: u8) -> OccupiedNodes<'_, Self> {
+        OccupiedNodes::new(self, level, (0, 0, 0), (usize::MAX, usize::MAX, usize::MAX))
+    }
+
+    /// Like `iter_nodes_at_level`, restricted to the octants overlapping `[min, max)`.
+    fn iter_nodes_at_level_in(
+        &self,
+        level: u8,
+        min: VolumePosition,
+        max: VolumePosition,
+    ) -> OccupiedNodes<'_, Self> {
+        OccupiedNodes::new(self, level, min, max)
+    }
+}
+
+/// A node by its index in the node array and the reflection it's seen with.
+pub(crate) type NodeRef = (usize, u8);
+
+/// Reads the node with its reflection applied, returning the occupied children by real child
+/// index and, above the leaves, the child nodes.
+pub(crate) fn read_node<S>(
+    svdag: &S,
+    (node_index, mirror): NodeRef,
+    height: u8,
+) -> Result<(Children, [Option<NodeRef>; 8]), SvdagError>
+where
+    S: SvdagRead + ?Sized,
+{
+    let stored_children = svdag
+        .word(node_index)
+        .ok_or(SvdagError::InvalidNode { index: node_index })?
+        .node()
+        .children;
+
+    let pointer_word_count = svdag.pointer_format().word_count();
+    let mut children = Children::default();
+    let mut child_nodes = [None; 8];
+
+    for (child_index, child_node) in child_nodes.iter_mut().enumerate() {
+        let stored_index = mirror_child_index(child_index, mirror);
+        if !stored_children.get(stored_index) {
+            continue;
+        }
+
+        children.set(child_index, true);
+
+        if height > 1 {
+            let pointer_index =
+                node_index + 1 + stored_children.get_n(stored_index) * pointer_word_count;
+            let (child_node_index, child_mirror) = svdag
+                .read_mirrored_pointer(pointer_index)
+                .ok_or(SvdagError::InvalidPointer { pointer_index })?;
+
+            *child_node = Some((child_node_index, mirror ^ child_mirror));
+        }
+    }
+
+    Ok((children, child_nodes))
+}
+
+/// Dimensions of the whole cube of side `2^depth`, saturated for depths too large to address.
+pub(crate) fn cube_dimensions(depth: u8) -> VolumeDimensions {
+    let side_size = 1usize.checked_shl(depth as u32).unwrap_or(usize::MAX);
+
+    (side_size, side_size, side_size)
+}
+
+/// Checks that the depth is addressable and the dimensions fit inside its cube.
+pub(crate) fn validate_dimensions(
+    depth: u8,
+    dimensions: VolumeDimensions,
+) -> Result<(), SvdagError> {
+    if depth as u32 >= usize::BITS {
+        return Err(SvdagError::InvalidDepth(depth));
+    }
+
+    let side_size = 1usize << depth;
+    if dimensions.0 > side_size || dimensions.1 > side_size || dimensions.2 > side_size {
+        return Err(SvdagError::InvalidDimensions(dimensions));
+    }
+
+    Ok(())
+}
+
+/// Whether the node at `node_index` is completely filled, remembering the answer per node in
+/// `full_nodes`. Reflecting a node doesn't change whether it's full.
+pub(crate) fn is_full_node<S>(
+    svdag: &S,
+    full_nodes: &mut HashMap<usize, bool>,
+    node_index: usize,
+    height: u8,
+) -> bool
+where
+    S: SvdagRead + ?Sized,
+{
+    if let Some(is_full) = full_nodes.get(&node_index) {
+        return *is_full;
+    }
+
+    let node = match svdag.word(node_index) {
+        Some(word) => word.node(),
+        None => return false,
+    };
+
+    let mut is_full = node.children.count_occupied() == 8;
+    if is_full && height > 1 {
+        let pointer_word_count = svdag.pointer_format().word_count();
+
+        for child_number in 0..8 {
+            let child_pointer_index = node_index + 1 + child_number * pointer_word_count;
+
+            is_full = match svdag.read_pointer(child_pointer_index) {
+                Some(child_node_index) => {
+                    is_full_node(svdag, full_nodes, child_node_index, height - 1)
+                }
+                None => false,
+            };
+
+            if !is_full {
+                break;
+            }
+        }
+    }
+
+    full_nodes.insert(node_index, is_full);
+
+    is_full
+}
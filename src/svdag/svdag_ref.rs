@@ -0,0 +1,102 @@
+use super::{
+    svdag_format::crc32, svdag_read::validate_dimensions, PointerFormat, Svdag, SvdagError,
+    SvdagHeader, SvdagRead, SvdagValue,
+};
+use crate::volume::{IsVolume, VolumeDimensions, VolumePosition};
+
+/// Borrowed view over a serialized svdag, queried in place without copying the node words.
+///
+/// Creating a view only reads the header, so it can come straight from a memory map or
+/// `include_bytes!` without touching the node words. Queries check every word they read and treat
+/// paths running outside the buffer as missing, `verify` checks the whole buffer up front.
+#[derive(Copy, Clone, Debug)]
+pub struct SvdagRef<'a> {
+    header: SvdagHeader,
+    words: &'a [u8],
+}
+
+impl<'a> SvdagRef<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<SvdagRef<'a>, SvdagError> {
+        let header = SvdagHeader::from_bytes(bytes)?;
+        let payload_size = header.payload_size()?;
+
+        let words = bytes
+            .get(header.size()..)
+            .and_then(|payload| payload.get(..payload_size))
+            .ok_or_else(|| SvdagError::Truncated {
+                expected: (header.size() as u64).saturating_add(payload_size as u64),
+                actual: bytes.len() as u64,
+            })?;
+
+        validate_dimensions(header.depth, header.dimensions)?;
+
+        Ok(SvdagRef { header, words })
+    }
+
+    /// Checks the checksum and that every reachable node and pointer lies inside the buffer,
+    /// which reads all of the node words.
+    pub fn verify(&self) -> Result<(), SvdagError> {
+        let checksum = crc32(self.words);
+        if checksum != self.header.checksum {
+            return Err(SvdagError::ChecksumMismatch {
+                expected: self.header.checksum,
+                actual: checksum,
+            });
+        }
+
+        self.validate()
+    }
+
+    pub fn header(&self) -> &SvdagHeader {
+        &self.header
+    }
+
+    pub fn get(&self, target_position: VolumePosition) -> Option<bool> {
+        SvdagRead::get(self, target_position)
+    }
+
+    /// Copies the node words into an owned `Svdag`.
+    pub fn to_svdag(&self) -> Svdag {
+        Svdag {
+            depth: self.header.depth,
+            dimensions: self.header.dimensions,
+            pointer_format: self.header.pointer_format,
+            nodes: (0..self.word_count())
+                .filter_map(|index| self.word(index))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> SvdagRead for SvdagRef<'a> {
+    fn depth(&self) -> u8 {
+        self.header.depth
+    }
+
+    fn pointer_format(&self) -> PointerFormat {
+        self.header.pointer_format
+    }
+
+    fn word_count(&self) -> usize {
+        self.words.len() / 2
+    }
+
+    fn word(&self, index: usize) -> Option<SvdagValue> {
+        let start = index.checked_mul(2)?;
+        let word = self.words.get(start..start.checked_add(2)?)?;
+
+        Some(SvdagValue {
+            bits: u16::from_le_bytes([word[0], word[1]]),
+        })
+    }
+
+    fn dimensions(&self) -> VolumeDimensions {
+        self.header.dimensions
+    }
+}
+
+impl<'a> IsVolume for SvdagRef<'a> {
+    fn get_dimensions(&self) -> VolumeDimensions {
+        self.header.dimensions
+    }
+}
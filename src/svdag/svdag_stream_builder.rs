@@ -0,0 +1,304 @@
+use super::{
+    svdag_node_table::{child_origin, NodeId, NodeTable, TableNode},
+    svdag_read::{cube_dimensions, validate_dimensions},
+    BoxBrush, Brush, PointerFormat, Svdag, SvdagError,
+};
+use crate::volume::{CubicVolume, DensityVolume, VolumeDimensions, VolumePosition};
+
+/// How much of an octant is occupied, as reported by the classifier of
+/// `SvdagStreamBuilder::build_from_octants`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OctantCoverage {
+    Empty,
+    Full,
+    /// Partially occupied or unknown, the octant is subdivided further.
+    Partial,
+}
+
+/// Builds an `Svdag` octant by octant without ever materializing the full volume.
+///
+/// Subtrees are deduplicated in a `NodeTable` as soon as they are complete, so memory grows with
+/// the number of unique nodes in the output rather than with the side of the volume.
+pub struct SvdagStreamBuilder {
+    depth: u8,
+    dimensions: VolumeDimensions,
+    pointer_format: Option<PointerFormat>,
+    table: NodeTable,
+}
+
+impl SvdagStreamBuilder {
+    pub fn new(depth: u8) -> SvdagStreamBuilder {
+        SvdagStreamBuilder::with_table(depth, NodeTable::new())
+    }
+
+    /// Builds into `table`, sharing nodes with everything already stored in it.
+    pub(crate) fn with_table(depth: u8, table: NodeTable) -> SvdagStreamBuilder {
+        SvdagStreamBuilder {
+            depth,
+            dimensions: cube_dimensions(depth),
+            pointer_format: None,
+            table,
+        }
+    }
+
+    pub(crate) fn into_table(self) -> NodeTable {
+        self.table
+    }
+
+    /// Forces a pointer format, by default the narrowest format that fits the graph is used.
+    pub fn pointer_format(&mut self, pointer_format: PointerFormat) -> &mut Self {
+        self.pointer_format = Some(pointer_format);
+        self
+    }
+
+    /// Limits the graph to a logical extent inside its cube, by default the whole cube. Voxels
+    /// past it are never sampled and stay empty.
+    pub fn dimensions(&mut self, dimensions: VolumeDimensions) -> &mut Self {
+        self.dimensions = dimensions;
+        self
+    }
+
+    /// Builds the graph by sampling every voxel through `sample`.
+    pub fn build_from_fn(
+        &mut self,
+        sample: impl FnMut(VolumePosition) -> bool,
+    ) -> Result<Svdag, SvdagError> {
+        self.build_from_octants(|_, _| OctantCoverage::Partial, sample)
+    }
+
+    /// Builds the graph top-down, asking `classify` about every octant by its origin and side
+    /// length first. Empty and full octants are emitted without sampling, only the voxels of
+    /// partial leaf octants are passed to `sample`.
+    pub fn build_from_octants(
+        &mut self,
+        classify: impl FnMut(VolumePosition, usize) -> OctantCoverage,
+        sample: impl FnMut(VolumePosition) -> bool,
+    ) -> Result<Svdag, SvdagError> {
+        let root = self.build_root_from_octants(classify, sample)?;
+
+        self.finish(root)
+    }
+
+    /// Like `build_from_octants`, but leaves the nodes in the table and returns the root's id.
+    pub(crate) fn build_root_from_octants(
+        &mut self,
+        mut classify: impl FnMut(VolumePosition, usize) -> OctantCoverage,
+        mut sample: impl FnMut(VolumePosition) -> bool,
+    ) -> Result<NodeId, SvdagError> {
+        self.check_depth()?;
+
+        //Octants reaching past the dimensions are subdivided until they lie inside or outside
+        let bounds = BoxBrush::new((0, 0, 0), self.dimensions);
+        let mut classify = |origin: VolumePosition, side: usize| match bounds.classify(origin, side)
+        {
+            OctantCoverage::Empty => OctantCoverage::Empty,
+            OctantCoverage::Full => classify(origin, side),
+            OctantCoverage::Partial => match classify(origin, side) {
+                OctantCoverage::Empty => OctantCoverage::Empty,
+                _ => OctantCoverage::Partial,
+            },
+        };
+        let mut sample = |position: VolumePosition| bounds.contains(position) && sample(position);
+
+        let root = match classify((0, 0, 0), 1 << self.depth) {
+            OctantCoverage::Empty => None,
+            OctantCoverage::Full => Some(self.table.insert_solid(self.depth)?),
+            OctantCoverage::Partial => {
+                self.build_octant(self.depth, (0, 0, 0), &mut classify, &mut sample)?
+            }
+        };
+
+        self.root_or_empty(root)
+    }
+
+    /// Builds the graph from a signed distance function evaluated at voxel positions, voxels
+    /// with a negative distance are occupied.
+    ///
+    /// The distance must never overestimate the distance to the surface (a 1-Lipschitz bound),
+    /// which lets whole octants far enough inside or outside the surface skip evaluation.
+    pub fn build_from_sdf(&mut self, sdf: impl Fn([f32; 3]) -> f32) -> Result<Svdag, SvdagError> {
+        let classify = |origin: VolumePosition, side: usize| {
+            let half_extent = (side - 1) as f32 / 2.0;
+            let center = [
+                origin.0 as f32 + half_extent,
+                origin.1 as f32 + half_extent,
+                origin.2 as f32 + half_extent,
+            ];
+
+            //Farthest any voxel position in the octant is from its center, padded for rounding
+            let bound = half_extent * 3f32.sqrt() * 1.001 + 1e-3;
+            let distance = sdf(center);
+
+            if distance >= bound {
+                OctantCoverage::Empty
+            } else if distance < -bound {
+                OctantCoverage::Full
+            } else {
+                OctantCoverage::Partial
+            }
+        };
+
+        self.build_from_octants(classify, |position| {
+            sdf([position.0 as f32, position.1 as f32, position.2 as f32]) < 0.0
+        })
+    }
+
+    /// Builds the graph from cubic bricks of side `2^brick_depth`, filled one at a time by
+    /// `fill_brick` with the brick's origin in the volume. The brick is cleared before every call
+    /// and `fill_brick` returns whether it wrote any occupied voxels, so empty bricks are skipped.
+    pub fn build_from_bricks(
+        &mut self,
+        brick_depth: u8,
+        mut fill_brick: impl FnMut(VolumePosition, &mut DensityVolume) -> bool,
+    ) -> Result<Svdag, SvdagError> {
+        self.check_depth()?;
+
+        let brick_depth = brick_depth.clamp(1, self.depth);
+        let mut brick = DensityVolume::new(brick_depth);
+        let mut is_brick_dirty = false;
+
+        let root = self.build_bricks(
+            self.depth,
+            (0, 0, 0),
+            (&mut brick, &mut is_brick_dirty),
+            &mut fill_brick,
+        )?;
+
+        let root = self.root_or_empty(root)?;
+        self.finish(root)
+    }
+
+    fn check_depth(&self) -> Result<(), SvdagError> {
+        if self.depth == 0 {
+            return Err(SvdagError::InvalidDepth(self.depth));
+        }
+
+        validate_dimensions(self.depth, self.dimensions)
+    }
+
+    fn root_or_empty(&mut self, root: Option<NodeId>) -> Result<NodeId, SvdagError> {
+        //A completely empty volume still needs a root node to be a valid graph
+        match root {
+            Some(root) => Ok(root),
+            None => self.table.insert(TableNode::empty(self.depth)),
+        }
+    }
+
+    fn finish(&mut self, root: NodeId) -> Result<Svdag, SvdagError> {
+        let svdag = self.table.to_svdag(root, self.pointer_format);
+        self.table = NodeTable::new();
+
+        svdag.map(|svdag| Svdag {
+            dimensions: self.dimensions,
+            ..svdag
+        })
+    }
+
+    fn build_bricks(
+        &mut self,
+        height: u8,
+        origin: VolumePosition,
+        (brick, is_brick_dirty): (&mut DensityVolume, &mut bool),
+        fill_brick: &mut impl FnMut(VolumePosition, &mut DensityVolume) -> bool,
+    ) -> Result<Option<NodeId>, SvdagError> {
+        let bounds = BoxBrush::new((0, 0, 0), self.dimensions);
+        if bounds.classify(origin, 1 << height) == OctantCoverage::Empty {
+            return Ok(None);
+        }
+
+        if height == brick.depth {
+            //Only bricks that received voxels need clearing, empty ones are left untouched
+            if *is_brick_dirty {
+                *brick = CubicVolume::new(brick.depth);
+            }
+
+            *is_brick_dirty = fill_brick(origin, brick);
+            if !*is_brick_dirty {
+                return Ok(None);
+            }
+
+            let brick: &DensityVolume = brick;
+            return self.build_octant(
+                height,
+                (0, 0, 0),
+                &mut |_, _| OctantCoverage::Partial,
+                &mut |position: VolumePosition| {
+                    let volume_position = (
+                        origin.0 + position.0,
+                        origin.1 + position.1,
+                        origin.2 + position.2,
+                    );
+                    bounds.contains(volume_position) && *brick.get(position)
+                },
+            );
+        }
+
+        let half_size = 1 << (height - 1);
+        let mut node = TableNode::empty(height);
+
+        for child_index in 0..8 {
+            let child_origin = child_origin(origin, child_index, half_size);
+
+            let child_id = self.build_bricks(
+                height - 1,
+                child_origin,
+                (brick, is_brick_dirty),
+                fill_brick,
+            )?;
+
+            if let Some(child_id) = child_id {
+                node.children.set(child_index, true);
+                node.child_ids[child_index] = child_id;
+            }
+        }
+
+        if node.children.have_occupied_children() {
+            Ok(Some(self.table.insert(node)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Builds the subtree of side `2^height` at `origin`, returning `None` if it's empty.
+    fn build_octant(
+        &mut self,
+        height: u8,
+        origin: VolumePosition,
+        classify: &mut impl FnMut(VolumePosition, usize) -> OctantCoverage,
+        sample: &mut impl FnMut(VolumePosition) -> bool,
+    ) -> Result<Option<NodeId>, SvdagError> {
+        let mut node = TableNode::empty(height);
+
+        if height == 1 {
+            for child_index in 0..8 {
+                node.children
+                    .set(child_index, sample(child_origin(origin, child_index, 1)));
+            }
+        } else {
+            let half_size = 1 << (height - 1);
+
+            for child_index in 0..8 {
+                let child_origin = child_origin(origin, child_index, half_size);
+
+                let child_id = match classify(child_origin, half_size) {
+                    OctantCoverage::Empty => None,
+                    OctantCoverage::Full => Some(self.table.insert_solid(height - 1)?),
+                    OctantCoverage::Partial => {
+                        self.build_octant(height - 1, child_origin, classify, sample)?
+                    }
+                };
+
+                if let Some(child_id) = child_id {
+                    node.children.set(child_index, true);
+                    node.child_ids[child_index] = child_id;
+                }
+            }
+        }
+
+        if node.children.have_occupied_children() {
+            Ok(Some(self.table.insert(node)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Reader for MagicaVoxel `.vox` files.
+//!
+//! A file starts with the magic bytes `VOX ` and a `u32` version, followed by a `MAIN` chunk
+//! whose children hold the models and the palette. Every chunk is a 4 byte id, the `u32` size
+//! of its content, the `u32` size of its children, the content and the children. Each model is
+//! a `SIZE` chunk followed by an `XYZI` chunk listing its voxels, the optional `RGBA` chunk
+//! replaces the default palette. Scene graph, material and other editor chunks are skipped.
+
+use super::{MaterialSvdag, MaterialSvdagBuilder, Svdag, SvdagError};
+use crate::volume::{CubicVolume, DensityVolume, IsVolume, VolumeDimensions};
+use std::io::Read;
+
+const MAGIC: [u8; 4] = *b"VOX ";
+/// Versions written by MagicaVoxel whose model and palette chunks are understood.
+const SUPPORTED_VERSIONS: [u32; 2] = [150, 200];
+/// Largest model side MagicaVoxel writes, models are read densely so this bounds the allocation.
+const MAX_MODEL_SIDE: usize = 256;
+
+/// Colors by palette index as RGBA, index 0 is empty space.
+pub type VoxPalette = [[u8; 4]; 256];
+
+/// A single model of a `.vox` file, in the file's coordinates where z points up.
+pub struct VoxModel {
+    /// Palette index per voxel, 0 for empty voxels.
+    pub volume: CubicVolume<u8>,
+}
+
+impl VoxModel {
+    /// Graph of the model's occupied voxels.
+    pub fn to_svdag(&self) -> Svdag {
+        let mut density_volume = DensityVolume::with_dimensions(self.volume.get_dimensions());
+        for index in 0..self.volume.get_element_count() {
+            *density_volume.get_mut(index) = *self.volume.get(index) != 0;
+        }
+
+        Svdag::from(&density_volume)
+    }
+
+    /// Graph of the model with the palette index of every voxel as its material.
+    pub fn to_material_svdag(&self) -> Result<MaterialSvdag, SvdagError> {
+        MaterialSvdagBuilder::new().build(&self.volume)
+    }
+}
+
+/// The models and palette of a MagicaVoxel file.
+pub struct VoxFile {
+    pub models: Vec<VoxModel>,
+    pub palette: VoxPalette,
+}
+
+impl VoxFile {
+    pub fn read_from(mut reader: impl Read) -> Result<VoxFile, SvdagError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        VoxFile::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<VoxFile, SvdagError> {
+        if bytes.len() < 8 {
+            return Err(SvdagError::Truncated {
+                expected: 8,
+                actual: bytes.len() as u64,
+            });
+        }
+
+        if bytes[0..4] != MAGIC {
+            return Err(SvdagError::InvalidVox(format!(
+                "expected the magic bytes `VOX `, found {:?}",
+                &bytes[0..4]
+            )));
+        }
+
+        let version = read_u32(bytes, 4);
+        if !SUPPORTED_VERSIONS.contains(&version) {
+            return Err(SvdagError::UnsupportedVoxVersion(version));
+        }
+
+        let main = read_chunk(bytes, 8)?;
+        if &main.id != b"MAIN" {
+            return Err(SvdagError::InvalidVox(format!(
+                "expected a MAIN chunk, found {}",
+                chunk_name(main.id)
+            )));
+        }
+
+        let mut vox_file = VoxFile {
+            models: Vec::new(),
+            palette: default_palette(),
+        };
+        let mut model_dimensions = None;
+        let mut offset = 0;
+
+        while offset < main.children.len() {
+            let chunk = read_chunk(main.children, offset)?;
+            offset = chunk.end;
+
+            match &chunk.id {
+                b"SIZE" => model_dimensions = Some(read_dimensions(chunk.content)?),
+                b"XYZI" => {
+                    let dimensions = model_dimensions.take().ok_or_else(|| {
+                        SvdagError::InvalidVox("XYZI chunk without a SIZE chunk".to_string())
+                    })?;
+
+                    vox_file
+                        .models
+                        .push(read_voxels(chunk.content, dimensions)?);
+                }
+                b"RGBA" => vox_file.palette = read_palette(chunk.content)?,
+                //Scene graph, materials and the rest of the editor state don't affect the voxels
+                _ => {}
+            }
+        }
+
+        Ok(vox_file)
+    }
+}
+
+struct Chunk<'a> {
+    id: [u8; 4],
+    content: &'a [u8],
+    children: &'a [u8],
+    /// Offset right past the chunk in its parent.
+    end: usize,
+}
+
+fn read_chunk(bytes: &[u8], offset: usize) -> Result<Chunk<'_>, SvdagError> {
+    let header = bytes
+        .get(offset..offset + 12)
+        .ok_or_else(|| SvdagError::InvalidVox("chunk header is truncated".to_string()))?;
+
+    let mut id = [0; 4];
+    id.copy_from_slice(&header[0..4]);
+    let content_size = read_u32(header, 4) as usize;
+    let children_size = read_u32(header, 8) as usize;
+
+    let content_start = offset + 12;
+    let children_start = content_start.saturating_add(content_size);
+    let end = children_start.saturating_add(children_size);
+
+    if end > bytes.len() {
+        return Err(SvdagError::InvalidVox(format!(
+            "{} chunk runs past the end of its parent",
+            chunk_name(id)
+        )));
+    }
+
+    Ok(Chunk {
+        id,
+        content: &bytes[content_start..children_start],
+        children: &bytes[children_start..end],
+        end,
+    })
+}
+
+fn read_dimensions(content: &[u8]) -> Result<VolumeDimensions, SvdagError> {
+    if content.len() < 12 {
+        return Err(SvdagError::InvalidVox(
+            "SIZE chunk is truncated".to_string(),
+        ));
+    }
+
+    let dimensions = (
+        read_u32(content, 0) as usize,
+        read_u32(content, 4) as usize,
+        read_u32(content, 8) as usize,
+    );
+
+    let sides = [dimensions.0, dimensions.1, dimensions.2];
+    if sides
+        .iter()
+        .any(|side| *side == 0 || *side > MAX_MODEL_SIDE)
+    {
+        return Err(SvdagError::InvalidVox(format!(
+            "model size {:?} is empty or larger than {} voxels",
+            dimensions, MAX_MODEL_SIDE
+        )));
+    }
+
+    Ok(dimensions)
+}
+
+fn read_voxels(content: &[u8], dimensions: VolumeDimensions) -> Result<VoxModel, SvdagError> {
+    let truncated = || SvdagError::InvalidVox("XYZI chunk is truncated".to_string());
+
+    if content.len() < 4 {
+        return Err(truncated());
+    }
+    let voxel_count = read_u32(content, 0) as usize;
+    let voxels = voxel_count
+        .checked_mul(4)
+        .and_then(|size| size.checked_add(4))
+        .and_then(|end| content.get(4..end))
+        .ok_or_else(truncated)?;
+
+    let mut volume = CubicVolume::with_dimensions(dimensions);
+
+    for voxel in voxels.chunks_exact(4) {
+        let position = (voxel[0] as usize, voxel[1] as usize, voxel[2] as usize);
+
+        if position.0 >= dimensions.0 || position.1 >= dimensions.1 || position.2 >= dimensions.2 {
+            return Err(SvdagError::InvalidVox(format!(
+                "voxel {:?} lies outside the model size {:?}",
+                position, dimensions
+            )));
+        }
+
+        *volume.get_mut(position) = voxel[3];
+    }
+
+    Ok(VoxModel { volume })
+}
+
+/// Entry `i` of the chunk is the color of palette index `i + 1`, the last entry is unused.
+fn read_palette(content: &[u8]) -> Result<VoxPalette, SvdagError> {
+    if content.len() < 256 * 4 {
+        return Err(SvdagError::InvalidVox(
+            "RGBA chunk is truncated".to_string(),
+        ));
+    }
+
+    let mut palette = [[0; 4]; 256];
+    for (index, color) in content.chunks_exact(4).take(255).enumerate() {
+        palette[index + 1].copy_from_slice(color);
+    }
+
+    Ok(palette)
+}
+
+/// The palette MagicaVoxel uses for files without an `RGBA` chunk.
+fn default_palette() -> VoxPalette {
+    let mut palette = [[0; 4]; 256];
+    let mut index = 1;
+
+    //A 6x6x6 color cube from white down to, but without, black
+    let levels = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    for red in levels.iter() {
+        for green in levels.iter() {
+            for blue in levels.iter() {
+                if index < 216 {
+                    palette[index] = [*red, *green, *blue, 0xff];
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    //Followed by ramps of red, green, blue and gray in the levels the cube skips
+    let ramp = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    for channels in [[1, 0, 0], [0, 1, 0], [0, 0, 1], [1, 1, 1]].iter() {
+        for level in ramp.iter() {
+            palette[index] = [
+                level * channels[0],
+                level * channels[1],
+                level * channels[2],
+                0xff,
+            ];
+            index += 1;
+        }
+    }
+
+    palette
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+fn chunk_name(id: [u8; 4]) -> String {
+    String::from_utf8_lossy(&id).into_owned()
+}
@@ -0,0 +1,355 @@
+use super::{
+    svdag_node_table::child_origin, NodeId, NodeTable, PointerFormat, Svdag, SvdagError, TableNode,
+    TriangleMesh,
+};
+use crate::volume::{DensityVolume, VolumePosition};
+
+type Triangle = [[f32; 3]; 3];
+
+/// Turns triangle meshes into graphs.
+///
+/// The surface is voxelized conservatively, every voxel a triangle touches is occupied. The
+/// octree is built top-down with each octant only testing the triangles that overlap its parent,
+/// and octants no triangle reaches become empty or, with solid filling, full nodes right away.
+/// Nodes go straight into a `NodeTable`, so no dense volume is needed at any depth.
+pub struct MeshVoxelizer {
+    depth: u8,
+    solid: bool,
+    placement: Option<([f32; 3], f32)>,
+    pointer_format: Option<PointerFormat>,
+}
+
+impl MeshVoxelizer {
+    pub fn new(depth: u8) -> MeshVoxelizer {
+        MeshVoxelizer {
+            depth,
+            solid: false,
+            placement: None,
+            pointer_format: None,
+        }
+    }
+
+    /// Also fills the interior of the mesh, which has to be watertight for the inside to be
+    /// well defined.
+    pub fn solid(&mut self, solid: bool) -> &mut Self {
+        self.solid = solid;
+        self
+    }
+
+    /// Maps a mesh position `p` to the voxel coordinates `(p - origin) / voxel_size`. By default
+    /// the mesh's bounding box is scaled uniformly to fit the volume.
+    pub fn placement(&mut self, origin: [f32; 3], voxel_size: f32) -> &mut Self {
+        self.placement = Some((origin, voxel_size));
+        self
+    }
+
+    /// Forces a pointer format, by default the narrowest format that fits the graph is used.
+    pub fn pointer_format(&mut self, pointer_format: PointerFormat) -> &mut Self {
+        self.pointer_format = Some(pointer_format);
+        self
+    }
+
+    pub fn build(&self, mesh: &TriangleMesh) -> Result<Svdag, SvdagError> {
+        if self.depth == 0 || self.depth as u32 >= usize::BITS {
+            return Err(SvdagError::InvalidDepth(self.depth));
+        }
+
+        let side_size = (1usize << self.depth) as f32;
+        let triangles = self.to_voxel_space(mesh, side_size);
+        let inside_test = if self.solid {
+            Some(InsideTest::new(&triangles, side_size))
+        } else {
+            None
+        };
+
+        let mut voxelizer = Voxelizer {
+            triangles: &triangles,
+            inside_test,
+            table: NodeTable::new(),
+        };
+
+        let candidates: Vec<usize> = (0..triangles.len()).collect();
+        let root = match voxelizer.voxelize_octant(self.depth, (0, 0, 0), &candidates)? {
+            Some(root) => root,
+            //A completely empty volume still needs a root node to be a valid graph
+            None => voxelizer.table.insert(TableNode::empty(self.depth))?,
+        };
+
+        voxelizer.table.to_svdag(root, self.pointer_format)
+    }
+
+    /// Voxelizes into a dense volume, only sensible for small depths.
+    pub fn voxelize(&self, mesh: &TriangleMesh) -> Result<DensityVolume, SvdagError> {
+        Ok(DensityVolume::from(&self.build(mesh)?))
+    }
+
+    fn to_voxel_space(&self, mesh: &TriangleMesh, side_size: f32) -> Vec<Triangle> {
+        let (origin, voxel_size) = match (self.placement, mesh.bounds()) {
+            (Some(placement), _) => placement,
+            (None, Some((min, max))) => {
+                let extent = (0..3).map(|axis| max[axis] - min[axis]).fold(0.0, f32::max);
+                let voxel_size = if extent > 0.0 {
+                    extent / side_size
+                } else {
+                    1.0
+                };
+
+                (min, voxel_size)
+            }
+            (None, None) => return Vec::new(),
+        };
+
+        (0..mesh.triangles.len())
+            .map(|index| {
+                let mut triangle = mesh.triangle(index);
+                for corner in triangle.iter_mut() {
+                    for axis in 0..3 {
+                        corner[axis] = (corner[axis] - origin[axis]) / voxel_size;
+                    }
+                }
+                triangle
+            })
+            .collect()
+    }
+}
+
+struct Voxelizer<'a> {
+    triangles: &'a [Triangle],
+    inside_test: Option<InsideTest>,
+    table: NodeTable,
+}
+
+impl<'a> Voxelizer<'a> {
+    /// Voxelizes the octant at `origin` from the `candidates` that may overlap it, `None` if
+    /// it's empty.
+    fn voxelize_octant(
+        &mut self,
+        height: u8,
+        origin: VolumePosition,
+        candidates: &[usize],
+    ) -> Result<Option<NodeId>, SvdagError> {
+        let side_size = 1usize << height;
+        let triangles = self.triangles;
+        let overlapping: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|index| triangle_overlaps_cell(&triangles[*index], origin, side_size))
+            .collect();
+
+        //Without a surface crossing it the octant is entirely inside or outside
+        if overlapping.is_empty() {
+            return if self.is_inside(origin, side_size) {
+                Ok(Some(self.table.insert_solid(height)?))
+            } else {
+                Ok(None)
+            };
+        }
+
+        let mut node = TableNode::empty(height);
+
+        for child_index in 0..8 {
+            let child_origin = child_origin(origin, child_index, side_size / 2);
+
+            if height == 1 {
+                let is_occupied = overlapping
+                    .iter()
+                    .any(|index| triangle_overlaps_cell(&triangles[*index], child_origin, 1))
+                    || self.is_inside(child_origin, 1);
+
+                node.children.set(child_index, is_occupied);
+            } else if let Some(child_id) =
+                self.voxelize_octant(height - 1, child_origin, &overlapping)?
+            {
+                node.children.set(child_index, true);
+                node.child_ids[child_index] = child_id;
+            }
+        }
+
+        if node.children.have_occupied_children() {
+            Ok(Some(self.table.insert(node)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn is_inside(&self, origin: VolumePosition, side_size: usize) -> bool {
+        let half_size = side_size as f32 / 2.0;
+        let center = [
+            origin.0 as f32 + half_size,
+            origin.1 as f32 + half_size,
+            origin.2 as f32 + half_size,
+        ];
+
+        match &self.inside_test {
+            Some(inside_test) => inside_test.is_inside(self.triangles, center),
+            None => false,
+        }
+    }
+}
+
+/// Separating axis test of a triangle against the cell `[origin, origin + side_size)`,
+/// counting touching as overlapping.
+fn triangle_overlaps_cell(triangle: &Triangle, origin: VolumePosition, side_size: usize) -> bool {
+    let half_size = side_size as f32 / 2.0;
+    let center = [
+        origin.0 as f32 + half_size,
+        origin.1 as f32 + half_size,
+        origin.2 as f32 + half_size,
+    ];
+
+    //Move the cell to the origin
+    let mut corners = *triangle;
+    for corner in corners.iter_mut() {
+        for axis in 0..3 {
+            corner[axis] -= center[axis];
+        }
+    }
+
+    //The cell's face normals, which is comparing bounding boxes
+    for axis in 0..3 {
+        let (min, max) = corners
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), corner| {
+                (min.min(corner[axis]), max.max(corner[axis]))
+            });
+
+        if min > half_size || max < -half_size {
+            return false;
+        }
+    }
+
+    let edges = [
+        subtract(corners[1], corners[0]),
+        subtract(corners[2], corners[1]),
+        subtract(corners[0], corners[2]),
+    ];
+
+    //The triangle's normal
+    if separates(cross(edges[0], edges[1]), &corners, half_size) {
+        return false;
+    }
+
+    //The cross products of the cell's and the triangle's edges
+    for edge in edges.iter() {
+        for axis in 0..3 {
+            let mut cell_edge = [0.0; 3];
+            cell_edge[axis] = 1.0;
+
+            if separates(cross(cell_edge, *edge), &corners, half_size) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Whether the projections onto `axis` of the triangle and the cell centered at the origin are disjoint.
+fn separates(axis: [f32; 3], corners: &Triangle, half_size: f32) -> bool {
+    let radius = half_size * (axis[0].abs() + axis[1].abs() + axis[2].abs());
+    let projections = [
+        dot(axis, corners[0]),
+        dot(axis, corners[1]),
+        dot(axis, corners[2]),
+    ];
+
+    let min = projections[0].min(projections[1]).min(projections[2]);
+    let max = projections[0].max(projections[1]).max(projections[2]);
+
+    min > radius || max < -radius
+}
+
+/// Point in mesh test by the parity of crossings along a ray towards +x. Triangles are
+/// bucketed by their extent in the yz plane, so a ray only visits the triangles of its bucket.
+struct InsideTest {
+    resolution: usize,
+    cell_size: f32,
+    cells: Vec<Vec<usize>>,
+}
+
+impl InsideTest {
+    fn new(triangles: &[Triangle], side_size: f32) -> InsideTest {
+        let resolution = (side_size as usize).clamp(1, 256);
+        let cell_size = side_size / resolution as f32;
+        let mut inside_test = InsideTest {
+            resolution,
+            cell_size,
+            cells: vec![Vec::new(); resolution * resolution],
+        };
+
+        for (index, triangle) in triangles.iter().enumerate() {
+            let min_y = triangle[0][1].min(triangle[1][1]).min(triangle[2][1]);
+            let max_y = triangle[0][1].max(triangle[1][1]).max(triangle[2][1]);
+            let min_z = triangle[0][2].min(triangle[1][2]).min(triangle[2][2]);
+            let max_z = triangle[0][2].max(triangle[1][2]).max(triangle[2][2]);
+
+            for y in inside_test.cell(min_y)..=inside_test.cell(max_y) {
+                for z in inside_test.cell(min_z)..=inside_test.cell(max_z) {
+                    inside_test.cells[y * resolution + z].push(index);
+                }
+            }
+        }
+
+        inside_test
+    }
+
+    fn cell(&self, coordinate: f32) -> usize {
+        ((coordinate / self.cell_size).floor().max(0.0) as usize).min(self.resolution - 1)
+    }
+
+    fn is_inside(&self, triangles: &[Triangle], point: [f32; 3]) -> bool {
+        //Nudge the ray off the voxel grid so it doesn't run exactly along edges of axis aligned meshes
+        let y = point[1] + 0.000_318_3;
+        let z = point[2] + 0.000_271_8;
+
+        let cell = &self.cells[self.cell(y) * self.resolution + self.cell(z)];
+        let crossings = cell
+            .iter()
+            .filter(|index| {
+                let triangle = &triangles[**index];
+
+                //Barycentric weights of the point in the triangle projected onto the yz plane
+                let edge_weight = |a: [f32; 3], b: [f32; 3]| {
+                    (b[1] - a[1]) * (z - a[2]) - (b[2] - a[2]) * (y - a[1])
+                };
+                let weights = [
+                    edge_weight(triangle[1], triangle[2]),
+                    edge_weight(triangle[2], triangle[0]),
+                    edge_weight(triangle[0], triangle[1]),
+                ];
+                let area = weights[0] + weights[1] + weights[2];
+
+                let is_covered = (weights.iter().all(|weight| *weight > 0.0)
+                    || weights.iter().all(|weight| *weight < 0.0))
+                    && area != 0.0;
+                if !is_covered {
+                    return false;
+                }
+
+                let x = (weights[0] * triangle[0][0]
+                    + weights[1] * triangle[1][0]
+                    + weights[2] * triangle[2][0])
+                    / area;
+                x > point[0]
+            })
+            .count();
+
+        crossings % 2 == 1
+    }
+}
+
+fn subtract(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
@@ -0,0 +1,331 @@
+use super::{OctantCoverage, SvdagError, SvdagHandle, SvdagPool, SvdagRead};
+use crate::volume::VolumePosition;
+use std::{collections::HashMap, convert::TryFrom};
+
+/// Signed position of a chunk, chunk `(x, y, z)` covers the voxels from `x * side` up to but not
+/// including `(x + 1) * side` on the x axis and likewise on the others.
+pub type ChunkPosition = (i32, i32, i32);
+
+/// Pools smaller than this many nodes are never collected automatically, see
+/// `SvdagWorld::collect_garbage`.
+const MIN_COLLECTED_NODE_COUNT: usize = 1024;
+
+/// Signed position of a voxel in the world.
+pub type WorldPosition = (i64, i64, i64);
+
+/// First occupied voxel along a ray cast through an `SvdagWorld`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WorldHit {
+    pub position: WorldPosition,
+    pub chunk: ChunkPosition,
+    /// Ray parameter at which the voxel is entered, a world distance if the direction is normalized.
+    pub distance: f32,
+    /// Normal of the voxel face the ray entered through, all zero if the ray starts inside the voxel.
+    pub normal: [i32; 3],
+}
+
+/// An unbounded world made of equally sized cubic chunks, each a graph in one shared `SvdagPool`.
+///
+/// Only chunks with occupied voxels are kept, everything else is empty space. Chunks with equal
+/// contents share their nodes, so repeated or untouched terrain costs nothing extra. Nodes that
+/// edits replace are collected once they outnumber the ones still in use.
+pub struct SvdagWorld {
+    chunk_depth: u8,
+    pool: SvdagPool,
+    chunks: HashMap<ChunkPosition, SvdagHandle>,
+    /// Smallest and largest chunk coordinates on every axis, `None` while there are no chunks.
+    bounds: Option<([i32; 3], [i32; 3])>,
+    /// Number of nodes in the pool right after the last collection.
+    live_node_count: usize,
+}
+
+impl SvdagWorld {
+    /// Creates an empty world of chunks with side `2^chunk_depth`.
+    pub fn new(chunk_depth: u8) -> Result<SvdagWorld, SvdagError> {
+        //Keep chunk sides well within what world positions and f32 ray parameters can describe
+        if chunk_depth == 0 || chunk_depth > 24 {
+            return Err(SvdagError::InvalidDepth(chunk_depth));
+        }
+
+        Ok(SvdagWorld {
+            chunk_depth,
+            pool: SvdagPool::new(),
+            chunks: HashMap::new(),
+            bounds: None,
+            live_node_count: 0,
+        })
+    }
+
+    pub fn chunk_depth(&self) -> u8 {
+        self.chunk_depth
+    }
+
+    pub fn chunk_side(&self) -> i64 {
+        1 << self.chunk_depth
+    }
+
+    pub fn pool(&self) -> &SvdagPool {
+        &self.pool
+    }
+
+    /// Splits a world position into the chunk holding it and the position inside that chunk.
+    pub fn split_position(
+        &self,
+        position: WorldPosition,
+    ) -> Result<(ChunkPosition, VolumePosition), SvdagError> {
+        let side = self.chunk_side();
+        let out_of_world = || SvdagError::OutOfWorld { position };
+
+        let chunk = (
+            i32::try_from(position.0.div_euclid(side)).map_err(|_| out_of_world())?,
+            i32::try_from(position.1.div_euclid(side)).map_err(|_| out_of_world())?,
+            i32::try_from(position.2.div_euclid(side)).map_err(|_| out_of_world())?,
+        );
+        let local_position = (
+            position.0.rem_euclid(side) as usize,
+            position.1.rem_euclid(side) as usize,
+            position.2.rem_euclid(side) as usize,
+        );
+
+        Ok((chunk, local_position))
+    }
+
+    /// World position of the voxel at `local_position` in `chunk`.
+    pub fn world_position(
+        &self,
+        chunk: ChunkPosition,
+        local_position: VolumePosition,
+    ) -> WorldPosition {
+        let side = self.chunk_side();
+
+        (
+            chunk.0 as i64 * side + local_position.0 as i64,
+            chunk.1 as i64 * side + local_position.1 as i64,
+            chunk.2 as i64 * side + local_position.2 as i64,
+        )
+    }
+
+    /// Handle of the chunk's graph in `pool`. Edits may collect garbage, which renumbers the
+    /// nodes, so a handle is only valid until the world is edited again.
+    pub fn chunk(&self, chunk: ChunkPosition) -> Option<SvdagHandle> {
+        self.chunks.get(&chunk).copied()
+    }
+
+    /// Positions of all chunks with occupied voxels, in no particular order.
+    pub fn chunk_positions(&self) -> impl Iterator<Item = ChunkPosition> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Replaces the contents of `chunk` with a graph of depth `chunk_depth`.
+    pub fn insert_chunk<S>(&mut self, chunk: ChunkPosition, svdag: &S) -> Result<(), SvdagError>
+    where
+        S: SvdagRead + ?Sized,
+    {
+        if svdag.depth() != self.chunk_depth {
+            return Err(SvdagError::InvalidDepth(svdag.depth()));
+        }
+
+        let handle = self.pool.insert_svdag(svdag)?;
+        self.store_chunk(chunk, handle);
+
+        Ok(())
+    }
+
+    /// Empties `chunk`, returning whether it had any occupied voxels.
+    pub fn remove_chunk(&mut self, chunk: ChunkPosition) -> bool {
+        if self.chunks.remove(&chunk).is_some() {
+            self.shrink_bounds(chunk);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Drops every node of the pool that no chunk uses anymore, returning how many were freed.
+    /// Edits do this on their own once the replaced nodes outnumber the ones in use.
+    pub fn collect_garbage(&mut self) -> usize {
+        let chunks: Vec<ChunkPosition> = self.chunks.keys().copied().collect();
+        let mut handles: Vec<SvdagHandle> = chunks.iter().map(|chunk| self.chunks[chunk]).collect();
+
+        let freed = self.pool.collect_garbage(&mut handles);
+        self.chunks.extend(chunks.into_iter().zip(handles));
+        self.live_node_count = self.pool.node_count();
+
+        freed
+    }
+
+    pub fn get(&self, position: WorldPosition) -> bool {
+        let (chunk, local_position) = match self.split_position(position) {
+            Ok(split_position) => split_position,
+            Err(_) => return false,
+        };
+
+        match self.chunks.get(&chunk) {
+            Some(handle) => self.pool.get(*handle, local_position) == Some(true),
+            None => false,
+        }
+    }
+
+    /// Sets or clears a voxel, creating or dropping its chunk as needed.
+    pub fn set(&mut self, position: WorldPosition, value: bool) -> Result<(), SvdagError> {
+        let (chunk, local_position) = self.split_position(position)?;
+
+        let handle = match self.chunks.get(&chunk) {
+            Some(handle) => *handle,
+            //An empty chunk is a single node, nothing needs sampling
+            None if value => self.pool.insert_octants(
+                self.chunk_depth,
+                |_, _| OctantCoverage::Empty,
+                |_| false,
+            )?,
+            None => return Ok(()),
+        };
+
+        let handle = self.pool.set(handle, local_position, value)?;
+        self.store_chunk(chunk, handle);
+
+        Ok(())
+    }
+
+    /// Casts a ray through the world up to the ray parameter `max_t`, crossing chunk boundaries,
+    /// and returns the first occupied voxel. Voxel `(x, y, z)` spans `[x, x + 1)` on every axis.
+    pub fn raycast(&self, origin: [f32; 3], direction: [f32; 3], max_t: f32) -> Option<WorldHit> {
+        let (bounds_min, bounds_max) = self.bounds?;
+        let side = self.chunk_side() as f32;
+
+        //Clip the ray against the box around all chunks, nothing outside of it can be hit
+        let mut t = 0.0f32;
+        let mut t_exit = max_t;
+        for axis in 0..3 {
+            let box_min = bounds_min[axis] as f32 * side;
+            let box_max = (bounds_max[axis] as f32 + 1.0) * side;
+
+            if direction[axis] == 0.0 {
+                if origin[axis] < box_min || origin[axis] >= box_max {
+                    return None;
+                }
+                continue;
+            }
+
+            let t0 = (box_min - origin[axis]) / direction[axis];
+            let t1 = (box_max - origin[axis]) / direction[axis];
+
+            t = t.max(t0.min(t1));
+            t_exit = t_exit.min(t0.max(t1));
+        }
+
+        if t > t_exit {
+            return None;
+        }
+
+        //Walk the chunk grid from the chunk the clipped ray starts in
+        let mut chunk = [0i32; 3];
+        let mut t_next = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            let entry = origin[axis] + direction[axis] * t;
+            chunk[axis] = ((entry / side).floor() as i32).clamp(bounds_min[axis], bounds_max[axis]);
+
+            if direction[axis] > 0.0 {
+                t_next[axis] = ((chunk[axis] + 1) as f32 * side - origin[axis]) / direction[axis];
+            } else if direction[axis] < 0.0 {
+                t_next[axis] = (chunk[axis] as f32 * side - origin[axis]) / direction[axis];
+            }
+        }
+
+        loop {
+            let chunk_position = (chunk[0], chunk[1], chunk[2]);
+
+            if let Some(handle) = self.chunks.get(&chunk_position) {
+                let chunk_origin = [
+                    origin[0] - chunk[0] as f32 * side,
+                    origin[1] - chunk[1] as f32 * side,
+                    origin[2] - chunk[2] as f32 * side,
+                ];
+
+                if let Some(hit) = self.pool.raycast(*handle, chunk_origin, direction, max_t) {
+                    return Some(WorldHit {
+                        position: self.world_position(chunk_position, hit.position),
+                        chunk: chunk_position,
+                        distance: hit.distance,
+                        normal: hit.normal,
+                    });
+                }
+            }
+
+            let axis = (0..3)
+                .min_by(|a, b| t_next[*a].total_cmp(&t_next[*b]))
+                .unwrap();
+            if t_next[axis] > t_exit {
+                return None;
+            }
+
+            let step = if direction[axis] > 0.0 { 1 } else { -1 };
+            chunk[axis] += step;
+            t_next[axis] += side / direction[axis].abs();
+        }
+    }
+
+    /// Smallest and largest chunk coordinates on every axis, `None` if there are no chunks.
+    fn chunk_bounds(&self) -> Option<([i32; 3], [i32; 3])> {
+        let mut chunks = self.chunks.keys();
+        let first = chunks.next()?;
+
+        let mut bounds_min = [first.0, first.1, first.2];
+        let mut bounds_max = bounds_min;
+        for chunk in chunks {
+            let chunk = [chunk.0, chunk.1, chunk.2];
+
+            for axis in 0..3 {
+                bounds_min[axis] = bounds_min[axis].min(chunk[axis]);
+                bounds_max[axis] = bounds_max[axis].max(chunk[axis]);
+            }
+        }
+
+        Some((bounds_min, bounds_max))
+    }
+
+    fn grow_bounds(&mut self, chunk: ChunkPosition) {
+        let chunk = [chunk.0, chunk.1, chunk.2];
+
+        self.bounds = Some(match self.bounds {
+            Some((mut bounds_min, mut bounds_max)) => {
+                for axis in 0..3 {
+                    bounds_min[axis] = bounds_min[axis].min(chunk[axis]);
+                    bounds_max[axis] = bounds_max[axis].max(chunk[axis]);
+                }
+                (bounds_min, bounds_max)
+            }
+            None => (chunk, chunk),
+        });
+    }
+
+    /// Updates the bounds after `chunk` was removed, which only moves them if it lay on them.
+    fn shrink_bounds(&mut self, chunk: ChunkPosition) {
+        let chunk = [chunk.0, chunk.1, chunk.2];
+
+        if let Some((bounds_min, bounds_max)) = self.bounds {
+            if (0..3).any(|axis| chunk[axis] == bounds_min[axis] || chunk[axis] == bounds_max[axis])
+            {
+                self.bounds = self.chunk_bounds();
+            }
+        }
+    }
+
+    fn store_chunk(&mut self, chunk: ChunkPosition, handle: SvdagHandle) {
+        if self.pool.is_empty_graph(handle) {
+            self.remove_chunk(chunk);
+        } else {
+            self.chunks.insert(chunk, handle);
+            self.grow_bounds(chunk);
+        }
+
+        //Collecting once the pool doubled keeps the cost per edit constant on average
+        if self.pool.node_count() > 2 * self.live_node_count.max(MIN_COLLECTED_NODE_COUNT) {
+            self.collect_garbage();
+        }
+    }
+}
@@ -15,12 +15,19 @@ pub trait IsVolume {
 
 pub trait IsVolumeIndex {
     fn get_index(&self, volume: &impl IsVolume) -> VolumeIndex;
+
+    /// Whether the index lies inside a volume of the given dimensions.
+    fn is_inside(&self, dimensions: VolumeDimensions) -> bool;
 }
 
 impl IsVolumeIndex for usize {
     fn get_index(&self, _: &impl IsVolume) -> VolumeIndex {
         *self
     }
+
+    fn is_inside(&self, dimensions: VolumeDimensions) -> bool {
+        *self < dimensions.0 * dimensions.1 * dimensions.2
+    }
 }
 
 impl IsVolumeIndex for VolumePosition {
@@ -28,13 +35,23 @@ impl IsVolumeIndex for VolumePosition {
         let dimensions = volume.get_dimensions();
         self.2 * (dimensions.0 * dimensions.1) + self.1 * dimensions.0 + self.0
     }
+
+    fn is_inside(&self, dimensions: VolumeDimensions) -> bool {
+        self.0 < dimensions.0 && self.1 < dimensions.1 && self.2 < dimensions.2
+    }
 }
 
+/// A volume inside the cube of side `2^depth`.
+///
+/// Only the voxels inside `get_dimensions` are stored, the rest of the cube is padding that
+/// always reads as the default value.
 pub struct CubicVolume<T>
 where
     T: Default + Clone,
 {
     values: Vec<T>,
+    dimensions: VolumeDimensions,
+    padding: T,
     pub depth: u8,
 }
 
@@ -43,9 +60,7 @@ where
     T: Default + Clone,
 {
     fn get_dimensions(&self) -> VolumeDimensions {
-        let side_size = CubicVolume::<T>::get_side_element_count(self.depth);
-
-        (side_size, side_size, side_size)
+        self.dimensions
     }
 }
 
@@ -54,17 +69,65 @@ where
     T: Default + Clone,
 {
     pub fn new(depth: u8) -> CubicVolume<T> {
+        let side_size = CubicVolume::<T>::get_side_element_count(depth);
+
         CubicVolume {
             values: vec![T::default(); CubicVolume::<T>::get_volume_element_count(depth)],
+            dimensions: (side_size, side_size, side_size),
+            padding: T::default(),
             depth,
         }
     }
 
+    /// Creates a volume of arbitrary dimensions inside the smallest cube of side `2^depth` that
+    /// holds them, `depth` being at least 1 as graphs need at least one level of nodes.
+    pub fn with_dimensions(dimensions: VolumeDimensions) -> CubicVolume<T> {
+        let largest_side = dimensions.0.max(dimensions.1).max(dimensions.2).max(2);
+
+        CubicVolume::padded(
+            largest_side.next_power_of_two().trailing_zeros() as u8,
+            dimensions,
+        )
+    }
+
+    /// Creates a volume of the given dimensions in the cube of side `2^depth`, which has to hold them.
+    pub(crate) fn padded(depth: u8, dimensions: VolumeDimensions) -> CubicVolume<T> {
+        CubicVolume {
+            values: vec![T::default(); dimensions.0 * dimensions.1 * dimensions.2],
+            dimensions,
+            padding: T::default(),
+            depth,
+        }
+    }
+
+    /// Reads a value, positions in the padding outside of the dimensions read as the default value.
+    ///
+    /// # Panics
+    ///
+    /// If the position lies outside of the cube of side `2^depth`.
     pub fn get(&self, volume_index: impl IsVolumeIndex) -> &T {
-        &self.values[volume_index.get_index(self)]
+        if volume_index.is_inside(self.dimensions) {
+            return &self.values[volume_index.get_index(self)];
+        }
+
+        let side_size = CubicVolume::<T>::get_side_element_count(self.depth);
+        assert!(
+            volume_index.is_inside((side_size, side_size, side_size)),
+            "position lies outside of the volume"
+        );
+
+        &self.padding
     }
 
+    /// # Panics
+    ///
+    /// If the position lies outside of the dimensions, the padding can't be written.
     pub fn get_mut(&mut self, volume_index: impl IsVolumeIndex) -> &mut T {
+        assert!(
+            volume_index.is_inside(self.dimensions),
+            "position lies outside of the volume"
+        );
+
         let index = volume_index.get_index(self);
         &mut self.values[index]
     }
@@ -0,0 +1,97 @@
+mod common;
+
+use common::{for_each_position, Random};
+use svdag::{AttributedSvdag, CubicVolume, DensityVolume, Svdag, SvdagRead};
+
+type Rgba8 = [u8; 4];
+
+fn create_colored_volume(depth: u8, seed: u64) -> CubicVolume<Option<Rgba8>> {
+    let mut random = Random::new(seed);
+    let mut volume = CubicVolume::new(depth);
+    let half_size = 1usize << (depth - 1);
+
+    for_each_position(&CubicVolume::<bool>::new(depth), |(x, y, z)| {
+        //Both halves along x share their shape but get different random colors
+        let occupied = (x % half_size + y * 3 + z).is_multiple_of(4);
+        if occupied {
+            let color = random.next_u64().to_le_bytes();
+            *volume.get_mut((x, y, z)) = Some([color[0], color[1], color[2], 255]);
+        }
+    });
+
+    volume
+}
+
+#[test]
+fn attributes_match_source_volume() {
+    for depth in 1..=5 {
+        let volume = create_colored_volume(depth, depth as u64);
+        let attributed = AttributedSvdag::from_volume(&volume).unwrap();
+
+        for_each_position(&CubicVolume::<bool>::new(depth), |position| {
+            assert_eq!(
+                attributed.get(position),
+                Some(volume.get(position).is_some())
+            );
+            assert_eq!(
+                attributed.get_attribute(position),
+                volume.get(position).as_ref()
+            );
+        });
+    }
+}
+
+#[test]
+fn colors_do_not_break_geometry_sharing() {
+    let depth = 5;
+    let volume = create_colored_volume(depth, 99);
+    let attributed = AttributedSvdag::from_volume(&volume).unwrap();
+
+    let mut density_volume = DensityVolume::new(depth);
+    for_each_position(&CubicVolume::<bool>::new(depth), |position| {
+        *density_volume.get_mut(position) = volume.get(position).is_some();
+    });
+    let geometry = Svdag::from(&density_volume);
+
+    assert_eq!(attributed.geometry.nodes, geometry.nodes);
+    assert_eq!(
+        attributed.attributes.len() as u64,
+        attributed.voxel_counts[&0]
+    );
+
+    //The two halves along x are identical in shape, so the root points to the same subtrees
+    let root = attributed.geometry.nodes[0].node();
+    assert_eq!(root.children.child_bits, 0b1111_1111);
+    for child_index in 0..4 {
+        assert_eq!(
+            attributed.geometry.read_pointer(1 + child_index),
+            attributed.geometry.read_pointer(5 + child_index)
+        );
+    }
+}
+
+#[test]
+fn attribute_indices_follow_child_order() {
+    let mut volume = CubicVolume::new(2);
+    *volume.get_mut((3, 3, 3)) = Some(3u16);
+    *volume.get_mut((0, 0, 1)) = Some(1);
+    *volume.get_mut((0, 0, 0)) = Some(0);
+    *volume.get_mut((2, 0, 0)) = Some(2);
+
+    let attributed = AttributedSvdag::from_volume(&volume).unwrap();
+
+    assert_eq!(attributed.attributes, vec![0, 1, 2, 3]);
+    assert_eq!(attributed.attribute_index((2, 0, 0)), Some(2));
+    assert_eq!(attributed.get_attribute((1, 1, 1)), None);
+    assert_eq!(attributed.get_attribute((4, 0, 0)), None);
+
+    //Counts are kept for the root and its three leaves, never for pointer words
+    assert_eq!(attributed.voxel_counts.len(), 4);
+    assert!(attributed.geometry.nodes.len() > 4);
+    assert_eq!(attributed.voxel_counts[&0], 4);
+
+    //An empty graph has a counted root with no voxels
+    let empty = AttributedSvdag::from_volume(&CubicVolume::<Option<u16>>::new(2)).unwrap();
+    assert_eq!(empty.voxel_counts.get(&0), Some(&0));
+    assert_eq!(empty.get_attribute((0, 0, 0)), None);
+}
@@ -0,0 +1,83 @@
+#![allow(dead_code)]
+
+use svdag::{volume::VolumePosition, CubicVolume, DensityVolume, IsVolume, Svdag, SvdagRead};
+
+/// Small xorshift generator so tests are reproducible without extra dependencies.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Uniform value in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+pub fn for_each_position(volume: &impl IsVolume, mut f: impl FnMut(VolumePosition)) {
+    let dimensions = volume.get_dimensions();
+
+    for x in 0..dimensions.0 {
+        for y in 0..dimensions.1 {
+            for z in 0..dimensions.2 {
+                f((x, y, z));
+            }
+        }
+    }
+}
+
+/// Volume where every voxel is occupied with a chance of one in `one_in`.
+pub fn create_noise_volume(depth: u8, seed: u64, one_in: u64) -> DensityVolume {
+    let mut random = Random::new(seed);
+    let mut volume = CubicVolume::new(depth);
+
+    for_each_position(&CubicVolume::<bool>::new(depth), |position| {
+        *volume.get_mut(position) = random.next_u64().is_multiple_of(one_in);
+    });
+
+    volume
+}
+
+pub fn create_sphere_volume(depth: u8) -> DensityVolume {
+    let mut volume = CubicVolume::new(depth);
+    let center = volume.get_dimensions().0 as f32 / 2.0;
+
+    for_each_position(&CubicVolume::<bool>::new(depth), |(x, y, z)| {
+        let distance = ((x as f32 - center).powf(2.0)
+            + (y as f32 - center).powf(2.0)
+            + (z as f32 - center).powf(2.0))
+        .sqrt();
+
+        *volume.get_mut((x, y, z)) = distance < center / 2.0;
+    });
+
+    volume
+}
+
+pub fn assert_matches_volume(svdag: &impl SvdagRead, volume: &DensityVolume) {
+    for_each_position(volume, |position| {
+        assert_eq!(
+            svdag.get(position),
+            Some(*volume.get(position)),
+            "mismatch at {:?}",
+            position
+        );
+    });
+}
+
+pub fn serialize(svdag: &Svdag) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    svdag.write_to(&mut bytes).unwrap();
+    bytes
+}
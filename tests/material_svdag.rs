@@ -0,0 +1,74 @@
+mod common;
+
+use common::{for_each_position, Random};
+use svdag::{CubicVolume, DensityVolume, MaterialSvdagBuilder, PointerFormat, Svdag};
+
+#[test]
+fn materials_match_source_volume() {
+    let mut random = Random::new(5);
+
+    for depth in 1..=5 {
+        let mut volume = CubicVolume::<u8>::new(depth);
+        for_each_position(&CubicVolume::<bool>::new(depth), |position| {
+            *volume.get_mut(position) = (random.next_u64() % 4) as u8;
+        });
+
+        for pointer_format in &[PointerFormat::Relative16, PointerFormat::Absolute32] {
+            let material_svdag = MaterialSvdagBuilder::new()
+                .pointer_format(*pointer_format)
+                .build(&volume)
+                .unwrap();
+
+            for_each_position(&volume, |position| {
+                let material = *volume.get(position);
+
+                assert_eq!(material_svdag.get_material(position), Some(material as u16));
+                assert_eq!(material_svdag.get(position), Some(material != 0));
+            });
+        }
+    }
+}
+
+#[test]
+fn uniform_material_regions_collapse() {
+    let depth = 5;
+    let mut volume = CubicVolume::<u16>::new(depth);
+    for_each_position(&CubicVolume::<bool>::new(depth), |(x, y, z)| {
+        *volume.get_mut((x, y, z)) = if x < 16 { 300 } else { 7 };
+    });
+
+    let material_svdag = MaterialSvdagBuilder::new().build(&volume).unwrap();
+
+    //One chain of nodes per material below the root
+    let per_level = 9;
+    let leaf = 1 + 8;
+    let chain = (depth as usize - 2) * per_level + leaf;
+    assert_eq!(material_svdag.nodes.len(), per_level + 2 * chain);
+    assert_eq!(material_svdag.get_material((3, 30, 1)), Some(300));
+    assert_eq!(material_svdag.get_material((16, 0, 31)), Some(7));
+    assert_eq!(material_svdag.get_material((32, 0, 0)), None);
+}
+
+#[test]
+fn equal_shapes_with_different_materials_stay_apart() {
+    let depth = 4;
+    let mut volume = CubicVolume::<u8>::new(depth);
+    let mut density_volume = DensityVolume::new(depth);
+    for_each_position(&CubicVolume::<bool>::new(depth), |(x, y, z)| {
+        if (x % 8 + y + z) % 3 == 0 {
+            *volume.get_mut((x, y, z)) = if x < 8 { 1 } else { 2 };
+            *density_volume.get_mut((x, y, z)) = true;
+        }
+    });
+
+    let material_svdag = MaterialSvdagBuilder::new().build(&volume).unwrap();
+    let geometry = Svdag::from(&density_volume);
+
+    assert!(material_svdag.nodes.len() > geometry.nodes.len());
+    for_each_position(&volume, |position| {
+        assert_eq!(
+            material_svdag.get_material(position),
+            Some(*volume.get(position) as u16)
+        );
+    });
+}
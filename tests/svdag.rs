@@ -0,0 +1,95 @@
+mod common;
+
+use common::{assert_matches_volume, create_noise_volume, create_sphere_volume, for_each_position};
+use svdag::{
+    svdag::SvdagValue, CubicVolume, DensityVolume, PointerFormat, Svdag, SvdagBuilder,
+    SvdagStreamBuilder,
+};
+
+fn create_structured_volumes(depth: u8) -> Vec<DensityVolume> {
+    let mut checkerboard = CubicVolume::new(depth);
+    let mut slab = CubicVolume::new(depth);
+    let mut corner = CubicVolume::new(depth);
+    let mut full = CubicVolume::new(depth);
+
+    for_each_position(&CubicVolume::<bool>::new(depth), |(x, y, z)| {
+        *checkerboard.get_mut((x, y, z)) = (x + y + z) % 2 == 0;
+        *slab.get_mut((x, y, z)) = y == 1;
+        *corner.get_mut((x, y, z)) = x + 1 == 1 << depth && y == 0 && z + 1 == 1 << depth;
+        *full.get_mut((x, y, z)) = true;
+    });
+
+    vec![
+        CubicVolume::new(depth),
+        checkerboard,
+        slab,
+        corner,
+        full,
+        create_sphere_volume(depth),
+    ]
+}
+
+#[test]
+fn get_matches_random_volumes() {
+    for depth in 1..=5 {
+        for (seed, one_in) in &[(1, 2), (7, 5), (13, 31)] {
+            let volume = create_noise_volume(depth, *seed, *one_in);
+
+            assert_matches_volume(&Svdag::from(&volume), &volume);
+        }
+    }
+}
+
+#[test]
+fn get_matches_structured_volumes() {
+    for depth in 1..=5 {
+        for volume in create_structured_volumes(depth) {
+            for pointer_format in &[PointerFormat::Relative16, PointerFormat::Absolute32] {
+                let dense = SvdagBuilder::new()
+                    .pointer_format(*pointer_format)
+                    .create_layers(&volume)
+                    .create_graph()
+                    .unwrap()
+                    .finish();
+                let streamed = SvdagStreamBuilder::new(depth)
+                    .pointer_format(*pointer_format)
+                    .build_from_fn(|position| *volume.get(position))
+                    .unwrap();
+
+                assert_matches_volume(&dense, &volume);
+                assert_matches_volume(&streamed, &volume);
+            }
+        }
+    }
+}
+
+#[test]
+fn get_rejects_positions_outside_the_volume() {
+    let svdag = Svdag::from(&create_sphere_volume(3));
+
+    assert_eq!(svdag.get((7, 7, 7)), Some(false));
+    assert_eq!(svdag.get((8, 0, 0)), None);
+    assert_eq!(svdag.get((0, 8, 0)), None);
+    assert_eq!(svdag.get((0, 0, 8)), None);
+    assert_eq!(svdag.get((usize::MAX, 0, 0)), None);
+}
+
+#[test]
+fn get_does_not_panic_on_broken_graphs() {
+    let volume = create_sphere_volume(3);
+    let mut svdag = Svdag::from(&volume);
+
+    //Point the root's first child past the end of the node array
+    svdag.nodes[1] = SvdagValue { bits: 0x7fff };
+    for_each_position(&volume, |position| {
+        let _ = svdag.get(position);
+    });
+
+    svdag.nodes.truncate(1);
+    for_each_position(&volume, |position| {
+        let _ = svdag.get(position);
+    });
+
+    let empty = Svdag::new();
+    assert_eq!(empty.get((0, 0, 0)), None);
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::{assert_matches_volume, create_noise_volume, create_sphere_volume, for_each_position};
+use svdag::{DensityVolume, Svdag, SvdagBuilder, SvdagError};
+
+fn combine_volumes(
+    a: &DensityVolume,
+    b: &DensityVolume,
+    op: impl Fn(bool, bool) -> bool,
+) -> DensityVolume {
+    let mut volume = DensityVolume::new(a.depth);
+
+    for_each_position(a, |position| {
+        *volume.get_mut(position) = op(*a.get(position), *b.get(position));
+    });
+
+    volume
+}
+
+/// Checks the result voxel by voxel and against a fresh build, which is fully deduplicated.
+fn assert_combined(svdag: &Svdag, expected: &DensityVolume) {
+    assert_matches_volume(svdag, expected);
+
+    let rebuilt = Svdag::from_fn(expected.depth, |position| *expected.get(position)).unwrap();
+    assert_eq!(svdag.nodes, rebuilt.nodes);
+}
+
+#[test]
+fn combines_noise_volumes() {
+    for (depth, seed) in [(1, 1), (3, 2), (5, 3)].iter() {
+        let a = create_noise_volume(*depth, *seed, 2);
+        let b = create_noise_volume(*depth, *seed + 100, 3);
+        let (svdag_a, svdag_b) = (Svdag::from(&a), Svdag::from(&b));
+
+        assert_combined(
+            &svdag_a.union(&svdag_b).unwrap(),
+            &combine_volumes(&a, &b, |a, b| a | b),
+        );
+        assert_combined(
+            &svdag_a.intersect(&svdag_b).unwrap(),
+            &combine_volumes(&a, &b, |a, b| a & b),
+        );
+        assert_combined(
+            &svdag_a.subtract(&svdag_b).unwrap(),
+            &combine_volumes(&a, &b, |a, b| a & !b),
+        );
+    }
+}
+
+#[test]
+fn combines_shapes_with_solid_and_empty_octants() {
+    let sphere = create_sphere_volume(5);
+    let mut slab = DensityVolume::new(5);
+    for_each_position(&DensityVolume::new(5), |position| {
+        *slab.get_mut(position) = position.1 < 16;
+    });
+    let (svdag_sphere, svdag_slab) = (Svdag::from(&sphere), Svdag::from(&slab));
+
+    let union = svdag_sphere.union(&svdag_slab).unwrap();
+    assert_combined(&union, &combine_volumes(&sphere, &slab, |a, b| a | b));
+
+    let intersection = svdag_sphere.intersect(&svdag_slab).unwrap();
+    assert_combined(
+        &intersection,
+        &combine_volumes(&sphere, &slab, |a, b| a & b),
+    );
+
+    let difference = svdag_slab.subtract(&svdag_sphere).unwrap();
+    assert_combined(&difference, &combine_volumes(&slab, &sphere, |a, b| a & !b));
+}
+
+#[test]
+fn handles_identities() {
+    let volume = create_noise_volume(4, 7, 2);
+    let svdag = Svdag::from(&volume);
+    let empty = Svdag::from_fn(4, |_| false).unwrap();
+    let full = Svdag::from_fn(4, |_| true).unwrap();
+
+    assert_eq!(svdag.union(&empty).unwrap().nodes, svdag.nodes);
+    assert_eq!(svdag.union(&full).unwrap().nodes, full.nodes);
+    assert_eq!(svdag.intersect(&full).unwrap().nodes, svdag.nodes);
+    assert_eq!(svdag.intersect(&empty).unwrap().nodes, empty.nodes);
+    assert_eq!(svdag.subtract(&full).unwrap().nodes, empty.nodes);
+    assert_eq!(svdag.subtract(&svdag).unwrap().nodes, empty.nodes);
+}
+
+#[test]
+fn combines_symmetric_graphs() {
+    let a = create_sphere_volume(4);
+    let b = create_noise_volume(4, 11, 2);
+    let symmetric_a = SvdagBuilder::new()
+        .symmetry(true)
+        .create_layers(&a)
+        .create_graph()
+        .unwrap()
+        .finish();
+    let svdag_b = Svdag::from(&b);
+
+    assert_matches_volume(
+        &symmetric_a.union(&svdag_b).unwrap(),
+        &combine_volumes(&a, &b, |a, b| a | b),
+    );
+    assert_matches_volume(
+        &svdag_b.subtract(&symmetric_a).unwrap(),
+        &combine_volumes(&b, &a, |a, b| a & !b),
+    );
+}
+
+#[test]
+fn rejects_mismatched_and_malformed_graphs() {
+    let a = Svdag::from_fn(3, |_| true).unwrap();
+    let b = Svdag::from_fn(4, |_| true).unwrap();
+
+    assert!(matches!(
+        a.union(&b),
+        Err(SvdagError::DepthMismatch {
+            expected: 3,
+            actual: 4
+        })
+    ));
+
+    //A child pointer past the end of the node array
+    let mut truncated = Svdag::from(&create_noise_volume(3, 5, 2));
+    truncated.nodes.truncate(3);
+    assert!(truncated.intersect(&a).is_err());
+    assert!(a.subtract(&truncated).is_err());
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::{assert_matches_volume, create_noise_volume};
+use std::hash::{BuildHasherDefault, Hasher};
+use svdag::{CubicVolume, DensityVolume, IsVolume, PointerFormat, Svdag, SvdagBuilder, SvdagError};
+
+/// Hasher that maps everything to the same value, so every subtree collides with every other.
+#[derive(Default)]
+struct CollidingHasher;
+
+impl Hasher for CollidingHasher {
+    fn finish(&self) -> u64 {
+        0
+    }
+
+    fn write(&mut self, _: &[u8]) {}
+}
+
+fn create_test_volume(depth: u8) -> DensityVolume {
+    let mut volume = CubicVolume::new(depth);
+    let dimensions = volume.get_dimensions();
+
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for x in 0..dimensions.0 {
+        for y in 0..dimensions.1 {
+            for z in 0..dimensions.2 {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+
+                //Mix repeated structure (the lower half slab) with noise so both merging and collisions happen
+                *volume.get_mut((x, y, z)) = y < dimensions.1 / 2 || state.is_multiple_of(5);
+            }
+        }
+    }
+
+    volume
+}
+
+#[test]
+fn colliding_hashes_do_not_merge_different_subtrees() {
+    for depth in 1..=4 {
+        let volume = create_test_volume(depth);
+
+        let reference = SvdagBuilder::new()
+            .create_layers(&volume)
+            .create_graph()
+            .unwrap()
+            .finish();
+        let colliding = SvdagBuilder::with_hasher(BuildHasherDefault::<CollidingHasher>::default())
+            .create_layers(&volume)
+            .create_graph()
+            .unwrap()
+            .finish();
+
+        assert_eq!(reference.nodes, colliding.nodes);
+        assert_matches_volume(&reference, &volume);
+        assert_matches_volume(&colliding, &volume);
+    }
+}
+
+#[test]
+fn relative_pointers_report_overflow() {
+    let volume = create_noise_volume(6, 0x9e37_79b9_7f4a_7c15, 2);
+
+    let result = SvdagBuilder::new()
+        .pointer_format(PointerFormat::Relative16)
+        .create_layers(&volume)
+        .create_graph()
+        .map(|builder| builder.finish());
+
+    match result {
+        Err(SvdagError::PointerOverflow { pointer_format, .. }) => {
+            assert_eq!(pointer_format, PointerFormat::Relative16)
+        }
+        other => panic!("expected a pointer overflow, got {:?}", other),
+    }
+}
+
+#[test]
+fn large_graphs_fall_back_to_absolute_pointers() {
+    let volume = create_noise_volume(6, 0x9e37_79b9_7f4a_7c15, 2);
+
+    let svdag = Svdag::from(&volume);
+
+    assert_eq!(svdag.pointer_format, PointerFormat::Absolute32);
+    assert!(svdag.nodes.len() > i16::MAX as usize);
+    assert_matches_volume(&svdag, &volume);
+}
+
+#[test]
+fn small_graphs_keep_relative_pointers() {
+    let volume = create_test_volume(4);
+
+    let relative = Svdag::from(&volume);
+    let absolute = SvdagBuilder::new()
+        .pointer_format(PointerFormat::Absolute32)
+        .create_layers(&volume)
+        .create_graph()
+        .unwrap()
+        .finish();
+
+    assert_eq!(relative.pointer_format, PointerFormat::Relative16);
+    assert_matches_volume(&relative, &volume);
+    assert_matches_volume(&absolute, &volume);
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use common::{assert_matches_volume, create_noise_volume, create_sphere_volume};
+use std::collections::HashMap;
+use svdag::{
+    svdag::SvdagValue, NodeOrder, PointerFormat, Svdag, SvdagBuilder, SvdagError, SvdagRead,
+};
+
+/// Height of every node reachable from the root by index.
+fn node_heights(svdag: &Svdag) -> HashMap<usize, u8> {
+    let mut heights = HashMap::new();
+    let mut stack = vec![(0, svdag.depth)];
+
+    while let Some((node_index, height)) = stack.pop() {
+        if heights.insert(node_index, height).is_some() || height == 1 {
+            continue;
+        }
+
+        let node = svdag.nodes[node_index].node();
+        for child_number in 0..node.children.count_occupied() {
+            let pointer_index = node_index + 1 + child_number * svdag.pointer_format.word_count();
+            stack.push((svdag.read_pointer(pointer_index).unwrap(), height - 1));
+        }
+    }
+
+    heights
+}
+
+/// Depth 2 graph with its two leaves stored as separate copies behind a block of dead words.
+fn create_patched_svdag() -> Svdag {
+    let node = |child_bits: u16| SvdagValue { bits: child_bits };
+    let pointer = |offset: i16| SvdagValue {
+        bits: offset as u16,
+    };
+
+    Svdag {
+        depth: 2,
+        dimensions: (4, 4, 4),
+        pointer_format: PointerFormat::Relative16,
+        nodes: vec![
+            node(0b1000_0001),
+            pointer(5),
+            pointer(5),
+            //Unreachable leftovers of an earlier edit
+            node(0b1111_1111),
+            node(0b0000_0011),
+            node(0b0101_0101),
+            //Two copies of the same leaf
+            node(0b0011_1100),
+            node(0b0011_1100),
+        ],
+    }
+}
+
+#[test]
+fn drops_unreachable_and_duplicate_nodes() {
+    let mut svdag = create_patched_svdag();
+    let original = svdag.clone();
+
+    let report = svdag.compact(NodeOrder::DepthFirst).unwrap();
+
+    assert_eq!(svdag.nodes.len(), 4);
+    assert_eq!(report.bytes_before, 16);
+    assert_eq!(report.bytes_after, 8);
+    assert_eq!(report.bytes_reclaimed(), 8);
+
+    for x in 0..4 {
+        for y in 0..4 {
+            for z in 0..4 {
+                assert_eq!(svdag.get((x, y, z)), original.get((x, y, z)));
+            }
+        }
+    }
+}
+
+#[test]
+fn keeps_built_graphs_as_they_are() {
+    let volume = create_noise_volume(5, 3, 4);
+    let mut svdag = Svdag::from(&volume);
+    let original = svdag.clone();
+
+    let report = svdag.compact(NodeOrder::DepthFirst).unwrap();
+
+    assert_eq!(report.bytes_reclaimed(), 0);
+    assert_eq!(svdag.nodes, original.nodes);
+}
+
+#[test]
+fn lays_out_levels_breadth_first() {
+    let volume = create_sphere_volume(6);
+    let mut svdag = Svdag::from(&volume);
+    let node_count = svdag.nodes.len();
+
+    svdag.compact(NodeOrder::BreadthFirst).unwrap();
+
+    assert_eq!(svdag.nodes.len(), node_count);
+    assert_matches_volume(&svdag, &volume);
+
+    //Every level lies entirely before the next one
+    let heights = node_heights(&svdag);
+    for height in 2..=svdag.depth {
+        let last_of_level = heights
+            .iter()
+            .filter(|(_, node_height)| **node_height == height)
+            .map(|(node_index, _)| *node_index)
+            .max()
+            .unwrap();
+        let first_of_next_level = heights
+            .iter()
+            .filter(|(_, node_height)| **node_height == height - 1)
+            .map(|(node_index, _)| *node_index)
+            .min()
+            .unwrap();
+
+        assert!(last_of_level < first_of_next_level);
+    }
+}
+
+#[test]
+fn keeps_mirrored_pointers() {
+    let volume = create_sphere_volume(5);
+    let mut svdag = SvdagBuilder::new()
+        .symmetry(true)
+        .create_layers(&volume)
+        .create_graph()
+        .unwrap()
+        .finish();
+
+    svdag.compact(NodeOrder::BreadthFirst).unwrap();
+
+    assert_eq!(svdag.pointer_format, PointerFormat::Mirrored32);
+    assert_matches_volume(&svdag, &volume);
+}
+
+#[test]
+fn rejects_broken_graphs() {
+    let mut svdag = create_patched_svdag();
+    svdag.nodes.truncate(6);
+
+    match svdag.compact(NodeOrder::DepthFirst) {
+        Err(SvdagError::InvalidNode { index }) => assert!(index >= 6),
+        result => panic!("expected InvalidNode, got {:?}", result),
+    }
+}
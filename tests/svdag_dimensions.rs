@@ -0,0 +1,252 @@
+mod common;
+
+use common::{assert_matches_volume, for_each_position, serialize, Random};
+use svdag::{
+    volume::VolumeDimensions, AttributedSvdag, BoxBrush, CubicVolume, DensityVolume, IsVolume,
+    MaterialSvdagBuilder, NodeOrder, Svdag, SvdagError, SvdagPool, SvdagRead, SvdagRef,
+    SvdagStreamBuilder,
+};
+
+fn create_noise_volume(dimensions: VolumeDimensions, seed: u64) -> DensityVolume {
+    let mut random = Random::new(seed);
+    let mut volume = DensityVolume::with_dimensions(dimensions);
+
+    for_each_position(&DensityVolume::with_dimensions(dimensions), |position| {
+        *volume.get_mut(position) = random.next_u64().is_multiple_of(3);
+    });
+
+    volume
+}
+
+/// The same voxels in the smallest cubic volume holding them.
+fn pad_volume(volume: &DensityVolume) -> DensityVolume {
+    let mut padded = DensityVolume::new(volume.depth);
+
+    for_each_position(volume, |position| {
+        *padded.get_mut(position) = *volume.get(position);
+    });
+
+    padded
+}
+
+/// Reads the padding around the dimensions as well, which has to stay empty.
+fn assert_padding_is_empty(svdag: &Svdag) {
+    let cubic = Svdag {
+        dimensions: CubicVolume::<bool>::new(svdag.depth).get_dimensions(),
+        ..svdag.clone()
+    };
+    let dimensions = svdag.dimensions;
+
+    for_each_position(&cubic, |(x, y, z)| {
+        if x >= dimensions.0 || y >= dimensions.1 || z >= dimensions.2 {
+            assert_eq!(
+                cubic.get((x, y, z)),
+                Some(false),
+                "padding at {:?}",
+                (x, y, z)
+            );
+        }
+    });
+}
+
+#[test]
+fn stores_only_the_dimensions() {
+    let mut volume = DensityVolume::with_dimensions((13, 5, 9));
+    *volume.get_mut((12, 4, 8)) = true;
+
+    assert_eq!(volume.depth, 4);
+    assert_eq!(volume.get_dimensions(), (13, 5, 9));
+    assert_eq!(volume.get_element_count(), 13 * 5 * 9);
+    assert!(*volume.get((12, 4, 8)));
+    assert!(!*volume.get((15, 15, 15)));
+}
+
+#[test]
+#[should_panic(expected = "outside of the volume")]
+fn rejects_writes_to_the_padding() {
+    let mut volume = DensityVolume::with_dimensions((13, 5, 9));
+
+    *volume.get_mut((0, 5, 0)) = true;
+}
+
+#[test]
+fn builds_graphs_of_arbitrary_dimensions() {
+    for (seed, dimensions) in [(1, 13, 5, 9), (2, 1, 7, 2), (3, 32, 17, 31), (4, 3, 3, 3)]
+        .iter()
+        .map(|(seed, x, y, z)| (*seed, (*x, *y, *z)))
+    {
+        let volume = create_noise_volume(dimensions, seed);
+        let svdag = Svdag::from(&volume);
+
+        assert_eq!(svdag.depth, volume.depth);
+        assert_eq!(svdag.get_dimensions(), dimensions);
+        assert_matches_volume(&svdag, &volume);
+        assert_padding_is_empty(&svdag);
+
+        assert_eq!(svdag.get((dimensions.0, 0, 0)), None);
+        assert_eq!(svdag.get((0, dimensions.1, 0)), None);
+        assert_eq!(svdag.get((0, 0, dimensions.2)), None);
+
+        //Padding is plain empty space, so it deduplicates like it
+        assert_eq!(svdag.nodes, Svdag::from(&pad_volume(&volume)).nodes);
+    }
+}
+
+#[test]
+fn builds_graphs_of_a_single_voxel() {
+    //Even a single voxel needs a leaf to live in
+    let mut volume = DensityVolume::with_dimensions((1, 1, 1));
+    assert_eq!(volume.depth, 1);
+    *volume.get_mut((0, 0, 0)) = true;
+
+    let svdag = Svdag::from(&volume);
+    assert_eq!(svdag.get_dimensions(), (1, 1, 1));
+    assert_eq!(svdag.get((0, 0, 0)), Some(true));
+    assert_eq!(svdag.get((1, 0, 0)), None);
+    assert_padding_is_empty(&svdag);
+}
+
+#[test]
+fn round_trips_dimensions_through_files() {
+    let svdag = Svdag::from(&create_noise_volume((20, 6, 11), 5));
+    let bytes = serialize(&svdag);
+
+    assert_eq!(bytes.len(), 32 + svdag.nodes.len() * 2);
+
+    let loaded = Svdag::read_from(bytes.as_slice()).unwrap();
+    assert_eq!(loaded.dimensions, (20, 6, 11));
+    assert_eq!(loaded.nodes, svdag.nodes);
+
+    let svdag_ref = SvdagRef::from_bytes(&bytes).unwrap();
+    assert_eq!(svdag_ref.get_dimensions(), (20, 6, 11));
+    assert_eq!(svdag_ref.get((19, 5, 10)), svdag.get((19, 5, 10)));
+    assert_eq!(svdag_ref.get((19, 6, 10)), None);
+
+    //Dimensions past the cube are rejected
+    let mut bytes = bytes;
+    bytes[20..24].copy_from_slice(&33u32.to_le_bytes());
+    match Svdag::read_from(bytes.as_slice()) {
+        Err(SvdagError::InvalidDimensions(dimensions)) => assert_eq!(dimensions, (33, 6, 11)),
+        other => panic!("expected invalid dimensions, got {:?}", other),
+    }
+}
+
+#[test]
+fn keeps_edits_inside_the_dimensions() {
+    let mut svdag = Svdag::from(&create_noise_volume((10, 3, 7), 6));
+
+    match svdag.set((0, 3, 0), true) {
+        Err(SvdagError::OutOfBounds { position }) => assert_eq!(position, (0, 3, 0)),
+        other => panic!("expected out of bounds, got {:?}", other),
+    }
+
+    svdag.fill(&BoxBrush::new((5, 0, 0), (16, 16, 16))).unwrap();
+    svdag.set((9, 2, 6), false).unwrap();
+
+    assert_eq!(svdag.dimensions, (10, 3, 7));
+    assert_eq!(svdag.get((5, 1, 6)), Some(true));
+    assert_eq!(svdag.get((9, 2, 6)), Some(false));
+    assert_padding_is_empty(&svdag);
+
+    svdag.compact(NodeOrder::BreadthFirst).unwrap();
+    assert_eq!(svdag.dimensions, (10, 3, 7));
+}
+
+#[test]
+fn combines_dimensions_in_set_operations() {
+    let a = Svdag::from(&create_noise_volume((10, 3, 7), 7));
+    let b = Svdag::from(&create_noise_volume((4, 12, 5), 8));
+
+    assert_eq!(a.union(&b).unwrap().dimensions, (10, 12, 7));
+    assert_eq!(a.intersect(&b).unwrap().dimensions, (4, 3, 5));
+    assert_eq!(a.subtract(&b).unwrap().dimensions, (10, 3, 7));
+}
+
+#[test]
+fn streams_graphs_of_arbitrary_dimensions() {
+    let volume = create_noise_volume((13, 5, 9), 9);
+    let expected = Svdag::from(&volume);
+
+    //Samples past the dimensions would fill the padding
+    let svdag = SvdagStreamBuilder::new(4)
+        .dimensions((13, 5, 9))
+        .build_from_fn(|position| position.0 >= 13 || *volume.get(position))
+        .unwrap();
+    assert_eq!(svdag.dimensions, (13, 5, 9));
+    assert_eq!(svdag.nodes, expected.nodes);
+
+    let bricks = SvdagStreamBuilder::new(4)
+        .dimensions((13, 5, 9))
+        .build_from_bricks(2, |origin, brick| {
+            for_each_position(&DensityVolume::new(2), |(x, y, z)| {
+                *brick.get_mut((x, y, z)) =
+                    *volume.get((origin.0 + x, origin.1 + y, origin.2 + z)) || origin.1 + y >= 5;
+            });
+            true
+        })
+        .unwrap();
+    assert_eq!(bricks.dimensions, (13, 5, 9));
+    assert_eq!(bricks.nodes, expected.nodes);
+
+    let full = SvdagStreamBuilder::new(4)
+        .dimensions((13, 5, 9))
+        .build_from_sdf(|_| -1.0)
+        .unwrap();
+    assert_padding_is_empty(&full);
+    assert_eq!(full.get((12, 4, 8)), Some(true));
+
+    assert!(SvdagStreamBuilder::new(2)
+        .dimensions((5, 1, 1))
+        .build_from_fn(|_| true)
+        .is_err());
+}
+
+#[test]
+fn keeps_the_dimensions_of_material_graphs() {
+    let mut volume = CubicVolume::<u8>::with_dimensions((3, 5, 7));
+    *volume.get_mut((2, 4, 6)) = 7;
+
+    let materials = MaterialSvdagBuilder::new().build(&volume).unwrap();
+    assert_eq!(materials.depth, 3);
+    assert_eq!(materials.get_dimensions(), (3, 5, 7));
+    assert_eq!(materials.dimensions(), (3, 5, 7));
+    assert_eq!(materials.get_material((2, 4, 6)), Some(7));
+    assert_eq!(materials.get_material((2, 4, 5)), Some(0));
+    assert_eq!(materials.get_material((3, 0, 0)), None);
+    assert_eq!(materials.get((0, 5, 0)), None);
+}
+
+#[test]
+fn looks_up_attributes_inside_the_dimensions() {
+    let mut volume = CubicVolume::<Option<u8>>::with_dimensions((3, 5, 7));
+    *volume.get_mut((2, 4, 6)) = Some(9);
+
+    let attributed = AttributedSvdag::from_volume(&volume).unwrap();
+    assert_eq!(attributed.get_attribute((2, 4, 6)), Some(&9));
+    assert_eq!(attributed.attribute_index((3, 4, 6)), None);
+    assert_eq!(attributed.attribute_index((2, 5, 6)), None);
+}
+
+#[test]
+fn keeps_pooled_edits_inside_the_dimensions() {
+    let volume = create_noise_volume((10, 3, 7), 10);
+    let mut pool = SvdagPool::new();
+
+    let handle = pool.insert_volume(&volume).unwrap();
+    assert_eq!(handle.dimensions, (10, 3, 7));
+    assert_eq!(pool.get(handle, (0, 3, 0)), None);
+    assert_eq!(handle, pool.insert_svdag(&Svdag::from(&volume)).unwrap());
+
+    match pool.set(handle, (0, 3, 0), true) {
+        Err(SvdagError::OutOfBounds { position }) => assert_eq!(position, (0, 3, 0)),
+        other => panic!("expected out of bounds, got {:?}", other),
+    }
+
+    let filled = pool
+        .fill(handle, &BoxBrush::new((5, 0, 0), (16, 16, 16)))
+        .unwrap();
+    let svdag = pool.to_svdag(filled, None).unwrap();
+    assert_eq!(svdag.dimensions, (10, 3, 7));
+    assert_eq!(svdag.get((9, 2, 6)), Some(true));
+    assert_padding_is_empty(&svdag);
+}
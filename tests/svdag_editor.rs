@@ -0,0 +1,263 @@
+mod common;
+
+use common::{assert_matches_volume, create_noise_volume, for_each_position, Random};
+use svdag::{
+    BoxBrush, Brush, DensityVolume, NodeOrder, PointerFormat, SphereBrush, Svdag, SvdagBrush,
+    SvdagBuilder, SvdagEditor, SvdagError, SvdagRead,
+};
+
+/// The graph a fresh build of the volume produces, edits must end up with exactly the same words.
+fn rebuild(volume: &DensityVolume) -> Svdag {
+    Svdag::from_fn(volume.depth, |position| *volume.get(position)).unwrap()
+}
+
+fn apply_to_volume(volume: &mut DensityVolume, brush: &impl Brush, value: bool) {
+    for_each_position(&DensityVolume::new(volume.depth), |position| {
+        if brush.contains(position) {
+            *volume.get_mut(position) = value;
+        }
+    });
+}
+
+#[test]
+fn sets_and_clears_single_voxels() {
+    let mut volume = create_noise_volume(4, 5, 3);
+    let mut svdag = Svdag::from(&volume);
+
+    let mut random = Random::new(9);
+    for _ in 0..50 {
+        let position = (
+            random.next_u64() as usize % 16,
+            random.next_u64() as usize % 16,
+            random.next_u64() as usize % 16,
+        );
+        let value = random.next_u64().is_multiple_of(2);
+
+        svdag.set(position, value).unwrap();
+        *volume.get_mut(position) = value;
+
+        assert_eq!(svdag.get(position), Some(value));
+    }
+
+    assert_matches_volume(&svdag, &volume);
+
+    //Compacting drops the replaced paths and merges the copies with their equals
+    svdag.compact(NodeOrder::DepthFirst).unwrap();
+    assert_eq!(svdag.nodes, rebuild(&volume).nodes);
+}
+
+#[test]
+fn clearing_every_voxel_leaves_an_empty_root() {
+    let mut svdag = Svdag::from_fn(2, |position| position == (1, 2, 3)).unwrap();
+
+    svdag.set((1, 2, 3), false).unwrap();
+
+    assert!(!svdag.nodes[0].node().children.have_occupied_children());
+    assert_eq!(svdag.get((1, 2, 3)), Some(false));
+
+    svdag.compact(NodeOrder::DepthFirst).unwrap();
+    assert_eq!(svdag.nodes.len(), 1);
+}
+
+#[test]
+fn appends_only_the_edited_path() {
+    let mut volume = create_noise_volume(5, 8, 4);
+    let mut svdag = Svdag::from(&volume);
+    let original = svdag.nodes.clone();
+    let pointer_word_count = svdag.pointer_format.word_count();
+
+    let position = (17, 30, 9);
+    let value = !*volume.get(position);
+    svdag.set(position, value).unwrap();
+    *volume.get_mut(position) = value;
+
+    //Everything but the root stays in place, the copied path is appended after it
+    let root_word_count = 1 + original[0].node().children.count_occupied() * pointer_word_count;
+    assert_eq!(
+        svdag.nodes[root_word_count..original.len()],
+        original[root_word_count..]
+    );
+    assert!(
+        svdag.nodes.len() - original.len()
+            <= (volume.depth as usize - 1) * (1 + 8 * pointer_word_count)
+    );
+    assert_matches_volume(&svdag, &volume);
+
+    let report = svdag.compact(NodeOrder::DepthFirst).unwrap();
+    assert!(report.bytes_reclaimed() > 0);
+    assert_eq!(svdag.nodes, rebuild(&volume).nodes);
+}
+
+#[test]
+fn rebuilds_when_the_root_outgrows_its_place() {
+    let mut svdag = Svdag::from_fn(3, |position| position == (0, 0, 0)).unwrap();
+    assert_eq!(svdag.nodes[0].node().children.count_occupied(), 1);
+
+    svdag.set((7, 7, 7), true).unwrap();
+
+    assert_eq!(svdag.get((0, 0, 0)), Some(true));
+    assert_eq!(svdag.get((7, 7, 7)), Some(true));
+    assert_eq!(
+        svdag.nodes,
+        Svdag::from_fn(3, |position| position == (0, 0, 0) || position == (7, 7, 7))
+            .unwrap()
+            .nodes
+    );
+}
+
+#[test]
+fn widens_relative_pointers_that_edits_outgrow() {
+    let mut volume = create_noise_volume(5, 31, 2);
+    let mut svdag = Svdag::from(&volume);
+    assert_eq!(svdag.pointer_format, PointerFormat::Relative16);
+
+    //Every edit appends a path pointing back to the start, until a relative pointer can't reach
+    let mut random = Random::new(3);
+    while svdag.pointer_format == PointerFormat::Relative16 {
+        let position = (
+            random.next_u64() as usize % 32,
+            random.next_u64() as usize % 32,
+            random.next_u64() as usize % 32,
+        );
+        let value = !*volume.get(position);
+
+        svdag.set(position, value).unwrap();
+        *volume.get_mut(position) = value;
+    }
+
+    assert_eq!(svdag.pointer_format, PointerFormat::Absolute32);
+    svdag.validate().unwrap();
+    assert_matches_volume(&svdag, &volume);
+}
+
+#[test]
+fn rejects_positions_outside_the_volume() {
+    let mut svdag = Svdag::from_fn(3, |_| true).unwrap();
+
+    match svdag.set((8, 0, 0), false) {
+        Err(SvdagError::OutOfBounds { position }) => assert_eq!(position, (8, 0, 0)),
+        result => panic!("expected OutOfBounds, got {:?}", result),
+    }
+}
+
+#[test]
+fn fills_and_carves_brushes() {
+    let mut volume = create_noise_volume(5, 21, 6);
+    let mut editor = SvdagEditor::from_svdag(&Svdag::from(&volume)).unwrap();
+
+    let sphere = SphereBrush::new([12.0, 14.5, 20.0], 9.3);
+    let carved_sphere = SphereBrush::new([20.0, 10.0, 8.0], 6.0);
+    let slab = BoxBrush::new((0, 0, 0), (32, 4, 32));
+    let overhang = BoxBrush::new((25, 20, 3), (40, 40, 9));
+
+    editor
+        .fill(&sphere)
+        .and_then(|editor| editor.carve(&carved_sphere))
+        .and_then(|editor| editor.fill(&slab))
+        .and_then(|editor| editor.carve(&overhang))
+        .unwrap();
+    apply_to_volume(&mut volume, &sphere, true);
+    apply_to_volume(&mut volume, &carved_sphere, false);
+    apply_to_volume(&mut volume, &slab, true);
+    apply_to_volume(&mut volume, &overhang, false);
+
+    for_each_position(&volume, |position| {
+        assert_eq!(editor.get(position), Some(*volume.get(position)));
+    });
+
+    let svdag = editor.to_svdag().unwrap();
+    assert_matches_volume(&svdag, &volume);
+    assert_eq!(svdag.nodes, rebuild(&volume).nodes);
+}
+
+#[test]
+fn stamps_other_graphs() {
+    let stamp = create_noise_volume(3, 2, 2);
+    let stamp_svdag = Svdag::from(&stamp);
+
+    for offset in [(0, 0, 0), (8, 16, 24), (3, 21, 30)].iter() {
+        let mut volume = create_noise_volume(5, 4, 9);
+        let mut svdag = Svdag::from(&volume);
+
+        let brush = SvdagBrush::new(&stamp_svdag, *offset);
+        svdag.fill(&brush).unwrap();
+        apply_to_volume(&mut volume, &brush, true);
+
+        assert_matches_volume(&svdag, &volume);
+        svdag.compact(NodeOrder::DepthFirst).unwrap();
+        assert_eq!(svdag.nodes, rebuild(&volume).nodes);
+
+        svdag.carve(&brush).unwrap();
+        apply_to_volume(&mut volume, &brush, false);
+
+        assert_matches_volume(&svdag, &volume);
+    }
+}
+
+#[test]
+fn copies_only_the_edited_path() {
+    let volume = create_noise_volume(6, 8, 4);
+    let mut editor = SvdagEditor::from_svdag(&Svdag::from(&volume)).unwrap();
+    let node_count = editor.table().len();
+
+    editor
+        .set((17, 40, 63), !*volume.get((17, 40, 63)))
+        .unwrap();
+
+    assert!(editor.table().len() <= node_count + volume.depth as usize);
+}
+
+#[test]
+fn collects_replaced_nodes() {
+    let mut volume = create_noise_volume(5, 14, 3);
+    let mut editor = SvdagEditor::from_svdag(&Svdag::from(&volume)).unwrap();
+
+    let mut random = Random::new(5);
+    for _ in 0..40 {
+        let position = (
+            random.next_u64() as usize % 32,
+            random.next_u64() as usize % 32,
+            random.next_u64() as usize % 32,
+        );
+        editor.set(position, true).unwrap();
+        *volume.get_mut(position) = true;
+    }
+
+    let node_count = editor.table().len();
+    let freed = editor.collect_garbage();
+    assert!(freed > 0);
+    assert_eq!(editor.table().len(), node_count - freed);
+
+    //Only the current state is left, exactly the nodes of a fresh build
+    let svdag = editor.to_svdag().unwrap();
+    assert_eq!(
+        editor.table().len(),
+        SvdagEditor::from_svdag(&svdag).unwrap().table().len()
+    );
+    assert_eq!(svdag.nodes, rebuild(&volume).nodes);
+    assert_eq!(editor.collect_garbage(), 0);
+
+    for_each_position(&volume, |position| {
+        assert_eq!(editor.get(position), Some(*volume.get(position)));
+    });
+}
+
+#[test]
+fn edits_symmetric_graphs() {
+    let mut volume = create_noise_volume(4, 12, 3);
+    let mut svdag = SvdagBuilder::new()
+        .symmetry(true)
+        .create_layers(&volume)
+        .create_graph()
+        .unwrap()
+        .finish();
+
+    let brush = SphereBrush::new([4.0, 4.0, 12.0], 5.0);
+    svdag.fill(&brush).unwrap();
+    svdag.set((15, 0, 7), true).unwrap();
+    apply_to_volume(&mut volume, &brush, true);
+    *volume.get_mut((15, 0, 7)) = true;
+
+    assert_eq!(svdag.pointer_format, PointerFormat::Mirrored32);
+    assert_matches_volume(&svdag, &volume);
+}
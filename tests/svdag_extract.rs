@@ -0,0 +1,90 @@
+mod common;
+
+use common::{create_noise_volume, create_sphere_volume, for_each_position};
+use svdag::{DensityVolume, IsVolume, Svdag};
+
+fn assert_same_volume(left: &DensityVolume, right: &DensityVolume) {
+    assert_eq!(left.get_dimensions(), right.get_dimensions());
+    for_each_position(left, |position| {
+        assert_eq!(left.get(position), right.get(position), "at {:?}", position);
+    });
+}
+
+#[test]
+fn decompresses_whole_volume() {
+    for depth in 1..=5 {
+        for volume in &[
+            create_sphere_volume(depth),
+            create_noise_volume(depth, 11, 3),
+            create_noise_volume(depth, 12, 1),
+        ] {
+            let svdag = Svdag::from(volume);
+
+            assert_same_volume(&DensityVolume::from(&svdag), volume);
+        }
+    }
+}
+
+#[test]
+fn round_trips_through_dense_edits() {
+    let volume = create_sphere_volume(4);
+    let mut decompressed = DensityVolume::from(&Svdag::from(&volume));
+    *decompressed.get_mut((0, 0, 0)) = true;
+
+    let edited = Svdag::from(&decompressed);
+
+    assert_eq!(edited.get((0, 0, 0)), Some(true));
+    assert_same_volume(&DensityVolume::from(&edited), &decompressed);
+}
+
+#[test]
+fn extracts_region_relative_to_its_minimum() {
+    let volume = create_noise_volume(5, 21, 2);
+    let svdag = Svdag::from(&volume);
+
+    let min = (3, 9, 17);
+    let max = (14, 16, 30);
+    let region = svdag.extract(min, max);
+
+    assert_eq!(region.get_dimensions(), (11, 7, 13));
+    assert_eq!(region.depth, 4);
+    for_each_position(&region, |(x, y, z)| {
+        assert_eq!(
+            region.get((x, y, z)),
+            volume.get((min.0 + x, min.1 + y, min.2 + z))
+        );
+    });
+}
+
+#[test]
+fn clips_region_to_the_volume() {
+    let volume = create_sphere_volume(3);
+    let svdag = Svdag::from(&volume);
+
+    let region = svdag.extract((4, 4, 4), (100, 100, 100));
+    assert_eq!(region.get_dimensions(), (4, 4, 4));
+    for_each_position(&region, |(x, y, z)| {
+        assert_eq!(region.get((x, y, z)), volume.get((x + 4, y + 4, z + 4)));
+    });
+
+    let empty = svdag.extract((5, 5, 5), (2, 2, 2));
+    assert_eq!(empty.get_dimensions(), (0, 0, 0));
+    assert!(!*empty.get((0, 0, 0)));
+}
+
+#[test]
+fn keeps_the_dimensions_of_non_cubic_volumes() {
+    let mut volume = DensityVolume::with_dimensions((13, 5, 9));
+    for_each_position(&DensityVolume::with_dimensions((13, 5, 9)), |(x, y, z)| {
+        *volume.get_mut((x, y, z)) = (x + y * z) % 3 == 0;
+    });
+
+    let decompressed = DensityVolume::from(&Svdag::from(&volume));
+    assert_eq!(decompressed.get_dimensions(), (13, 5, 9));
+    assert_same_volume(&decompressed, &volume);
+
+    //And back again
+    let svdag = Svdag::from(&decompressed);
+    assert_eq!(svdag.get_dimensions(), (13, 5, 9));
+    assert_eq!(svdag.nodes, Svdag::from(&volume).nodes);
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::{create_sphere_volume, serialize};
+use svdag::{svdag::SvdagValue, PointerFormat, Svdag, SvdagBuilder, SvdagError};
+
+#[test]
+fn round_trips_every_pointer_format() {
+    let volume = create_sphere_volume(5);
+
+    for pointer_format in &[PointerFormat::Relative16, PointerFormat::Absolute32] {
+        let svdag = SvdagBuilder::new()
+            .pointer_format(*pointer_format)
+            .create_layers(&volume)
+            .create_graph()
+            .unwrap()
+            .finish();
+
+        let bytes = serialize(&svdag);
+        assert_eq!(&bytes[0..4], b"SVDG");
+        assert_eq!(bytes.len(), 20 + svdag.nodes.len() * 2);
+
+        let loaded = Svdag::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.depth, svdag.depth);
+        assert_eq!(loaded.pointer_format, svdag.pointer_format);
+        assert_eq!(loaded.nodes, svdag.nodes);
+    }
+}
+
+#[test]
+fn rejects_invalid_magic() {
+    let mut bytes = serialize(&Svdag::from(&create_sphere_volume(3)));
+    bytes[0] = b'X';
+
+    match Svdag::read_from(bytes.as_slice()) {
+        Err(SvdagError::InvalidMagic(magic)) => assert_eq!(&magic, b"XVDG"),
+        other => panic!("expected invalid magic, got {:?}", other),
+    }
+}
+
+#[test]
+fn rejects_unsupported_version() {
+    let mut bytes = serialize(&Svdag::from(&create_sphere_volume(3)));
+    bytes[4] = 3;
+
+    match Svdag::read_from(bytes.as_slice()) {
+        Err(SvdagError::UnsupportedVersion(3)) => {}
+        other => panic!("expected unsupported version, got {:?}", other),
+    }
+}
+
+#[test]
+fn rejects_truncated_input() {
+    let bytes = serialize(&Svdag::from(&create_sphere_volume(3)));
+
+    match Svdag::read_from(&bytes[..10]) {
+        Err(SvdagError::Truncated { expected, actual }) => {
+            assert_eq!(expected, 20);
+            assert_eq!(actual, 10);
+        }
+        other => panic!("expected truncated header, got {:?}", other),
+    }
+
+    match Svdag::read_from(&bytes[..bytes.len() - 1]) {
+        Err(SvdagError::Truncated { expected, actual }) => {
+            assert_eq!(expected, bytes.len() as u64);
+            assert_eq!(actual, bytes.len() as u64 - 1);
+        }
+        other => panic!("expected truncated node words, got {:?}", other),
+    }
+
+    //A corrupt node count claiming nearly all of `u64` is reported, not overflowed
+    let mut corrupt = bytes.clone();
+    corrupt[8..16].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+
+    match Svdag::read_from(corrupt.as_slice()) {
+        Err(SvdagError::Truncated { expected, actual }) => {
+            assert_eq!(expected, u64::MAX);
+            assert_eq!(actual, bytes.len() as u64);
+        }
+        other => panic!("expected truncated node words, got {:?}", other),
+    }
+}
+
+#[test]
+fn rejects_corrupted_node_words() {
+    let mut bytes = serialize(&Svdag::from(&create_sphere_volume(3)));
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0x10;
+
+    match Svdag::read_from(bytes.as_slice()) {
+        Err(SvdagError::ChecksumMismatch { .. }) => {}
+        other => panic!("expected checksum mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn stores_the_standard_crc32() {
+    //The node words spell out `12345678` in little-endian
+    let svdag = Svdag {
+        nodes: [0x3231, 0x3433, 0x3635, 0x3837]
+            .iter()
+            .map(|bits| SvdagValue { bits: *bits })
+            .collect(),
+        ..Svdag::new()
+    };
+    let bytes = serialize(&svdag);
+
+    assert_eq!(&bytes[20..], b"12345678");
+    assert_eq!(bytes[16..20], 0x9ae0_daafu32.to_le_bytes());
+}
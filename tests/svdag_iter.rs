@@ -0,0 +1,161 @@
+mod common;
+
+use common::{create_noise_volume, create_sphere_volume, for_each_position};
+use svdag::{DensityVolume, PointerFormat, Svdag, SvdagBuilder, SvdagRead};
+
+fn morton_key(position: (usize, usize, usize), depth: u8) -> u64 {
+    (0..depth).rev().fold(0, |key, level| {
+        key << 3
+            | ((position.0 >> level & 1) << 2
+                | (position.1 >> level & 1) << 1
+                | (position.2 >> level & 1)) as u64
+    })
+}
+
+/// Occupied positions in `[min, max)` in Morton order, found by visiting every position.
+fn brute_force_occupied(
+    volume: &DensityVolume,
+    depth: u8,
+    min: (usize, usize, usize),
+    max: (usize, usize, usize),
+) -> Vec<(usize, usize, usize)> {
+    let mut positions = Vec::new();
+    for_each_position(volume, |position| {
+        let is_inside = position.0 >= min.0
+            && position.1 >= min.1
+            && position.2 >= min.2
+            && position.0 < max.0
+            && position.1 < max.1
+            && position.2 < max.2;
+        if is_inside && *volume.get(position) {
+            positions.push(position);
+        }
+    });
+
+    positions.sort_unstable_by_key(|position| morton_key(*position, depth));
+    positions
+}
+
+#[test]
+fn iterates_occupied_voxels_in_morton_order() {
+    let volume = create_noise_volume(5, 12, 3);
+    let svdag = Svdag::from(&volume);
+    let everything = (usize::MAX, usize::MAX, usize::MAX);
+
+    let occupied: Vec<_> = svdag.iter_occupied().collect();
+    assert_eq!(
+        occupied,
+        brute_force_occupied(&volume, 5, (0, 0, 0), everything)
+    );
+
+    //Mirrored pointers give the same voxels in the same order
+    let volume = create_sphere_volume(5);
+    let symmetric = SvdagBuilder::new()
+        .symmetry(true)
+        .create_layers(&volume)
+        .create_graph()
+        .unwrap()
+        .finish();
+    assert_eq!(symmetric.pointer_format, PointerFormat::Mirrored32);
+    assert_eq!(
+        symmetric.iter_occupied().collect::<Vec<_>>(),
+        brute_force_occupied(&volume, 5, (0, 0, 0), everything)
+    );
+
+    assert_eq!(
+        Svdag::from(&DensityVolume::new(3)).iter_occupied().count(),
+        0
+    );
+}
+
+#[test]
+fn restricts_to_regions() {
+    let volume = create_noise_volume(4, 7, 2);
+    let svdag = Svdag::from(&volume);
+
+    for (min, max) in [
+        ((0, 0, 0), (16, 16, 16)),
+        ((3, 5, 1), (11, 6, 9)),
+        ((7, 7, 7), (9, 9, 9)),
+        ((12, 0, 4), (40, 40, 40)),
+        ((5, 5, 5), (5, 9, 9)),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            svdag.iter_occupied_in(*min, *max).collect::<Vec<_>>(),
+            brute_force_occupied(&volume, 4, *min, *max),
+            "region from {:?} to {:?}",
+            min,
+            max
+        );
+    }
+
+    //Voxels past the dimensions are never yielded
+    let mut box_volume = DensityVolume::with_dimensions((5, 3, 7));
+    for_each_position(&DensityVolume::with_dimensions((5, 3, 7)), |position| {
+        *box_volume.get_mut(position) = true;
+    });
+    assert_eq!(Svdag::from(&box_volume).iter_occupied().count(), 5 * 3 * 7);
+}
+
+#[test]
+fn iterates_nodes_at_a_level() {
+    let volume = create_noise_volume(4, 3, 6);
+    let svdag = Svdag::from(&volume);
+
+    assert_eq!(
+        svdag.iter_nodes_at_level(0).collect::<Vec<_>>(),
+        vec![((0, 0, 0), 16, 0)]
+    );
+    assert_eq!(svdag.iter_nodes_at_level(4).count(), 0);
+
+    for level in 1..4u8 {
+        let side_size = 16 >> level;
+        let nodes: Vec<_> = svdag.iter_nodes_at_level(level).collect();
+
+        //Exactly the octants holding an occupied voxel, each with its voxels
+        let mut expected: Vec<_> = svdag
+            .iter_occupied()
+            .map(|position| {
+                (
+                    position.0 / side_size * side_size,
+                    position.1 / side_size * side_size,
+                    position.2 / side_size * side_size,
+                )
+            })
+            .collect();
+        expected.dedup();
+        assert_eq!(
+            nodes
+                .iter()
+                .map(|(origin, _, _)| *origin)
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        for (origin, size, node_index) in nodes.iter() {
+            assert_eq!(*size, side_size);
+            assert!(svdag.nodes[*node_index]
+                .node()
+                .children
+                .have_occupied_children());
+
+            let end = (origin.0 + size, origin.1 + size, origin.2 + size);
+            assert!(svdag.iter_occupied_in(*origin, end).next().is_some());
+        }
+    }
+
+    //Only octants overlapping the region are visited
+    let overlapping: Vec<_> = svdag
+        .iter_nodes_at_level(2)
+        .filter(|(origin, _, _)| origin.0 + 4 > 3 && origin.0 < 5 && origin.1 < 4 && origin.2 < 4)
+        .collect();
+    assert!(!overlapping.is_empty());
+    assert_eq!(
+        svdag
+            .iter_nodes_at_level_in(2, (3, 0, 0), (5, 4, 4))
+            .collect::<Vec<_>>(),
+        overlapping
+    );
+}
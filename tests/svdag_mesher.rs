@@ -0,0 +1,247 @@
+mod common;
+
+use common::{create_noise_volume, create_sphere_volume, for_each_position};
+use std::collections::HashMap;
+use svdag::{
+    DensityVolume, IsVolume, PointCloud, PointerFormat, Svdag, SvdagBuilder, SvdagMesher,
+    TriangleMesh,
+};
+
+/// Number of voxel faces between an occupied voxel and an empty or outside one.
+fn count_exposed_faces(volume: &DensityVolume) -> usize {
+    let dimensions = volume.get_dimensions();
+    let mut face_count = 0;
+
+    for_each_position(volume, |(x, y, z)| {
+        if !*volume.get((x, y, z)) {
+            return;
+        }
+
+        let neighbours = [
+            (x.wrapping_sub(1), y, z),
+            (x + 1, y, z),
+            (x, y.wrapping_sub(1), z),
+            (x, y + 1, z),
+            (x, y, z.wrapping_sub(1)),
+            (x, y, z + 1),
+        ];
+        face_count += neighbours
+            .iter()
+            .filter(|(x, y, z)| {
+                *x >= dimensions.0
+                    || *y >= dimensions.1
+                    || *z >= dimensions.2
+                    || !*volume.get((*x, *y, *z))
+            })
+            .count();
+    });
+
+    face_count
+}
+
+/// Whether every edge is used as often in one direction as in the other, so the surface is
+/// closed and consistently oriented.
+fn assert_closed(mesh: &TriangleMesh) {
+    let mut edges: HashMap<(usize, usize), i64> = HashMap::new();
+    for triangle in mesh.triangles.iter() {
+        for corner in 0..3 {
+            let (from, to) = (triangle[corner], triangle[(corner + 1) % 3]);
+            *edges.entry((from.min(to), from.max(to))).or_default() +=
+                if from < to { 1 } else { -1 };
+        }
+    }
+
+    assert!(edges.values().all(|balance| *balance == 0));
+}
+
+/// Enclosed volume by the divergence theorem, positive for outward facing triangles.
+fn signed_volume(mesh: &TriangleMesh) -> f32 {
+    (0..mesh.triangles.len())
+        .map(|index| {
+            let [a, b, c] = mesh.triangle(index);
+            (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                + a[2] * (b[0] * c[1] - b[1] * c[0]))
+                / 6.0
+        })
+        .sum()
+}
+
+fn area(mesh: &TriangleMesh) -> f32 {
+    (0..mesh.triangles.len())
+        .map(|index| {
+            let [a, b, c] = mesh.triangle(index);
+            let (e0, e1) = (
+                [b[0] - a[0], b[1] - a[1], b[2] - a[2]],
+                [c[0] - a[0], c[1] - a[1], c[2] - a[2]],
+            );
+            let cross = [
+                e0[1] * e1[2] - e0[2] * e1[1],
+                e0[2] * e1[0] - e0[0] * e1[2],
+                e0[0] * e1[1] - e0[1] * e1[0],
+            ];
+            (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt() / 2.0
+        })
+        .sum()
+}
+
+fn occupied_count(volume: &DensityVolume) -> usize {
+    let mut count = 0;
+    for_each_position(volume, |position| count += *volume.get(position) as usize);
+    count
+}
+
+#[test]
+fn meshes_voxel_faces() {
+    let volume = create_noise_volume(4, 23, 3);
+    let svdag = Svdag::from(&volume);
+    let face_count = count_exposed_faces(&volume);
+
+    let faces = SvdagMesher::new().voxel_faces(&svdag).unwrap();
+    assert_eq!(faces.triangles.len(), 2 * face_count);
+    assert_closed(&faces);
+    assert_eq!(
+        signed_volume(&faces).round() as usize,
+        occupied_count(&volume)
+    );
+
+    //Merging keeps the surface but needs fewer triangles
+    let greedy = SvdagMesher::new().greedy(true).voxel_faces(&svdag).unwrap();
+    assert!(greedy.triangles.len() < faces.triangles.len());
+    assert_eq!(area(&greedy), face_count as f32);
+    assert_eq!(
+        signed_volume(&greedy).round() as usize,
+        occupied_count(&volume)
+    );
+}
+
+#[test]
+fn merges_full_octants() {
+    let volume = create_sphere_volume(5);
+    let svdag = Svdag::from(&volume);
+
+    let faces = SvdagMesher::new()
+        .placement([10.0, 0.0, -2.0], 0.5)
+        .voxel_faces(&svdag)
+        .unwrap();
+    assert_eq!(faces.triangles.len(), 2 * count_exposed_faces(&volume));
+    assert_closed(&faces);
+    assert!((signed_volume(&faces) - occupied_count(&volume) as f32 / 8.0).abs() < 0.01);
+
+    //A full box of any size is a single quad per side
+    let mut box_volume = DensityVolume::with_dimensions((5, 3, 2));
+    for_each_position(&DensityVolume::with_dimensions((5, 3, 2)), |position| {
+        *box_volume.get_mut(position) = true;
+    });
+    let greedy = SvdagMesher::new()
+        .greedy(true)
+        .voxel_faces(&Svdag::from(&box_volume))
+        .unwrap();
+    assert_eq!(greedy.triangles.len(), 12);
+    assert_eq!(greedy.bounds(), Some(([0.0; 3], [5.0, 3.0, 2.0])));
+    assert_eq!(signed_volume(&greedy), 30.0);
+}
+
+#[test]
+fn walks_mirrored_graphs() {
+    let volume = create_sphere_volume(4);
+    let plain = Svdag::from(&volume);
+    let symmetric = SvdagBuilder::new()
+        .symmetry(true)
+        .create_layers(&volume)
+        .create_graph()
+        .unwrap()
+        .finish();
+    assert_eq!(symmetric.pointer_format, PointerFormat::Mirrored32);
+
+    let mesher = SvdagMesher::new();
+    assert_eq!(
+        mesher.voxel_faces(&symmetric).unwrap(),
+        mesher.voxel_faces(&plain).unwrap()
+    );
+    assert_eq!(
+        mesher.marching_cubes(&symmetric).unwrap(),
+        mesher.marching_cubes(&plain).unwrap()
+    );
+}
+
+#[test]
+fn marches_cubes() {
+    let mut single = DensityVolume::new(2);
+    *single.get_mut((1, 2, 1)) = true;
+
+    //A lone voxel becomes an octahedron around its center
+    let octahedron = SvdagMesher::new()
+        .marching_cubes(&Svdag::from(&single))
+        .unwrap();
+    assert_eq!(octahedron.triangles.len(), 8);
+    assert_eq!(octahedron.vertices.len(), 6);
+    assert_eq!(
+        octahedron.bounds(),
+        Some(([1.0, 2.0, 1.0], [2.0, 3.0, 2.0]))
+    );
+    assert_closed(&octahedron);
+    assert!((signed_volume(&octahedron) - 1.0 / 6.0).abs() < 1e-6);
+
+    let volume = create_sphere_volume(5);
+    let sphere = SvdagMesher::new()
+        .marching_cubes(&Svdag::from(&volume))
+        .unwrap();
+    assert_closed(&sphere);
+
+    //The smooth surface cuts the corners off the voxels
+    let enclosed = signed_volume(&sphere);
+    let voxel_count = occupied_count(&volume) as f32;
+    assert!(enclosed < voxel_count && enclosed > 0.8 * voxel_count);
+
+    let noise = create_noise_volume(4, 5, 2);
+    assert_closed(
+        &SvdagMesher::new()
+            .marching_cubes(&Svdag::from(&noise))
+            .unwrap(),
+    );
+
+    //Full octants are clipped to the dimensions, the surface still closes at their boundary
+    let mut box_volume = DensityVolume::with_dimensions((5, 3, 2));
+    for_each_position(&DensityVolume::with_dimensions((5, 3, 2)), |position| {
+        *box_volume.get_mut(position) = true;
+    });
+    let rounded_box = SvdagMesher::new()
+        .marching_cubes(&Svdag::from(&box_volume))
+        .unwrap();
+    assert_closed(&rounded_box);
+    assert_eq!(rounded_box.bounds(), Some(([0.0; 3], [5.0, 3.0, 2.0])));
+    assert!(signed_volume(&rounded_box) < 30.0);
+}
+
+#[test]
+fn writes_obj_and_ply() {
+    let mesh = SvdagMesher::new()
+        .greedy(true)
+        .voxel_faces(&Svdag::from(&create_noise_volume(3, 9, 4)))
+        .unwrap();
+
+    let mut obj = Vec::new();
+    mesh.write_obj(&mut obj).unwrap();
+    assert_eq!(TriangleMesh::read_obj(obj.as_slice()).unwrap(), mesh);
+
+    let mut ply = Vec::new();
+    mesh.write_ply(&mut ply).unwrap();
+    let header_end = ply
+        .windows(11)
+        .position(|bytes| bytes == b"end_header\n")
+        .unwrap();
+    let header = String::from_utf8_lossy(&ply[..header_end]);
+    assert!(header.contains(&format!("element face {}", mesh.triangles.len())));
+    assert_eq!(
+        ply.len(),
+        header_end + 11 + mesh.vertices.len() * 12 + mesh.triangles.len() * 13
+    );
+
+    let points = PointCloud::read_ply(ply.as_slice()).unwrap();
+    let vertices: Vec<[f64; 3]> = mesh
+        .vertices
+        .iter()
+        .map(|vertex| [vertex[0] as f64, vertex[1] as f64, vertex[2] as f64])
+        .collect();
+    assert_eq!(points.positions, vertices);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{create_noise_volume, create_sphere_volume, for_each_position};
+use svdag::{
+    DensityVolume, Face, FaceMask, IsVolume, PointerFormat, Svdag, SvdagBuilder, SvdagRead,
+};
+
+/// Occupied neighbours of `position` looked up one by one, neighbours outside count as empty.
+fn brute_force_neighbors(volume: &DensityVolume, position: (usize, usize, usize)) -> FaceMask {
+    let dimensions = volume.get_dimensions();
+    let mut mask = FaceMask::default();
+
+    for face in Face::ALL.iter() {
+        let is_occupied = face.neighbor(position).is_some_and(|(x, y, z)| {
+            x < dimensions.0 && y < dimensions.1 && z < dimensions.2 && *volume.get((x, y, z))
+        });
+        mask.set(*face, is_occupied);
+    }
+
+    mask
+}
+
+fn brute_force_surface(volume: &DensityVolume) -> Vec<((usize, usize, usize), FaceMask)> {
+    let mut surface = Vec::new();
+    for_each_position(volume, |position| {
+        let exposed_faces = brute_force_neighbors(volume, position).inverted();
+        if *volume.get(position) && !exposed_faces.is_empty() {
+            surface.push((position, exposed_faces));
+        }
+    });
+
+    surface.sort_unstable_by_key(|(position, _)| *position);
+    surface
+}
+
+fn assert_matches_brute_force(svdag: &impl SvdagRead, volume: &DensityVolume) {
+    for_each_position(volume, |position| {
+        assert_eq!(
+            svdag.neighbors(position),
+            Some(brute_force_neighbors(volume, position)),
+            "neighbours of {:?}",
+            position
+        );
+    });
+
+    let mut surface: Vec<_> = svdag.surface_voxels().collect();
+    surface.sort_unstable_by_key(|(position, _)| *position);
+    assert_eq!(surface, brute_force_surface(volume));
+}
+
+#[test]
+fn masks_faces() {
+    let mut mask = FaceMask::new(0xff);
+    assert_eq!(mask.count(), 6);
+
+    mask.set(Face::PositiveY, false);
+    assert!(!mask.get(Face::PositiveY));
+    assert!(mask.get(Face::NegativeY));
+    assert_eq!(mask.inverted(), FaceMask::new(1 << Face::PositiveY as u8));
+
+    assert_eq!(Face::PositiveZ.axis(), 2);
+    assert!(!Face::NegativeX.is_positive());
+    assert_eq!(Face::NegativeX.neighbor((0, 1, 1)), None);
+    assert_eq!(Face::PositiveY.neighbor((0, 1, 1)), Some((0, 2, 1)));
+}
+
+#[test]
+fn finds_neighbors() {
+    for (depth, seed) in [(3, 24), (4, 5), (5, 11)].iter() {
+        let volume = create_noise_volume(*depth, *seed, 2);
+        assert_matches_brute_force(&Svdag::from(&volume), &volume);
+    }
+
+    let volume = create_sphere_volume(5);
+    let svdag = Svdag::from(&volume);
+    assert_matches_brute_force(&svdag, &volume);
+    assert_eq!(svdag.neighbors((32, 0, 0)), None);
+}
+
+#[test]
+fn follows_mirrored_pointers() {
+    let volume = create_sphere_volume(4);
+    let symmetric = SvdagBuilder::new()
+        .symmetry(true)
+        .create_layers(&volume)
+        .create_graph()
+        .unwrap()
+        .finish();
+    assert_eq!(symmetric.pointer_format, PointerFormat::Mirrored32);
+
+    assert_matches_brute_force(&symmetric, &volume);
+}
+
+#[test]
+fn stops_at_the_dimensions() {
+    let mut volume = DensityVolume::with_dimensions((5, 3, 7));
+    for_each_position(&DensityVolume::with_dimensions((5, 3, 7)), |position| {
+        *volume.get_mut(position) = (position.0 + position.2) % 4 != 1;
+    });
+
+    let svdag = Svdag::from(&volume);
+    assert_eq!(svdag.get_dimensions(), (5, 3, 7));
+    assert_matches_brute_force(&svdag, &volume);
+    assert_eq!(svdag.neighbors((0, 3, 0)), None);
+}
+
+#[test]
+fn skips_buried_octants() {
+    //Only the shell of a full box is exposed, whatever the size of its full octants
+    let mut volume = DensityVolume::new(5);
+    for_each_position(&DensityVolume::new(5), |position| {
+        *volume.get_mut(position) = position.0 >= 3 && position.1 < 30 && position.2 >= 1;
+    });
+
+    let svdag = Svdag::from(&volume);
+    assert_matches_brute_force(&svdag, &volume);
+
+    let shell = svdag.surface_voxels().count();
+    assert_eq!(shell, 29 * 30 * 31 - 27 * 28 * 29);
+
+    //A solid cube only has its outermost voxels exposed
+    let mut solid = DensityVolume::new(4);
+    for_each_position(&DensityVolume::new(4), |position| {
+        *solid.get_mut(position) = true;
+    });
+    let corners = Svdag::from(&solid)
+        .surface_voxels()
+        .filter(|(_, faces)| faces.count() == 3)
+        .count();
+    assert_eq!(corners, 8);
+}
@@ -0,0 +1,238 @@
+mod common;
+
+use common::{assert_matches_volume, Random};
+use svdag::{DensityVolume, IsVolume, PointCloud, PointCloudImporter, PointerFormat, SvdagError};
+
+fn create_random_cloud(count: usize, seed: u64) -> PointCloud {
+    let mut random = Random::new(seed);
+    let mut point_cloud = PointCloud::new();
+
+    for _ in 0..count {
+        point_cloud.positions.push([
+            1000.0 + random.next_f32() as f64 * 8.0,
+            -20.0 + random.next_f32() as f64 * 4.0,
+            random.next_f32() as f64 * 2.0,
+        ]);
+        point_cloud
+            .colors
+            .push([random.next_u64() as u8, random.next_u64() as u8, 7]);
+    }
+
+    point_cloud
+}
+
+fn invalid_point_cloud_message(result: Result<PointCloud, SvdagError>) -> String {
+    match result {
+        Err(SvdagError::InvalidPointCloud(message)) => message,
+        other => panic!("expected invalid point cloud, got {:?}", other),
+    }
+}
+
+#[test]
+fn reads_xyz_files() {
+    let xyz = "X,Y,Z,Red,Green,Blue,Intensity\n\
+               # exported scan\n\
+               1.5,2,-3,255,0,10,0.5\n\
+               \n\
+               4 5 6 1 2 3 0.25\n";
+
+    let point_cloud = PointCloud::read_xyz(xyz.as_bytes()).unwrap();
+    assert_eq!(
+        point_cloud.positions,
+        vec![[1.5, 2.0, -3.0], [4.0, 5.0, 6.0]]
+    );
+    assert_eq!(point_cloud.colors, vec![[255, 0, 10], [1, 2, 3]]);
+    assert_eq!(
+        point_cloud.bounds(),
+        Some(([1.5, 2.0, -3.0], [4.0, 5.0, 6.0]))
+    );
+
+    let plain = PointCloud::read_xyz("0 0 0\n1 1 1\n".as_bytes()).unwrap();
+    assert_eq!(plain.len(), 2);
+    assert!(!plain.has_colors());
+
+    for (xyz, reason) in [
+        ("0 0 0\n1 1 1 5 5 5\n", "line 2: either every point or none"),
+        ("0 0 0 256 0 0\n", "line 1: colors have to be integers"),
+        ("0 0\n", "line 1: expected at least three"),
+        ("x y z\n0 0 0\ny z x\n", "line 3: expected numeric columns"),
+    ]
+    .iter()
+    {
+        let message = invalid_point_cloud_message(PointCloud::read_xyz(xyz.as_bytes()));
+        assert!(
+            message.contains(reason),
+            "{:?} should mention {:?}",
+            message,
+            reason
+        );
+    }
+}
+
+#[test]
+fn reads_ascii_and_binary_ply() {
+    let ascii = "ply\n\
+                 format ascii 1.0\n\
+                 comment scanned\n\
+                 element vertex 2\n\
+                 property float x\n\
+                 property float y\n\
+                 property float z\n\
+                 property float intensity\n\
+                 property uchar red\n\
+                 property uchar green\n\
+                 property uchar blue\n\
+                 element face 1\n\
+                 property list uchar int vertex_indices\n\
+                 end_header\n\
+                 0.5 1 2 0.1 10 20 30\n\
+                 3 4 5 0.2 40 50 60\n\
+                 3 0 1 0\n";
+
+    let expected = PointCloud {
+        positions: vec![[0.5, 1.0, 2.0], [3.0, 4.0, 5.0]],
+        colors: vec![[10, 20, 30], [40, 50, 60]],
+    };
+    assert_eq!(PointCloud::read_ply(ascii.as_bytes()).unwrap(), expected);
+
+    //A list element before the vertices has to be skipped, 16 bit colors are scaled down
+    let mut binary = b"ply\n\
+                       format binary_big_endian 1.0\n\
+                       element camera 1\n\
+                       property list uchar float view\n\
+                       element vertex 2\n\
+                       property double x\n\
+                       property double y\n\
+                       property double z\n\
+                       property ushort red\n\
+                       property ushort green\n\
+                       property ushort blue\n\
+                       end_header\n"
+        .to_vec();
+    binary.push(2);
+    binary.extend_from_slice(&1.0f32.to_be_bytes());
+    binary.extend_from_slice(&2.0f32.to_be_bytes());
+    for (position, color) in expected.positions.iter().zip(expected.colors.iter()) {
+        for coordinate in position.iter() {
+            binary.extend_from_slice(&coordinate.to_be_bytes());
+        }
+        for channel in color.iter() {
+            binary.extend_from_slice(&(*channel as u16 * 257).to_be_bytes());
+        }
+    }
+    assert_eq!(PointCloud::read_ply(binary.as_slice()).unwrap(), expected);
+
+    binary.pop();
+    let message = invalid_point_cloud_message(PointCloud::read_ply(binary.as_slice()));
+    assert!(message.contains("body ends"), "{:?}", message);
+
+    let without_z =
+        "ply\nformat ascii 1.0\nelement vertex 0\nproperty float x\nproperty float y\nend_header\n";
+    let message = invalid_point_cloud_message(PointCloud::read_ply(without_z.as_bytes()));
+    assert!(message.contains("no `z` property"), "{:?}", message);
+}
+
+#[test]
+fn bins_points_into_voxels() {
+    let point_cloud = create_random_cloud(500, 22);
+    let svdag = PointCloudImporter::new(4)
+        .bounds([1000.0, -20.0, 0.0], [1008.0, -16.0, 2.0])
+        .pointer_format(PointerFormat::Absolute32)
+        .build(&point_cloud)
+        .unwrap();
+
+    //The longest side spans the 16 voxels, the others only as far as the box goes
+    assert_eq!(svdag.pointer_format, PointerFormat::Absolute32);
+    assert_eq!(svdag.get_dimensions(), (16, 8, 4));
+    assert_eq!(svdag.get((0, 8, 0)), None);
+
+    let mut volume = DensityVolume::with_dimensions(svdag.get_dimensions());
+    for position in point_cloud.positions.iter() {
+        let voxel = |coordinate: f64, min: f64| ((coordinate - min) / 0.5) as usize;
+        *volume.get_mut((
+            voxel(position[0], 1000.0),
+            voxel(position[1], -20.0),
+            voxel(position[2], 0.0),
+        )) = true;
+    }
+
+    assert_matches_volume(&svdag, &volume);
+
+    //Fitting to the points gives the same voxels once they span the box
+    let mut fitted_cloud = point_cloud.clone();
+    fitted_cloud.positions.push([1000.0, -20.0, 0.0]);
+    fitted_cloud.positions.push([1008.0, -16.0, 2.0]);
+    *volume.get_mut((0, 0, 0)) = true;
+    *volume.get_mut((15, 7, 3)) = true;
+
+    let fitted = PointCloudImporter::new(4).build(&fitted_cloud).unwrap();
+    assert_eq!(fitted.get_dimensions(), (16, 8, 4));
+    assert_matches_volume(&fitted, &volume);
+}
+
+#[test]
+fn filters_sparse_voxels_and_averages_colors() {
+    let mut point_cloud = PointCloud::new();
+    let points = [
+        ([0.2, 0.2, 0.2], [10, 0, 255]),
+        ([0.7, 0.9, 0.1], [20, 1, 255]),
+        ([0.5, 0.5, 0.5], [31, 2, 255]),
+        ([3.5, 3.5, 3.5], [100, 100, 100]),
+        ([3.9, 3.1, 3.2], [200, 200, 200]),
+        //A stray point in its own voxel
+        ([2.5, 0.5, 1.5], [1, 1, 1]),
+        //Outside of the bounds
+        ([4.5, 0.5, 0.5], [1, 1, 1]),
+    ];
+    for (position, color) in points.iter() {
+        point_cloud.positions.push(*position);
+        point_cloud.colors.push(*color);
+    }
+
+    let colored = PointCloudImporter::new(2)
+        .bounds([0.0; 3], [4.0; 3])
+        .min_points(2)
+        .build_colored(&point_cloud)
+        .unwrap();
+
+    assert_eq!(colored.geometry.get_dimensions(), (4, 4, 4));
+    assert_eq!(colored.attributes.len(), 2);
+    assert_eq!(colored.get_attribute((0, 0, 0)), Some(&[20, 1, 255]));
+    assert_eq!(colored.get_attribute((3, 3, 3)), Some(&[150, 150, 150]));
+    assert_eq!(colored.get((2, 0, 1)), Some(false));
+
+    let all_points = PointCloudImporter::new(2)
+        .bounds([0.0; 3], [4.0; 3])
+        .build(&point_cloud)
+        .unwrap();
+    assert_eq!(all_points.get((2, 0, 1)), Some(true));
+}
+
+#[test]
+fn rejects_invalid_imports() {
+    let point_cloud = create_random_cloud(10, 3);
+
+    assert!(matches!(
+        PointCloudImporter::new(0).build(&point_cloud),
+        Err(SvdagError::InvalidDepth(0))
+    ));
+    assert!(matches!(
+        PointCloudImporter::new(3)
+            .bounds([0.0, 1.0, 0.0], [1.0, 0.0, 1.0])
+            .build(&point_cloud),
+        Err(SvdagError::InvalidPointCloud(_))
+    ));
+
+    let mut uncolored = point_cloud.clone();
+    uncolored.colors.clear();
+    assert!(matches!(
+        PointCloudImporter::new(3).build_colored(&uncolored),
+        Err(SvdagError::InvalidPointCloud(_))
+    ));
+
+    //An empty cloud is still a valid graph
+    let empty = PointCloudImporter::new(3)
+        .build(&PointCloud::new())
+        .unwrap();
+    assert_eq!(empty.get((1, 2, 3)), Some(false));
+}
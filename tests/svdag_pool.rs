@@ -0,0 +1,140 @@
+mod common;
+
+use common::{assert_matches_volume, create_noise_volume, for_each_position};
+use svdag::{DensityVolume, Svdag, SvdagPool};
+
+/// Terrain chunk: ground up to a height that depends on the chunk, with a prop standing on it.
+fn create_chunk(depth: u8, ground_height: usize, prop: &DensityVolume) -> DensityVolume {
+    let mut volume = DensityVolume::new(depth);
+    let prop_side = 1 << prop.depth;
+
+    for_each_position(&DensityVolume::new(depth), |(x, y, z)| {
+        let is_ground = y < ground_height;
+        let is_prop = y >= ground_height
+            && x < prop_side
+            && y - ground_height < prop_side
+            && z < prop_side
+            && *prop.get((x, y - ground_height, z));
+
+        *volume.get_mut((x, y, z)) = is_ground || is_prop;
+    });
+
+    volume
+}
+
+#[test]
+fn shares_subtrees_across_graphs() {
+    let prop = create_noise_volume(3, 5, 2);
+    let chunks: Vec<DensityVolume> = [8, 16, 8, 24, 0, 32]
+        .iter()
+        .map(|ground_height| create_chunk(5, *ground_height, &prop))
+        .collect();
+
+    let mut pool = SvdagPool::new();
+    let handles: Vec<_> = chunks
+        .iter()
+        .map(|chunk| pool.insert_volume(chunk).unwrap())
+        .collect();
+
+    //The same chunk twice is the same graph
+    assert_eq!(handles[0], handles[2]);
+
+    let separate_node_count: usize = chunks
+        .iter()
+        .map(|chunk| {
+            let mut chunk_pool = SvdagPool::new();
+            chunk_pool.insert_volume(chunk).unwrap();
+            chunk_pool.node_count()
+        })
+        .sum();
+    assert!(
+        pool.node_count() * 2 < separate_node_count,
+        "{} nodes shared, {} separately",
+        pool.node_count(),
+        separate_node_count
+    );
+
+    for (chunk, handle) in chunks.iter().zip(handles.iter()) {
+        for_each_position(chunk, |position| {
+            assert_eq!(pool.get(*handle, position), Some(*chunk.get(position)));
+        });
+        assert_eq!(pool.get(*handle, (32, 0, 0)), None);
+    }
+}
+
+#[test]
+fn flattens_graphs_like_a_standalone_build() {
+    let volume = create_noise_volume(4, 9, 3);
+    let mut pool = SvdagPool::new();
+    pool.insert_fn(4, |(x, y, z)| x == y || y == z).unwrap();
+
+    let handle = pool.insert_volume(&volume).unwrap();
+    let svdag = pool.to_svdag(handle, None).unwrap();
+
+    assert_matches_volume(&svdag, &volume);
+    assert_eq!(svdag.nodes, Svdag::from(&volume).nodes);
+}
+
+#[test]
+fn imports_existing_graphs() {
+    let volume = create_noise_volume(4, 13, 2);
+    let mut pool = SvdagPool::new();
+
+    let built = pool.insert_volume(&volume).unwrap();
+    let node_count = pool.node_count();
+    let imported = pool.insert_svdag(&Svdag::from(&volume)).unwrap();
+
+    assert_eq!(built, imported);
+    assert_eq!(pool.node_count(), node_count);
+}
+
+#[test]
+fn shares_empty_and_solid_graphs() {
+    let mut pool = SvdagPool::new();
+
+    let empty = pool.insert_fn(6, |_| false).unwrap();
+    let solid = pool.insert_fn(6, |_| true).unwrap();
+    let node_count = pool.node_count();
+
+    assert!(pool.is_empty_graph(empty));
+    assert!(!pool.is_empty_graph(solid));
+    assert_eq!(pool.insert_fn(6, |_| false).unwrap(), empty);
+    assert_eq!(pool.insert_fn(6, |_| true).unwrap(), solid);
+    assert_eq!(pool.node_count(), node_count);
+
+    //A single level of nodes per height for the solid graph plus the empty root
+    assert_eq!(node_count, 7);
+}
+
+#[test]
+fn collects_graphs_no_longer_needed() {
+    let first = create_noise_volume(4, 17, 2);
+    let mut second = create_noise_volume(4, 18, 3);
+
+    let mut pool = SvdagPool::new();
+    let first_handle = pool.insert_volume(&first).unwrap();
+    let mut second_handle = pool.insert_volume(&second).unwrap();
+
+    for x in 0..16 {
+        second_handle = pool.set(second_handle, (x, 7, 3), true).unwrap();
+        *second.get_mut((x, 7, 3)) = true;
+    }
+
+    let mut handles = [first_handle, second_handle];
+    let node_count = pool.node_count();
+    let freed = pool.collect_garbage(&mut handles);
+    assert!(freed > 0);
+    assert_eq!(pool.node_count(), node_count - freed);
+
+    //What's left is exactly what storing the two graphs takes
+    let mut fresh_pool = SvdagPool::new();
+    fresh_pool.insert_volume(&first).unwrap();
+    fresh_pool.insert_volume(&second).unwrap();
+    assert_eq!(pool.node_count(), fresh_pool.node_count());
+
+    for (volume, handle) in [&first, &second].iter().zip(handles.iter()) {
+        for_each_position(*volume, |position| {
+            assert_eq!(pool.get(*handle, position), Some(*volume.get(position)));
+        });
+    }
+}
@@ -0,0 +1,222 @@
+mod common;
+
+use common::{create_noise_volume, create_sphere_volume, Random};
+use svdag::{DensityVolume, IsVolume, Svdag, SvdagHit, SvdagRead, SvdagRef};
+
+/// Plain voxel-by-voxel DDA over the dense volume, following the same conventions as the graph.
+fn raycast_dense(
+    volume: &DensityVolume,
+    origin: [f32; 3],
+    direction: [f32; 3],
+    max_t: f32,
+) -> Option<SvdagHit> {
+    let side_size = volume.get_dimensions().0;
+
+    let mut t = 0.0f32;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = [0; 3];
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if origin[axis] < 0.0 || origin[axis] >= side_size as f32 {
+                return None;
+            }
+            continue;
+        }
+
+        let t0 = -origin[axis] / direction[axis];
+        let t1 = (side_size as f32 - origin[axis]) / direction[axis];
+        if t0.min(t1) > t {
+            t = t0.min(t1);
+            normal = [0; 3];
+            normal[axis] = -direction[axis].signum() as i32;
+        }
+        t_exit = t_exit.min(t0.max(t1));
+    }
+
+    if t > t_exit || t > max_t {
+        return None;
+    }
+
+    let mut position = [0isize; 3];
+    for axis in 0..3 {
+        position[axis] = if normal[axis] > 0 {
+            side_size as isize - 1
+        } else if normal[axis] < 0 {
+            0
+        } else {
+            ((origin[axis] + direction[axis] * t).floor() as isize).clamp(0, side_size as isize - 1)
+        };
+    }
+
+    let mut steps = 0;
+    loop {
+        steps += 1;
+
+        let voxel = (
+            position[0] as usize,
+            position[1] as usize,
+            position[2] as usize,
+        );
+        if *volume.get(voxel) {
+            return Some(SvdagHit {
+                position: voxel,
+                distance: t,
+                normal,
+                steps,
+            });
+        }
+
+        let mut exit_axis = 0;
+        let mut t_voxel_exit = f32::INFINITY;
+        for axis in 0..3 {
+            let boundary = if direction[axis] > 0.0 {
+                (position[axis] + 1) as f32
+            } else if direction[axis] < 0.0 {
+                position[axis] as f32
+            } else {
+                continue;
+            };
+
+            let t_axis = (boundary - origin[axis]) / direction[axis];
+            if t_axis < t_voxel_exit {
+                t_voxel_exit = t_axis;
+                exit_axis = axis;
+            }
+        }
+
+        if !t_voxel_exit.is_finite() || t_voxel_exit > max_t {
+            return None;
+        }
+
+        position[exit_axis] += direction[exit_axis].signum() as isize;
+        if position[exit_axis] < 0 || position[exit_axis] >= side_size as isize {
+            return None;
+        }
+
+        t = t.max(t_voxel_exit);
+        normal = [0; 3];
+        normal[exit_axis] = -direction[exit_axis].signum() as i32;
+    }
+}
+
+fn random_ray(random: &mut Random, side_size: f32) -> ([f32; 3], [f32; 3]) {
+    let origin = [
+        random.next_f32() * side_size * 3.0 - side_size,
+        random.next_f32() * side_size * 3.0 - side_size,
+        random.next_f32() * side_size * 3.0 - side_size,
+    ];
+
+    let target = [
+        random.next_f32() * side_size,
+        random.next_f32() * side_size,
+        random.next_f32() * side_size,
+    ];
+
+    let direction = [
+        target[0] - origin[0],
+        target[1] - origin[1],
+        target[2] - origin[2],
+    ];
+    let length =
+        (direction[0] * direction[0] + direction[1] * direction[1] + direction[2] * direction[2])
+            .sqrt();
+
+    (
+        origin,
+        [
+            direction[0] / length,
+            direction[1] / length,
+            direction[2] / length,
+        ],
+    )
+}
+
+fn assert_same_hit(svdag_hit: Option<SvdagHit>, dense_hit: Option<SvdagHit>) {
+    match (svdag_hit, dense_hit) {
+        (Some(svdag_hit), Some(dense_hit)) => {
+            assert_eq!(svdag_hit.position, dense_hit.position);
+            assert_eq!(svdag_hit.normal, dense_hit.normal);
+            assert!((svdag_hit.distance - dense_hit.distance).abs() < 1e-3);
+        }
+        (None, None) => {}
+        other => panic!("hits differ: {:?}", other),
+    }
+}
+
+#[test]
+fn raycast_matches_dense_dda() {
+    let mut random = Random::new(42);
+
+    for volume in &[
+        create_sphere_volume(5),
+        create_noise_volume(5, 3, 97),
+        create_noise_volume(4, 5, 3),
+    ] {
+        let svdag = Svdag::from(volume);
+        let side_size = volume.get_dimensions().0 as f32;
+
+        for _ in 0..2000 {
+            let (origin, direction) = random_ray(&mut random, side_size);
+
+            assert_same_hit(
+                svdag.raycast(origin, direction, f32::INFINITY),
+                raycast_dense(volume, origin, direction, f32::INFINITY),
+            );
+        }
+    }
+}
+
+#[test]
+fn raycast_respects_max_distance() {
+    let volume = create_sphere_volume(5);
+    let svdag = Svdag::from(&volume);
+
+    let hit = svdag
+        .raycast([-10.0, 16.5, 16.5], [1.0, 0.0, 0.0], 100.0)
+        .unwrap();
+    assert_eq!(hit.position, (9, 16, 16));
+    assert_eq!(hit.normal, [-1, 0, 0]);
+    assert!((hit.distance - 19.0).abs() < 1e-4);
+
+    assert_eq!(
+        svdag.raycast([-10.0, 16.5, 16.5], [1.0, 0.0, 0.0], 18.0),
+        None
+    );
+}
+
+#[test]
+fn raycast_skips_empty_octants() {
+    let volume = create_sphere_volume(6);
+    let svdag = Svdag::from(&volume);
+
+    //A ray missing the sphere crosses 64 voxels but only a handful of octants
+    let miss = svdag.raycast([0.5, 0.5, -1.0], [0.0, 0.0, 1.0], f32::INFINITY);
+    assert_eq!(miss, None);
+
+    let hit = svdag
+        .raycast([32.5, 32.5, -1.0], [0.0, 0.0, 1.0], f32::INFINITY)
+        .unwrap();
+    assert_eq!(hit.position, (32, 32, 17));
+    assert!(hit.steps < 10);
+}
+
+#[test]
+fn raycast_starting_inside_occupied_voxel() {
+    let volume = create_sphere_volume(4);
+    let svdag = Svdag::from(&volume);
+    let mut bytes = Vec::new();
+    svdag.write_to(&mut bytes).unwrap();
+    let svdag_ref = SvdagRef::from_bytes(&bytes).unwrap();
+
+    let hit = svdag
+        .raycast([8.5, 8.5, 8.5], [0.0, 1.0, 0.0], 1.0)
+        .unwrap();
+    assert_eq!(hit.position, (8, 8, 8));
+    assert_eq!(hit.normal, [0, 0, 0]);
+    assert_eq!(hit.distance, 0.0);
+
+    assert_eq!(
+        svdag_ref.raycast([8.5, 8.5, 8.5], [0.0, 1.0, 0.0], 1.0),
+        Some(hit)
+    );
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::{assert_matches_volume, create_sphere_volume, serialize};
+use svdag::{
+    svdag::SvdagValue, IsVolume, PointerFormat, Svdag, SvdagBuilder, SvdagError, SvdagRef,
+};
+
+#[test]
+fn queries_serialized_bytes_in_place() {
+    let volume = create_sphere_volume(5);
+
+    for pointer_format in &[PointerFormat::Relative16, PointerFormat::Absolute32] {
+        let svdag = SvdagBuilder::new()
+            .pointer_format(*pointer_format)
+            .create_layers(&volume)
+            .create_graph()
+            .unwrap()
+            .finish();
+        let bytes = serialize(&svdag);
+
+        let svdag_ref = SvdagRef::from_bytes(&bytes).unwrap();
+        svdag_ref.verify().unwrap();
+        assert_eq!(svdag_ref.get_dimensions(), volume.get_dimensions());
+        assert_eq!(svdag_ref.to_svdag().nodes, svdag.nodes);
+        assert_matches_volume(&svdag_ref, &volume);
+    }
+}
+
+#[test]
+fn loads_embedded_file() {
+    let svdag_ref = SvdagRef::from_bytes(include_bytes!("../svdag.bin")).unwrap();
+
+    assert_eq!(svdag_ref.header().depth, 3);
+    assert_eq!(svdag_ref.get((6, 6, 6)), Some(true));
+    assert_eq!(svdag_ref.get((0, 0, 0)), Some(false));
+    assert_eq!(svdag_ref.get((8, 8, 6)), None);
+}
+
+#[test]
+fn rejects_pointers_outside_the_node_array() {
+    let mut svdag = Svdag::from(&create_sphere_volume(3));
+    svdag.nodes[1] = SvdagValue { bits: 0x7fff };
+    let bytes = serialize(&svdag);
+
+    //Loading only reads the header, the broken pointer shows up once it's followed
+    let svdag_ref = SvdagRef::from_bytes(&bytes).unwrap();
+    assert_eq!(svdag_ref.get((0, 0, 0)), None);
+
+    match svdag_ref.verify() {
+        Err(SvdagError::InvalidPointer { pointer_index: 1 }) => {}
+        other => panic!("expected an invalid pointer, got {:?}", other),
+    }
+
+    match Svdag::read_from(bytes.as_slice()) {
+        Err(SvdagError::InvalidPointer { pointer_index: 1 }) => {}
+        other => panic!("expected an invalid pointer, got {:?}", other),
+    }
+}
+
+#[test]
+fn rejects_truncated_buffers() {
+    let bytes = serialize(&Svdag::from(&create_sphere_volume(3)));
+
+    match SvdagRef::from_bytes(&bytes[..bytes.len() - 2]) {
+        Err(SvdagError::Truncated { expected, actual }) => {
+            assert_eq!(expected, bytes.len() as u64);
+            assert_eq!(actual, bytes.len() as u64 - 2);
+        }
+        other => panic!("expected a truncated buffer, got {:?}", other),
+    }
+
+    //A corrupt node count claiming nearly all of `u64` is reported, not overflowed
+    let mut corrupt = bytes.clone();
+    corrupt[8..16].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+
+    match SvdagRef::from_bytes(&corrupt) {
+        Err(SvdagError::Truncated { expected, actual }) => {
+            assert_eq!(expected, u64::MAX);
+            assert_eq!(actual, bytes.len() as u64);
+        }
+        other => panic!("expected a truncated buffer, got {:?}", other),
+    }
+}
+
+#[test]
+fn verifies_checksums_on_request() {
+    let svdag = Svdag::from(&create_sphere_volume(3));
+    let mut bytes = serialize(&svdag);
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0x10;
+
+    let svdag_ref = SvdagRef::from_bytes(&bytes).unwrap();
+    match svdag_ref.verify() {
+        Err(SvdagError::ChecksumMismatch { .. }) => {}
+        other => panic!("expected checksum mismatch, got {:?}", other),
+    }
+
+    assert!(SvdagRef::from_bytes(&serialize(&svdag))
+        .unwrap()
+        .verify()
+        .is_ok());
+}
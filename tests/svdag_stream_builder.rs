@@ -0,0 +1,192 @@
+use std::cell::Cell;
+use svdag::{
+    CubicVolume, DensityVolume, IsVolume, PointerFormat, Svdag, SvdagBuilder, SvdagError,
+    SvdagStreamBuilder,
+};
+
+fn create_test_volume(depth: u8) -> DensityVolume {
+    let mut volume = CubicVolume::new(depth);
+    let dimensions = volume.get_dimensions();
+    let center = dimensions.0 as f32 / 2.0;
+
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for x in 0..dimensions.0 {
+        for y in 0..dimensions.1 {
+            for z in 0..dimensions.2 {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+
+                let distance = ((x as f32 - center).powf(2.0)
+                    + (y as f32 - center).powf(2.0)
+                    + (z as f32 - center).powf(2.0))
+                .sqrt();
+
+                *volume.get_mut((x, y, z)) = distance < center / 2.0 || state.is_multiple_of(11);
+            }
+        }
+    }
+
+    volume
+}
+
+#[test]
+fn sampling_matches_dense_builder() {
+    for depth in 1..=5 {
+        let volume = create_test_volume(depth);
+        let dense = Svdag::from(&volume);
+
+        let streamed = SvdagStreamBuilder::new(depth)
+            .build_from_fn(|position| *volume.get(position))
+            .unwrap();
+
+        assert_eq!(streamed.depth, dense.depth);
+        assert_eq!(streamed.pointer_format, dense.pointer_format);
+        assert_eq!(streamed.nodes, dense.nodes);
+    }
+}
+
+#[test]
+fn bricks_match_dense_builder() {
+    let depth = 5;
+    let volume = create_test_volume(depth);
+    let dense = Svdag::from(&volume);
+
+    for brick_depth in 1..=depth {
+        let streamed = SvdagStreamBuilder::new(depth)
+            .build_from_bricks(brick_depth, |origin, brick| {
+                let dimensions = brick.get_dimensions();
+                let mut any_occupied = false;
+
+                for x in 0..dimensions.0 {
+                    for y in 0..dimensions.1 {
+                        for z in 0..dimensions.2 {
+                            let value = *volume.get((origin.0 + x, origin.1 + y, origin.2 + z));
+                            *brick.get_mut((x, y, z)) = value;
+                            any_occupied |= value;
+                        }
+                    }
+                }
+
+                any_occupied
+            })
+            .unwrap();
+
+        assert_eq!(streamed.nodes, dense.nodes);
+    }
+}
+
+#[test]
+fn sparse_deep_volumes_stay_small() {
+    let depth = 10;
+
+    //Only a single brick holds anything, all others are skipped without being sampled
+    let svdag = SvdagStreamBuilder::new(depth)
+        .pointer_format(PointerFormat::Absolute32)
+        .build_from_bricks(5, |origin, brick| {
+            if origin != (0, 0, 0) {
+                return false;
+            }
+
+            *brick.get_mut((1, 2, 3)) = true;
+            true
+        })
+        .unwrap();
+
+    assert_eq!(svdag.depth, depth);
+    assert_eq!(svdag.get((1, 2, 3)), Some(true));
+    assert_eq!(svdag.get((3, 2, 1)), Some(false));
+    assert_eq!(svdag.get((1023, 1023, 1023)), Some(false));
+    assert!(svdag.nodes.len() < 64);
+}
+
+#[test]
+fn empty_volumes_have_an_empty_root() {
+    let svdag = SvdagStreamBuilder::new(4).build_from_fn(|_| false).unwrap();
+
+    assert_eq!(svdag.nodes.len(), 1);
+    assert_eq!(svdag.get((3, 3, 3)), Some(false));
+}
+
+#[test]
+fn rejects_zero_depth() {
+    match SvdagStreamBuilder::new(0).build_from_fn(|_| true) {
+        Err(SvdagError::InvalidDepth(0)) => {}
+        other => panic!("expected an invalid depth, got {:?}", other),
+    }
+}
+
+#[test]
+fn matches_dense_builder_with_forced_pointer_format() {
+    let volume = create_test_volume(4);
+    let dense = SvdagBuilder::new()
+        .pointer_format(PointerFormat::Absolute32)
+        .create_layers(&volume)
+        .create_graph()
+        .unwrap()
+        .finish();
+
+    let streamed = SvdagStreamBuilder::new(4)
+        .pointer_format(PointerFormat::Absolute32)
+        .build_from_fn(|position| *volume.get(position))
+        .unwrap();
+
+    assert_eq!(streamed.nodes, dense.nodes);
+}
+
+fn sphere_sdf(center: [f32; 3], radius: f32) -> impl Fn([f32; 3]) -> f32 {
+    move |position| {
+        ((position[0] - center[0]).powf(2.0)
+            + (position[1] - center[1]).powf(2.0)
+            + (position[2] - center[2]).powf(2.0))
+        .sqrt()
+            - radius
+    }
+}
+
+#[test]
+fn from_fn_matches_dense_builder() {
+    let volume = create_test_volume(4);
+
+    let svdag = Svdag::from_fn(4, |position| *volume.get(position)).unwrap();
+
+    assert_eq!(svdag.nodes, Svdag::from(&volume).nodes);
+}
+
+#[test]
+fn sdf_matches_dense_builder_with_fewer_evaluations() {
+    let depth = 6;
+    let sdf = sphere_sdf([30.0, 33.5, 29.0], 21.0);
+
+    let mut volume = CubicVolume::new(depth);
+    let dimensions = volume.get_dimensions();
+    for x in 0..dimensions.0 {
+        for y in 0..dimensions.1 {
+            for z in 0..dimensions.2 {
+                *volume.get_mut((x, y, z)) = sdf([x as f32, y as f32, z as f32]) < 0.0;
+            }
+        }
+    }
+
+    let evaluations = Cell::new(0);
+    let svdag = Svdag::from_sdf(depth, |position| {
+        evaluations.set(evaluations.get() + 1);
+        sdf(position)
+    })
+    .unwrap();
+
+    assert_eq!(svdag.nodes, Svdag::from(&volume).nodes);
+    assert!(evaluations.get() < volume.get_element_count() / 4);
+}
+
+#[test]
+fn sdf_handles_fully_inside_and_outside_volumes() {
+    let full = Svdag::from_sdf(5, |_| -1000.0).unwrap();
+    let empty = Svdag::from_sdf(5, |_| 1000.0).unwrap();
+
+    assert_eq!(full.nodes.len(), 5 * 9 - 8);
+    assert_eq!(full.get((0, 0, 0)), Some(true));
+    assert_eq!(full.get((31, 17, 5)), Some(true));
+    assert_eq!(empty.nodes.len(), 1);
+    assert_eq!(empty.get((31, 17, 5)), Some(false));
+}
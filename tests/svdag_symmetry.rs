@@ -0,0 +1,175 @@
+mod common;
+
+use common::{
+    assert_matches_volume, create_noise_volume, create_sphere_volume, for_each_position, serialize,
+    Random,
+};
+use svdag::{
+    CubicVolume, DensityVolume, IsVolume, PointerFormat, Svdag, SvdagBuilder, SvdagError, SvdagRef,
+};
+
+fn build(volume: &DensityVolume, symmetry: bool) -> Svdag {
+    SvdagBuilder::new()
+        .symmetry(symmetry)
+        .create_layers(volume)
+        .create_graph()
+        .unwrap()
+        .finish()
+}
+
+/// Graph without symmetry but with pointers as wide as mirrored ones, for comparing sizes.
+fn build_wide(volume: &DensityVolume) -> Svdag {
+    SvdagBuilder::new()
+        .pointer_format(PointerFormat::Absolute32)
+        .create_layers(volume)
+        .create_graph()
+        .unwrap()
+        .finish()
+}
+
+/// Noise in one octant, copied into every other octant reflected by that octant's child index.
+fn create_reflected_volume(depth: u8, seed: u64) -> DensityVolume {
+    let octant = create_noise_volume(depth - 1, seed, 3);
+    let half_size = octant.get_dimensions().0;
+    let mut volume = CubicVolume::new(depth);
+
+    for_each_position(&octant, |(x, y, z)| {
+        for child_index in 0..8 {
+            let reflect = |bit: usize, value: usize| {
+                if child_index & bit != 0 {
+                    2 * half_size - 1 - value
+                } else {
+                    value
+                }
+            };
+
+            *volume.get_mut((reflect(4, x), reflect(2, y), reflect(1, z))) = *octant.get((x, y, z));
+        }
+    });
+
+    volume
+}
+
+#[test]
+fn reflected_octants_share_storage() {
+    let volume = create_reflected_volume(5, 7);
+
+    let plain = build_wide(&volume);
+    let symmetric = build(&volume, true);
+
+    assert_eq!(symmetric.pointer_format, PointerFormat::Mirrored32);
+    assert_matches_volume(&symmetric, &volume);
+
+    //The root points to the same node 8 times, so only the root and a single octant remain
+    let root = symmetric.nodes[0].node();
+    assert_eq!(root.children.count_occupied(), 8);
+    assert!(
+        symmetric.nodes.len() * 4 < plain.nodes.len(),
+        "{} words with symmetry, {} without",
+        symmetric.nodes.len(),
+        plain.nodes.len()
+    );
+}
+
+#[test]
+fn matches_plain_graph_on_asymmetric_volumes() {
+    for (seed, depth) in [(1, 1), (2, 2), (3, 4), (4, 5)].iter() {
+        let volume = create_noise_volume(*depth, *seed, 4);
+        let symmetric = build(&volume, true);
+
+        assert_eq!(symmetric.depth, *depth);
+        assert_matches_volume(&symmetric, &volume);
+    }
+
+    let sphere = create_sphere_volume(5);
+    let symmetric = build(&sphere, true);
+
+    assert_matches_volume(&symmetric, &sphere);
+    assert!(symmetric.nodes.len() < build_wide(&sphere).nodes.len());
+}
+
+#[test]
+fn raycasts_like_plain_graph() {
+    let volume = create_reflected_volume(5, 11);
+    let plain = build(&volume, false);
+    let symmetric = build(&volume, true);
+    let side_size = volume.get_dimensions().0 as f32;
+
+    let mut random = Random::new(3);
+    for _ in 0..500 {
+        let origin = [
+            random.next_f32() * side_size * 2.0 - side_size / 2.0,
+            random.next_f32() * side_size * 2.0 - side_size / 2.0,
+            random.next_f32() * side_size * 2.0 - side_size / 2.0,
+        ];
+        let direction = [
+            random.next_f32() - 0.5,
+            random.next_f32() - 0.5,
+            random.next_f32() - 0.5,
+        ];
+
+        let expected = plain
+            .raycast(origin, direction, 1000.0)
+            .map(|hit| (hit.position, hit.normal));
+        let actual = symmetric
+            .raycast(origin, direction, 1000.0)
+            .map(|hit| (hit.position, hit.normal));
+
+        assert_eq!(
+            actual, expected,
+            "ray from {:?} along {:?}",
+            origin, direction
+        );
+    }
+}
+
+#[test]
+fn extracts_like_plain_graph() {
+    let volume = create_reflected_volume(5, 13);
+    let symmetric = build(&volume, true);
+
+    let region = symmetric.extract((3, 9, 17), (29, 30, 31));
+    assert_eq!(region.get_dimensions(), (26, 21, 14));
+    for_each_position(&region, |(x, y, z)| {
+        assert_eq!(
+            region.get((x, y, z)),
+            volume.get((x + 3, y + 9, z + 17)),
+            "mismatch at {:?}",
+            (x, y, z)
+        );
+    });
+
+    assert_matches_volume(&symmetric, &DensityVolume::from(&symmetric));
+}
+
+#[test]
+fn mirrored_pointers_round_trip_through_files() {
+    let volume = create_reflected_volume(4, 17);
+    let symmetric = build(&volume, true);
+    let bytes = serialize(&symmetric);
+
+    let loaded = Svdag::read_from(&bytes[..]).unwrap();
+    assert_eq!(loaded.pointer_format, PointerFormat::Mirrored32);
+    assert_eq!(loaded.nodes, symmetric.nodes);
+
+    assert_matches_volume(&SvdagRef::from_bytes(&bytes).unwrap(), &volume);
+}
+
+#[test]
+fn rejects_formats_without_mirror_bits() {
+    let volume = create_reflected_volume(4, 19);
+
+    let result = SvdagBuilder::new()
+        .symmetry(true)
+        .pointer_format(PointerFormat::Absolute32)
+        .create_layers(&volume)
+        .create_graph()
+        .map(|builder| builder.finish());
+
+    match result {
+        Err(SvdagError::MirrorUnsupported { pointer_format }) => {
+            assert_eq!(pointer_format, PointerFormat::Absolute32)
+        }
+        result => panic!("expected MirrorUnsupported, got {:?}", result),
+    }
+}
@@ -0,0 +1,145 @@
+use svdag::{IsVolume, SvdagError, SvdagRead, VoxFile};
+
+fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+    let mut bytes = id.to_vec();
+    bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(content);
+    bytes.extend_from_slice(children);
+    bytes
+}
+
+fn size_chunk(size: [u32; 3]) -> Vec<u8> {
+    let content: Vec<u8> = size.iter().flat_map(|side| side.to_le_bytes()).collect();
+    chunk(b"SIZE", &content, &[])
+}
+
+fn xyzi_chunk(voxels: &[[u8; 4]]) -> Vec<u8> {
+    let mut content = (voxels.len() as u32).to_le_bytes().to_vec();
+    content.extend(voxels.iter().flatten());
+    chunk(b"XYZI", &content, &[])
+}
+
+fn vox_file(version: u32, children: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = b"VOX ".to_vec();
+    bytes.extend_from_slice(&version.to_le_bytes());
+    bytes.extend(chunk(b"MAIN", &[], &children.concat()));
+    bytes
+}
+
+fn read_error(bytes: &[u8]) -> SvdagError {
+    match VoxFile::from_bytes(bytes) {
+        Err(error) => error,
+        Ok(_) => panic!("expected the file to be rejected"),
+    }
+}
+
+#[test]
+fn reads_multiple_models() {
+    let mut palette = vec![0; 256 * 4];
+    palette[0..4].copy_from_slice(&[10, 20, 30, 255]);
+    palette[4 * 6..4 * 7].copy_from_slice(&[200, 100, 50, 255]);
+
+    let bytes = vox_file(
+        150,
+        &[
+            chunk(b"PACK", &2u32.to_le_bytes(), &[]),
+            size_chunk([3, 5, 7]),
+            xyzi_chunk(&[[0, 0, 0, 1], [2, 4, 6, 7], [1, 2, 3, 1]]),
+            size_chunk([2, 2, 2]),
+            xyzi_chunk(&[[1, 1, 1, 7]]),
+            //Editor chunks in between are skipped
+            chunk(b"nTRN", &[1, 2, 3, 4], &[]),
+            chunk(b"RGBA", &palette, &[]),
+        ],
+    );
+
+    let vox = VoxFile::read_from(bytes.as_slice()).unwrap();
+    assert_eq!(vox.models.len(), 2);
+    assert_eq!(vox.palette[0], [0, 0, 0, 0]);
+    assert_eq!(vox.palette[1], [10, 20, 30, 255]);
+    assert_eq!(vox.palette[7], [200, 100, 50, 255]);
+
+    let model = &vox.models[0];
+    assert_eq!(model.volume.get_dimensions(), (3, 5, 7));
+    assert_eq!(*model.volume.get((2, 4, 6)), 7);
+    assert_eq!(*model.volume.get((1, 2, 3)), 1);
+    assert_eq!(*model.volume.get((1, 1, 1)), 0);
+
+    let svdag = model.to_svdag();
+    assert_eq!(svdag.get_dimensions(), (3, 5, 7));
+    assert_eq!(svdag.get((0, 0, 0)), Some(true));
+    assert_eq!(svdag.get((2, 4, 6)), Some(true));
+    assert_eq!(svdag.get((2, 4, 5)), Some(false));
+    assert_eq!(svdag.get((3, 0, 0)), None);
+
+    let materials = model.to_material_svdag().unwrap();
+    assert_eq!(materials.get_dimensions(), (3, 5, 7));
+    assert_eq!(materials.get_material((2, 4, 6)), Some(7));
+    assert_eq!(materials.get_material((0, 5, 0)), None);
+
+    let materials = vox.models[1].to_material_svdag().unwrap();
+    assert_eq!(materials.get_material((1, 1, 1)), Some(7));
+    assert_eq!(materials.get_material((0, 1, 1)), Some(0));
+    assert_eq!(materials.depth(), 1);
+}
+
+#[test]
+fn falls_back_to_the_default_palette() {
+    let bytes = vox_file(200, &[size_chunk([1, 1, 1]), xyzi_chunk(&[[0, 0, 0, 1]])]);
+    let vox = VoxFile::from_bytes(&bytes).unwrap();
+
+    assert_eq!(vox.palette[1], [255, 255, 255, 255]);
+    assert_eq!(vox.palette[2], [255, 255, 204, 255]);
+    assert_eq!(vox.palette[215], [0, 0, 51, 255]);
+    assert_eq!(vox.palette[216], [238, 0, 0, 255]);
+    assert_eq!(vox.palette[255], [17, 17, 17, 255]);
+
+    //A single voxel model still makes a valid graph
+    assert_eq!(vox.models[0].to_svdag().get((0, 0, 0)), Some(true));
+}
+
+#[test]
+fn rejects_unsupported_versions() {
+    let bytes = vox_file(151, &[size_chunk([1, 1, 1]), xyzi_chunk(&[])]);
+
+    match read_error(&bytes) {
+        SvdagError::UnsupportedVoxVersion(version) => assert_eq!(version, 151),
+        other => panic!("expected unsupported version, got {:?}", other),
+    }
+}
+
+#[test]
+fn rejects_malformed_files() {
+    let mut not_vox = vox_file(150, &[]);
+    not_vox[0] = b'B';
+    let models_without_size = vox_file(150, &[xyzi_chunk(&[[0, 0, 0, 1]])]);
+    let voxels_outside = vox_file(150, &[size_chunk([2, 2, 2]), xyzi_chunk(&[[0, 2, 0, 1]])]);
+    let empty_size = vox_file(150, &[size_chunk([4, 0, 4]), xyzi_chunk(&[])]);
+    let oversized = vox_file(150, &[size_chunk([2, 257, 2]), xyzi_chunk(&[])]);
+    let mut truncated = vox_file(150, &[size_chunk([2, 2, 2]), xyzi_chunk(&[[0, 0, 0, 1]])]);
+    truncated.pop();
+
+    for (bytes, reason) in [
+        (not_vox, "magic"),
+        (models_without_size, "without a SIZE"),
+        (voxels_outside, "outside the model size"),
+        (empty_size, "empty or larger"),
+        (oversized, "larger than 256"),
+        (truncated, "runs past the end"),
+    ]
+    .iter()
+    {
+        match read_error(bytes) {
+            SvdagError::InvalidVox(message) => {
+                assert!(
+                    message.contains(reason),
+                    "{:?} should mention {:?}",
+                    message,
+                    reason
+                )
+            }
+            other => panic!("expected invalid vox, got {:?}", other),
+        }
+    }
+}
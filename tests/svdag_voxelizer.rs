@@ -0,0 +1,421 @@
+mod common;
+
+use common::{for_each_position, Random};
+use svdag::{IsVolume, MeshVoxelizer, PointerFormat, SvdagError, SvdagRead, TriangleMesh};
+
+/// Closed box between `min` and `max` with outward facing triangles.
+fn create_box_mesh(min: f32, max: f32) -> TriangleMesh {
+    let mut mesh = TriangleMesh::new();
+    for corner in 0..8 {
+        let coordinate = |bit: usize| if corner & bit != 0 { max } else { min };
+        mesh.vertices
+            .push([coordinate(4), coordinate(2), coordinate(1)]);
+    }
+
+    mesh.triangles = vec![
+        [0, 1, 3],
+        [0, 3, 2],
+        [4, 6, 7],
+        [4, 7, 5],
+        [0, 4, 5],
+        [0, 5, 1],
+        [2, 3, 7],
+        [2, 7, 6],
+        [0, 2, 6],
+        [0, 6, 4],
+        [1, 5, 7],
+        [1, 7, 3],
+    ];
+
+    mesh
+}
+
+fn create_sphere_mesh(
+    center: [f32; 3],
+    radius: f32,
+    segments: usize,
+    rings: usize,
+) -> TriangleMesh {
+    let mut mesh = TriangleMesh::new();
+
+    for ring in 0..=rings {
+        let polar = std::f32::consts::PI * ring as f32 / rings as f32;
+        for segment in 0..segments {
+            let azimuth = 2.0 * std::f32::consts::PI * segment as f32 / segments as f32;
+            mesh.vertices.push([
+                center[0] + radius * polar.sin() * azimuth.cos(),
+                center[1] + radius * polar.sin() * azimuth.sin(),
+                center[2] + radius * polar.cos(),
+            ]);
+        }
+    }
+
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let next_segment = (segment + 1) % segments;
+            let a = ring * segments + segment;
+            let b = ring * segments + next_segment;
+            let c = (ring + 1) * segments + segment;
+            let d = (ring + 1) * segments + next_segment;
+
+            mesh.triangles.push([a, c, d]);
+            mesh.triangles.push([a, d, b]);
+        }
+    }
+
+    mesh
+}
+
+fn to_binary_stl(mesh: &TriangleMesh) -> Vec<u8> {
+    let mut bytes = vec![0; 80];
+    bytes.extend_from_slice(&(mesh.triangles.len() as u32).to_le_bytes());
+
+    for index in 0..mesh.triangles.len() {
+        bytes.extend_from_slice(&[0; 12]);
+        for corner in mesh.triangle(index).iter() {
+            for coordinate in corner.iter() {
+                bytes.extend_from_slice(&coordinate.to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&[0; 2]);
+    }
+
+    bytes
+}
+
+fn to_ascii_stl(mesh: &TriangleMesh) -> String {
+    let mut text = String::from("solid test\n");
+
+    for index in 0..mesh.triangles.len() {
+        text.push_str("  facet normal 0 0 0\n    outer loop\n");
+        for corner in mesh.triangle(index).iter() {
+            text.push_str(&format!(
+                "      vertex {} {} {}\n",
+                corner[0], corner[1], corner[2]
+            ));
+        }
+        text.push_str("    endloop\n  endfacet\n");
+    }
+
+    text.push_str("endsolid test\n");
+    text
+}
+
+#[test]
+fn reads_obj_faces() {
+    let obj = "# a quad and a triangle\n\
+               v 0 0 0\n\
+               v 1 0 0\n\
+               v 1 1 0\n\
+               v 0 1 0\n\
+               vn 0 0 1\n\
+               f 1//1 2//1 3//1 4//1\n\
+               v 0 0 1\n\
+               f -1 1/1 2/2/1\n";
+
+    let mesh = TriangleMesh::read_obj(obj.as_bytes()).unwrap();
+    assert_eq!(mesh.vertices.len(), 5);
+    assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3], [4, 0, 1]]);
+    assert_eq!(mesh.bounds(), Some(([0.0; 3], [1.0; 3])));
+
+    for (obj, reason) in [
+        (
+            "v 0 0 0\nf 1 2 1\n",
+            "line 2: face refers to a missing vertex",
+        ),
+        (
+            "v 0 0 0\nf 1 -2 1\n",
+            "line 2: face refers to a missing vertex",
+        ),
+        ("v 0 0 0\nf 1 1\n", "line 2: face has fewer than three"),
+        ("v 0 zero 0\n", "line 1: expected three vertex coordinates"),
+    ]
+    .iter()
+    {
+        match TriangleMesh::read_obj(obj.as_bytes()) {
+            Err(SvdagError::InvalidMesh(message)) => {
+                assert!(
+                    message.contains(reason),
+                    "{:?} should mention {:?}",
+                    message,
+                    reason
+                )
+            }
+            other => panic!("expected invalid mesh, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn reads_binary_and_ascii_stl() {
+    let mesh = create_box_mesh(-0.5, 2.25);
+    let binary = TriangleMesh::read_stl(to_binary_stl(&mesh).as_slice()).unwrap();
+    let ascii = TriangleMesh::read_stl(to_ascii_stl(&mesh).as_bytes()).unwrap();
+
+    assert_eq!(binary, ascii);
+    assert_eq!(binary.triangles.len(), mesh.triangles.len());
+    for index in 0..mesh.triangles.len() {
+        assert_eq!(binary.triangle(index), mesh.triangle(index));
+    }
+
+    //A binary header may start with `solid` as well
+    let mut named_binary = to_binary_stl(&mesh);
+    named_binary[..5].copy_from_slice(b"solid");
+    assert_eq!(
+        TriangleMesh::read_stl(named_binary.as_slice()).unwrap(),
+        binary
+    );
+
+    let mut truncated = to_binary_stl(&mesh);
+    truncated[..5].copy_from_slice(b"hello");
+    truncated.pop();
+    assert!(matches!(
+        TriangleMesh::read_stl(truncated.as_slice()),
+        Err(SvdagError::Truncated { .. })
+    ));
+}
+
+#[test]
+fn voxelizes_box_surface_and_interior() {
+    let mesh = create_box_mesh(1.5, 6.5);
+
+    let surface = MeshVoxelizer::new(3)
+        .placement([0.0; 3], 1.0)
+        .voxelize(&mesh)
+        .unwrap();
+    let solid = MeshVoxelizer::new(3)
+        .placement([0.0; 3], 1.0)
+        .solid(true)
+        .voxelize(&mesh)
+        .unwrap();
+
+    for_each_position(&surface, |(x, y, z)| {
+        let is_within = [x, y, z].iter().all(|side| (1..=6).contains(side));
+        let is_on_surface = [x, y, z].iter().any(|side| *side == 1 || *side == 6);
+
+        assert_eq!(
+            *surface.get((x, y, z)),
+            is_within && is_on_surface,
+            "surface at {:?}",
+            (x, y, z)
+        );
+        assert_eq!(*solid.get((x, y, z)), is_within, "solid at {:?}", (x, y, z));
+    });
+}
+
+#[test]
+fn fills_sphere_interior() {
+    let radius = 11.0;
+    let center = [16.0, 15.5, 16.25];
+    let mesh = create_sphere_mesh(center, radius, 48, 24);
+
+    let svdag = MeshVoxelizer::new(5)
+        .placement([0.0; 3], 1.0)
+        .solid(true)
+        .build(&mesh)
+        .unwrap();
+
+    let mut occupied = 0;
+    for_each_position(&svdag.extract((0, 0, 0), (32, 32, 32)), |position| {
+        let voxel = [position.0 as f32, position.1 as f32, position.2 as f32];
+        let center_distance = (0..3)
+            .map(|axis| (voxel[axis] + 0.5 - center[axis]).powi(2))
+            .sum::<f32>()
+            .sqrt();
+        let nearest_distance = (0..3)
+            .map(|axis| {
+                let nearest = center[axis].clamp(voxel[axis], voxel[axis] + 1.0);
+                (nearest - center[axis]).powi(2)
+            })
+            .sum::<f32>()
+            .sqrt();
+
+        let is_occupied = svdag.get(position).unwrap();
+        if center_distance < radius - 1.0 {
+            assert!(is_occupied, "interior voxel {:?} is empty", position);
+        }
+        if nearest_distance > radius + 0.01 {
+            assert!(!is_occupied, "outside voxel {:?} is occupied", position);
+        }
+        occupied += is_occupied as usize;
+    });
+
+    let sphere_volume = 4.0 / 3.0 * std::f32::consts::PI * radius.powi(3);
+    assert!((occupied as f32) > sphere_volume);
+}
+
+#[test]
+fn marks_every_touched_voxel() {
+    let mut random = Random::new(21);
+    let mut mesh = TriangleMesh::new();
+
+    for _ in 0..40 {
+        let first = mesh.vertices.len();
+        for _ in 0..3 {
+            mesh.vertices.push([
+                random.next_f32() * 16.0,
+                random.next_f32() * 16.0,
+                random.next_f32() * 16.0,
+            ]);
+        }
+        mesh.triangles.push([first, first + 1, first + 2]);
+    }
+
+    let svdag = MeshVoxelizer::new(4)
+        .placement([0.0; 3], 1.0)
+        .build(&mesh)
+        .unwrap();
+
+    //Sample points across every triangle, the voxel of each has to be occupied
+    for index in 0..mesh.triangles.len() {
+        let [a, b, c] = mesh.triangle(index);
+        for u in 0..=40 {
+            for v in 0..=40 - u {
+                let (u, v) = (u as f32 / 40.0, v as f32 / 40.0);
+                let point: Vec<usize> = (0..3)
+                    .map(|axis| {
+                        let coordinate =
+                            a[axis] + u * (b[axis] - a[axis]) + v * (c[axis] - a[axis]);
+                        (coordinate as usize).min(15)
+                    })
+                    .collect();
+
+                assert_eq!(
+                    svdag.get((point[0], point[1], point[2])),
+                    Some(true),
+                    "triangle {} misses {:?}",
+                    index,
+                    point
+                );
+            }
+        }
+    }
+}
+
+/// Whether any part of the triangle lies in the voxel, found by clipping it to the voxel's faces.
+fn touches_voxel(triangle: &[[f32; 3]; 3], voxel: (usize, usize, usize)) -> bool {
+    let voxel = [voxel.0 as f32, voxel.1 as f32, voxel.2 as f32];
+    let mut polygon = triangle.to_vec();
+
+    for axis in 0..3 {
+        for (bound, sign) in [(voxel[axis], 1.0), (voxel[axis] + 1.0, -1.0)].iter() {
+            let distance = |point: [f32; 3]| sign * (point[axis] - bound);
+            let mut clipped = Vec::new();
+
+            for (index, a) in polygon.iter().enumerate() {
+                let b = polygon[(index + 1) % polygon.len()];
+                let (a_distance, b_distance) = (distance(*a), distance(b));
+
+                if a_distance >= 0.0 {
+                    clipped.push(*a);
+                }
+                if (a_distance >= 0.0) != (b_distance >= 0.0) {
+                    let t = a_distance / (a_distance - b_distance);
+                    let mut crossing = [0.0; 3];
+                    for (coordinate, crossing) in crossing.iter_mut().enumerate() {
+                        *crossing = a[coordinate] + t * (b[coordinate] - a[coordinate]);
+                    }
+                    crossing[axis] = *bound;
+                    clipped.push(crossing);
+                }
+            }
+
+            if clipped.is_empty() {
+                return false;
+            }
+            polygon = clipped;
+        }
+    }
+
+    true
+}
+
+/// Whether the point lies on the same side of every face as `center`, which only holds for
+/// convex meshes.
+fn is_inside_convex(mesh: &TriangleMesh, center: [f32; 3], point: [f32; 3]) -> bool {
+    (0..mesh.triangles.len()).all(|index| {
+        let [a, b, c] = mesh.triangle(index);
+        let (u, v) = (
+            [b[0] - a[0], b[1] - a[1], b[2] - a[2]],
+            [c[0] - a[0], c[1] - a[1], c[2] - a[2]],
+        );
+        let normal = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let side = |point: [f32; 3]| {
+            (0..3)
+                .map(|axis| normal[axis] * (point[axis] - a[axis]))
+                .sum::<f32>()
+        };
+
+        //Triangles collapsed at the poles don't bound anything
+        normal == [0.0; 3] || side(point) * side(center) > 0.0
+    })
+}
+
+#[test]
+fn build_matches_dense_voxelization() {
+    //Off the voxel grid, so no triangle merely grazes a voxel
+    let center = [8.1, 7.3, 8.6];
+    let mesh = create_sphere_mesh(center, 5.7, 24, 12);
+    let mut voxelizer = MeshVoxelizer::new(4);
+    voxelizer.placement([0.0; 3], 1.0).solid(true);
+
+    let svdag = voxelizer.build(&mesh).unwrap();
+    let absolute = voxelizer
+        .pointer_format(PointerFormat::Absolute32)
+        .build(&mesh)
+        .unwrap();
+    assert_eq!(absolute.pointer_format(), PointerFormat::Absolute32);
+
+    //Every voxel against every triangle, plus its center against the convex hull
+    let mut occupied = 0;
+    for_each_position(&svdag.extract((0, 0, 0), (16, 16, 16)), |position| {
+        let voxel_center = [
+            position.0 as f32 + 0.5,
+            position.1 as f32 + 0.5,
+            position.2 as f32 + 0.5,
+        ];
+        let is_occupied = (0..mesh.triangles.len())
+            .any(|index| touches_voxel(&mesh.triangle(index), position))
+            || is_inside_convex(&mesh, center, voxel_center);
+
+        assert_eq!(
+            svdag.get(position),
+            Some(is_occupied),
+            "voxel {:?}",
+            position
+        );
+        assert_eq!(
+            absolute.get(position),
+            Some(is_occupied),
+            "voxel {:?}",
+            position
+        );
+        occupied += is_occupied as usize;
+    });
+    assert!(occupied > 0 && occupied < 16 * 16 * 16);
+
+    //Fitted to the volume by default
+    let fitted = MeshVoxelizer::new(4)
+        .solid(true)
+        .voxelize(&create_sphere_mesh([-3.0, 40.0, 7.0], 0.25, 24, 12))
+        .unwrap();
+    assert_eq!(fitted.get_dimensions(), (16, 16, 16));
+    assert!(*fitted.get((8, 8, 8)));
+    assert!(!*fitted.get((0, 0, 0)));
+}
+
+#[test]
+fn handles_empty_meshes_and_bad_depths() {
+    let svdag = MeshVoxelizer::new(3).build(&TriangleMesh::new()).unwrap();
+    assert_eq!(svdag.depth(), 3);
+    assert_eq!(svdag.get((4, 4, 4)), Some(false));
+
+    assert!(matches!(
+        MeshVoxelizer::new(0).build(&create_box_mesh(0.0, 1.0)),
+        Err(SvdagError::InvalidDepth(0))
+    ));
+}
@@ -0,0 +1,278 @@
+mod common;
+
+use common::Random;
+use std::collections::HashSet;
+use svdag::{Svdag, SvdagError, SvdagPool, SvdagWorld, WorldPosition};
+
+/// Plain voxel-by-voxel walk over a set of occupied world voxels.
+fn raycast_voxels(
+    voxels: &HashSet<WorldPosition>,
+    origin: [f32; 3],
+    direction: [f32; 3],
+    max_t: f32,
+) -> Option<(WorldPosition, [i32; 3])> {
+    let mut voxel = [0i64; 3];
+    let mut step = [0i64; 3];
+    let mut t_next = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+
+    for axis in 0..3 {
+        voxel[axis] = origin[axis].floor() as i64;
+
+        if direction[axis] > 0.0 {
+            step[axis] = 1;
+            t_next[axis] = ((voxel[axis] + 1) as f32 - origin[axis]) / direction[axis];
+            t_delta[axis] = 1.0 / direction[axis];
+        } else if direction[axis] < 0.0 {
+            step[axis] = -1;
+            t_next[axis] = (voxel[axis] as f32 - origin[axis]) / direction[axis];
+            t_delta[axis] = -1.0 / direction[axis];
+        }
+    }
+
+    let mut normal = [0; 3];
+    loop {
+        let position = (voxel[0], voxel[1], voxel[2]);
+        if voxels.contains(&position) {
+            return Some((position, normal));
+        }
+
+        let axis = (0..3)
+            .min_by(|a, b| t_next[*a].total_cmp(&t_next[*b]))
+            .unwrap();
+        if t_next[axis] > max_t {
+            return None;
+        }
+
+        voxel[axis] += step[axis];
+        t_next[axis] += t_delta[axis];
+        normal = [0; 3];
+        normal[axis] = -step[axis] as i32;
+    }
+}
+
+#[test]
+fn sets_voxels_at_negative_coordinates() {
+    let mut world = SvdagWorld::new(3).unwrap();
+
+    world.set((-1, -1, -1), true).unwrap();
+    world.set((0, 0, 0), true).unwrap();
+    world.set((-9, 17, -100), true).unwrap();
+
+    assert!(world.get((-1, -1, -1)));
+    assert!(world.get((0, 0, 0)));
+    assert!(world.get((-9, 17, -100)));
+    assert!(!world.get((-1, 0, -1)));
+    assert!(!world.get((1_000_000, 0, 0)));
+    assert_eq!(world.chunk_count(), 3);
+
+    assert_eq!(
+        world.split_position((-1, -9, 8)).unwrap(),
+        ((-1, -2, 1), (7, 7, 0))
+    );
+    assert_eq!(
+        world.world_position((-2, 2, -13), (7, 1, 4)),
+        (-9, 17, -100)
+    );
+
+    //Clearing the last voxel of a chunk drops the chunk
+    world.set((-1, -1, -1), false).unwrap();
+    world.set((5, 5, 5), false).unwrap();
+    assert!(!world.get((-1, -1, -1)));
+    assert_eq!(world.chunk_count(), 2);
+}
+
+#[test]
+fn creates_large_chunks_without_sampling() {
+    //Sampling every voxel of a fresh chunk this deep would never finish
+    let mut world = SvdagWorld::new(16).unwrap();
+
+    world.set((70_000, -3, 12), true).unwrap();
+    assert!(world.get((70_000, -3, 12)));
+    assert!(!world.get((70_001, -3, 12)));
+}
+
+#[test]
+fn shares_nodes_between_chunks() {
+    let mut world = SvdagWorld::new(4).unwrap();
+    let ground = Svdag::from_fn(4, |(_, y, _)| y < 5).unwrap();
+
+    for x in -4..4 {
+        for z in -4..4 {
+            world.insert_chunk((x, -1, z), &ground).unwrap();
+        }
+    }
+    let node_count = world.pool().table().len();
+
+    world.insert_chunk((9, -1, 9), &ground).unwrap();
+
+    assert_eq!(world.chunk_count(), 65);
+    assert_eq!(world.pool().table().len(), node_count);
+    assert_eq!(world.chunk((0, -1, 0)), world.chunk((-4, -1, 3)));
+    assert!(world.get((-64, -16 + 4, 63)));
+    assert!(!world.get((-64, -16 + 5, 63)));
+}
+
+#[test]
+fn raycasts_across_chunks() {
+    let mut world = SvdagWorld::new(3).unwrap();
+    let mut voxels = HashSet::new();
+
+    let mut random = Random::new(17);
+    for _ in 0..400 {
+        let position = (
+            (random.next_u64() % 48) as i64 - 24,
+            (random.next_u64() % 48) as i64 - 24,
+            (random.next_u64() % 48) as i64 - 24,
+        );
+
+        world.set(position, true).unwrap();
+        voxels.insert(position);
+    }
+
+    //A wall straddling several chunks
+    for y in -12..12 {
+        for z in -12..12 {
+            world.set((-5, y, z), true).unwrap();
+            voxels.insert((-5, y, z));
+        }
+    }
+
+    let hit = world.raycast([30.0, 3.5, 2.5], [-1.0, 0.0, 0.0], 100.0);
+    let expected = raycast_voxels(&voxels, [30.0, 3.5, 2.5], [-1.0, 0.0, 0.0], 100.0);
+    assert_eq!(hit.map(|hit| (hit.position, hit.normal)), expected);
+
+    for _ in 0..500 {
+        let origin = [
+            random.next_f32() * 80.0 - 40.0,
+            random.next_f32() * 80.0 - 40.0,
+            random.next_f32() * 80.0 - 40.0,
+        ];
+        let direction = [
+            random.next_f32() - 0.5,
+            random.next_f32() - 0.5,
+            random.next_f32() - 0.5,
+        ];
+
+        let hit = world.raycast(origin, direction, 200.0);
+        let expected = raycast_voxels(&voxels, origin, direction, 200.0);
+
+        assert_eq!(
+            hit.map(|hit| (hit.position, hit.normal)),
+            expected,
+            "ray from {:?} along {:?}",
+            origin,
+            direction
+        );
+
+        if let Some(hit) = hit {
+            let (chunk, _) = world.split_position(hit.position).unwrap();
+            assert_eq!(hit.chunk, chunk);
+        }
+    }
+}
+
+#[test]
+fn keeps_bounds_through_edits() {
+    let mut world = SvdagWorld::new(3).unwrap();
+
+    world.set((2, 2, 2), true).unwrap();
+    world.set((-300, 2, 2), true).unwrap();
+    world.set((900, 2, 2), true).unwrap();
+    assert_eq!(
+        world
+            .raycast([-1000.5, 2.5, 2.5], [1.0, 0.0, 0.0], 5000.0)
+            .map(|hit| hit.position),
+        Some((-300, 2, 2))
+    );
+
+    //Dropping the outermost chunks shrinks the bounds, rays still find what's left
+    world.set((-300, 2, 2), false).unwrap();
+    assert!(world.remove_chunk((112, 0, 0)));
+    assert_eq!(
+        world
+            .raycast([-1000.5, 2.5, 2.5], [1.0, 0.0, 0.0], 5000.0)
+            .map(|hit| hit.position),
+        Some((2, 2, 2))
+    );
+    assert_eq!(
+        world.raycast([1000.5, 2.5, 2.5], [1.0, 0.0, 0.0], 5000.0),
+        None
+    );
+
+    world.set((2, 2, 2), false).unwrap();
+    assert_eq!(
+        world.raycast([-1000.5, 2.5, 2.5], [1.0, 0.0, 0.0], 5000.0),
+        None
+    );
+}
+
+#[test]
+fn collects_replaced_nodes() {
+    let mut world = SvdagWorld::new(6).unwrap();
+    world
+        .insert_chunk(
+            (0, 0, 0),
+            &Svdag::from_fn(6, |(x, y, z)| (x ^ y ^ z) % 3 == 0).unwrap(),
+        )
+        .unwrap();
+
+    //Toggling voxels over and over replaces a path per edit, which mustn't pile up
+    let mut random = Random::new(7);
+    let mut largest_node_count = 0;
+    for _ in 0..5000 {
+        let position = (
+            (random.next_u64() % 64) as i64,
+            (random.next_u64() % 64) as i64,
+            (random.next_u64() % 64) as i64,
+        );
+        world.set(position, !world.get(position)).unwrap();
+        largest_node_count = largest_node_count.max(world.pool().node_count());
+    }
+
+    world.collect_garbage();
+    let live_node_count = world.pool().node_count();
+    assert!(largest_node_count <= 2 * live_node_count.max(1024) + 64);
+    assert_eq!(world.collect_garbage(), 0);
+
+    //The collected pool holds exactly what the chunk needs
+    let svdag = world
+        .pool()
+        .to_svdag(world.chunk((0, 0, 0)).unwrap(), None)
+        .unwrap();
+    let mut pool = SvdagPool::new();
+    pool.insert_svdag(&svdag).unwrap();
+    assert_eq!(pool.node_count(), live_node_count);
+}
+
+#[test]
+fn misses_in_empty_worlds() {
+    let world = SvdagWorld::new(4).unwrap();
+
+    assert_eq!(
+        world.raycast([0.5, 0.5, 0.5], [1.0, 0.0, 0.0], 1000.0),
+        None
+    );
+}
+
+#[test]
+fn rejects_invalid_chunks_and_positions() {
+    let mut world = SvdagWorld::new(4).unwrap();
+
+    match world.insert_chunk((0, 0, 0), &Svdag::from_fn(3, |_| true).unwrap()) {
+        Err(SvdagError::InvalidDepth(depth)) => assert_eq!(depth, 3),
+        result => panic!("expected InvalidDepth, got {:?}", result),
+    }
+
+    let position = (i64::MAX, 0, 0);
+    match world.set(position, true) {
+        Err(SvdagError::OutOfWorld {
+            position: error_position,
+        }) => {
+            assert_eq!(error_position, position)
+        }
+        result => panic!("expected OutOfWorld, got {:?}", result),
+    }
+
+    assert!(SvdagWorld::new(0).is_err());
+}